@@ -0,0 +1,426 @@
+//! Subcommands of the `yuv` tool
+
+#![allow(deprecated)]
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::process::ExitCode;
+use std::str::FromStr;
+use yuv::color::{BitDepth, ChromaSamplePosition, ChromaSampling, Colorimetry, Depth, MatrixCoefficients, Range, TransferCharacteristics};
+use yuv::convert::{RGBConvert, YUVConvert};
+use yuv::frame::{YUVFrame, YUVFrameRef};
+use yuv::guess::{self, Context, Guess, Rule, Signalled};
+use yuv::pnm::{self, PnmImage};
+use yuv::raw::{RawFormat, RawReader};
+use yuv::transfer;
+use yuv::y4m::{Y4mHeader, Y4mReader, Y4mWriter};
+use yuv::RGB;
+
+type Result<T, E = Box<dyn std::error::Error>> = std::result::Result<T, E>;
+
+const USAGE: &str = "\
+Usage: yuv <command> [options] <files>
+
+Commands:
+  convert <in.y4m|in.yuv> <out.ppm|out.pfm|out.rgb>  Convert YUV to RGB
+  encode <in.ppm> <out.y4m|out.yuv>                  Convert RGB to YUV
+  stats <in.y4m|in.yuv>                              Colorimetry, and min, max and mean of every plane
+  compare <a.y4m|a.yuv> <b.y4m|b.yuv>                PSNR and the largest difference of every plane
+  list                                               Supported matrices and transfer functions
+
+Options:
+  --size WxH             Size of raw .yuv files
+  --format NAME          Pixel format of raw files, e.g. yuv420p10le or nv12 (default yuv420p)
+  --matrix NAME          Matrix coefficients, e.g. bt709 (guessed if not set)
+  --range tv|pc          Range (guessed if not set)
+  --transfer NAME        Transfer characteristics of the YUV (guessed if not set)
+  --to-transfer NAME     Re-encode RGB with another transfer function, e.g. linear or srgb
+  --frame N              Convert only the frame N (from 0)
+  --sampling 420|422|444|400   Chroma subsampling of encoded files (default 420)
+  --depth 8|10|12|16     Bit depth of encoded files (default 8)
+
+Names are FFmpeg's, e.g. bt709, bt2020nc, smpte2084 or arib-std-b67.
+";
+
+const OPTIONS: [&str; 9] = ["size", "format", "matrix", "range", "transfer", "to-transfer", "frame", "sampling", "depth"];
+
+const MATRICES: [MatrixCoefficients; 13] = [
+    MatrixCoefficients::Identity, MatrixCoefficients::BT709, MatrixCoefficients::FCC, MatrixCoefficients::BT470BG,
+    MatrixCoefficients::BT601, MatrixCoefficients::SMPTE240, MatrixCoefficients::YCgCo, MatrixCoefficients::BT2020NCL,
+    MatrixCoefficients::BT2020CL, MatrixCoefficients::SMPTE2085, MatrixCoefficients::ChromatNCL, MatrixCoefficients::ChromatCL,
+    MatrixCoefficients::ICtCp,
+];
+
+const TRANSFERS: [TransferCharacteristics; 16] = [
+    TransferCharacteristics::BT709, TransferCharacteristics::BT470M, TransferCharacteristics::BT470BG, TransferCharacteristics::BT601,
+    TransferCharacteristics::SMPTE240, TransferCharacteristics::Linear, TransferCharacteristics::Log100, TransferCharacteristics::Log100Sqrt10,
+    TransferCharacteristics::IEC61966, TransferCharacteristics::BT1361, TransferCharacteristics::SRGB, TransferCharacteristics::BT2020_10Bit,
+    TransferCharacteristics::BT2020_12Bit, TransferCharacteristics::SMPTE2084, TransferCharacteristics::SMPTE428, TransferCharacteristics::HLG,
+];
+
+pub fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let command = args.next().unwrap_or_default();
+    let res = Args::parse(args).and_then(|args| match (command.as_str(), args.files.as_slice()) {
+        ("convert", [input, output]) => convert(input, output, &args),
+        ("encode", [input, output]) => encode(input, output, &args),
+        ("stats", [input]) => stats(input, &args),
+        ("compare", [a, b]) => compare(a, b, &args),
+        ("list", []) => list(),
+        ("help" | "--help" | "-h", _) => {
+            io::stdout().lock().write_all(USAGE.as_bytes())?;
+            Ok(())
+        },
+        _ => Err(USAGE.into()),
+    });
+    match res {
+        Ok(()) => ExitCode::SUCCESS,
+        // output piped to e.g. `head`, which has exited
+        Err(err) if err.downcast_ref::<io::Error>().is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        },
+    }
+}
+
+/// File names, and `--name value` or `--name=value` options
+struct Args {
+    files: Vec<String>,
+    options: HashMap<String, String>,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self> {
+        let mut files = Vec::new();
+        let mut options = HashMap::new();
+        while let Some(arg) = args.next() {
+            let Some(name) = arg.strip_prefix("--") else {
+                files.push(arg);
+                continue;
+            };
+            let (name, value) = match name.split_once('=') {
+                Some((name, value)) => (name.to_string(), value.to_string()),
+                None => (name.to_string(), args.next().ok_or_else(|| format!("--{name} needs a value"))?),
+            };
+            if !OPTIONS.contains(&name.as_str()) {
+                return Err(format!("unknown option --{name}").into());
+            }
+            options.insert(name, value);
+        }
+        Ok(Self { files, options })
+    }
+
+    fn get<T: FromStr>(&self, name: &str) -> Result<Option<T>> {
+        self.options.get(name)
+            .map(|v| v.parse().map_err(|_| format!("invalid value of --{name}: '{v}'").into()))
+            .transpose()
+    }
+
+    fn size(&self) -> Result<Option<(usize, usize)>> {
+        let Some(size) = self.options.get("size") else {
+            return Ok(None);
+        };
+        let parsed = size.split_once('x').and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)));
+        parsed.map(Some).ok_or_else(|| format!("invalid value of --size: '{size}', expected WxH").into())
+    }
+
+    /// Signalled values, which override the file's
+    fn signalled(&self) -> Result<Signalled> {
+        Ok(Signalled {
+            matrix: self.get("matrix")?,
+            range: self.get("range")?,
+            primaries: None,
+            transfer: self.get("transfer")?,
+            chroma_position: None,
+        })
+    }
+}
+
+/// Frames of a Y4M or raw file, with what's known about them
+struct Input {
+    frames: Box<dyn Iterator<Item = io::Result<YUVFrame<u16>>>>,
+    width: usize,
+    height: usize,
+    depth: BitDepth,
+    sampling: ChromaSampling,
+    range: Option<Range>,
+    /// Y4M stores chroma position (`None` is centered), and raw files don't
+    chroma_position: Option<Option<ChromaSamplePosition>>,
+}
+
+impl Input {
+    fn open(path: &str, args: &Args) -> Result<Self> {
+        let mut file = BufReader::new(File::open(path).map_err(|e| format!("{path}: {e}"))?);
+        if path.ends_with(".y4m") || file.fill_buf()?.starts_with(b"YUV4MPEG2") {
+            let reader = Y4mReader::new(file).map_err(|e| format!("{path}: {e}"))?;
+            let header = reader.header().clone();
+            return Ok(Self {
+                frames: Box::new(reader),
+                width: header.width,
+                height: header.height,
+                depth: header.depth,
+                sampling: header.sampling,
+                range: header.range,
+                chroma_position: Some(header.chroma_position),
+            });
+        }
+        let (width, height) = args.size()?.ok_or_else(|| format!("{path}: raw files need --size"))?;
+        let format = args.get::<RawFormat>("format")?.unwrap_or(RawFormat::planar(ChromaSampling::Cs420, Depth::Depth8));
+        let reader = RawReader::new(file, width, height, format).map_err(|e| format!("{path}: {e}"))?;
+        Ok(Self {
+            frames: Box::new(reader),
+            width,
+            height,
+            depth: format.depth,
+            sampling: format.sampling,
+            range: None,
+            chroma_position: None,
+        })
+    }
+
+    /// Colorimetry from the options and the file, and guesses for the rest
+    fn guess(&self, args: &Args) -> Result<Guess> {
+        let mut signalled = args.signalled()?;
+        signalled.range = signalled.range.or(self.range);
+        signalled.chroma_position = self.chroma_position.flatten();
+        let mut guess = guess::fill(&signalled, &Context::new(self.width, self.height, self.depth, self.sampling));
+        if let Some(position) = self.chroma_position {
+            guess.colorimetry.chroma_position = position;
+            guess.chroma_position = Rule::Signalled;
+        }
+        Ok(guess)
+    }
+}
+
+fn convert(input_path: &str, output_path: &str, args: &Args) -> Result<()> {
+    let input = Input::open(input_path, args)?;
+    let colorimetry = input.guess(args)?.colorimetry;
+    let to_transfer = args.get::<TransferCharacteristics>("to-transfer")?;
+    let conv = RGBConvert::<u16>::new(colorimetry.range, colorimetry.matrix, input.depth)?;
+    let max = if input.depth.bits() == 8 { 255 } else { 65535 };
+    eprintln!("{input_path}: {}", describe(&colorimetry));
+
+    let extension = output_path.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase()).unwrap_or_default();
+    if !["ppm", "pfm", "rgb"].contains(&extension.as_str()) {
+        return Err(format!("{output_path}: output must be .ppm, .pfm or .rgb").into());
+    }
+    let mut out = BufWriter::new(File::create(output_path).map_err(|e| format!("{output_path}: {e}"))?);
+    let mut rgb = vec![RGB::new(0., 0., 0.); input.width * input.height];
+    let frames: Box<dyn Iterator<Item = _>> = match args.get::<usize>("frame")? {
+        Some(n) => Box::new(input.frames.skip(n).take(1)),
+        None => input.frames,
+    };
+    let mut count = 0;
+    for frame in frames {
+        let mut frame = frame?;
+        frame.chroma_position = colorimetry.chroma_position;
+        frame.as_frame_ref().convert(&conv, &mut rgb, input.width)?;
+        if let Some(to) = to_transfer {
+            for px in &mut rgb {
+                let [r, g, b] = [px.r, px.g, px.b].map(|v| transfer::from_linear(to, transfer::to_linear(colorimetry.transfer, v.into())) as f32);
+                *px = RGB::new(r, g, b);
+            }
+        }
+        match extension.as_str() {
+            "ppm" => pnm::write_ppm(&mut out, input.width, input.height, &rgb, input.width, max)?,
+            "pfm" => pnm::write_pfm(&mut out, input.width, input.height, &rgb, input.width)?,
+            _ => write_rgb(&mut out, &rgb, max)?,
+        }
+        count += 1;
+    }
+    out.flush()?;
+    eprintln!("{output_path}: {count} frames");
+    Ok(())
+}
+
+/// Interleaved RGB quantized to 0..=`max`, as bytes if it's 255, or little-endian `u16`
+fn write_rgb(out: &mut impl Write, rgb: &[RGB<f32>], max: u16) -> io::Result<()> {
+    let samples = rgb.iter().flat_map(|px| [px.r, px.g, px.b]).map(|v| (v.clamp(0., 1.) * f32::from(max)).round() as u16);
+    let bytes: Vec<u8> = if max <= 255 {
+        samples.map(|v| v as u8).collect()
+    } else {
+        samples.flat_map(u16::to_le_bytes).collect()
+    };
+    out.write_all(&bytes)
+}
+
+fn encode(input_path: &str, output_path: &str, args: &Args) -> Result<()> {
+    let mut input = BufReader::new(File::open(input_path).map_err(|e| format!("{input_path}: {e}"))?);
+    let sampling = args.get("sampling")?.unwrap_or(ChromaSampling::Cs420);
+    let depth = args.get("depth")?.unwrap_or(Depth::Depth8);
+
+    let mut output: Option<(Output, usize, usize, Colorimetry)> = None;
+    let mut count = 0;
+    while let Some(PnmImage { width, height, max, pixels }) = pnm::read_ppm(&mut input).map_err(|e| format!("{input_path}: {e}"))? {
+        let rgb_depth = match max {
+            255 => Depth::Depth8,
+            1023 => Depth::Depth10,
+            4095 => Depth::Depth12,
+            65535 => Depth::Depth16,
+            _ => return Err(format!("{input_path}: unsupported PPM maxval {max}").into()),
+        };
+        let (out, _, _, colorimetry) = match &mut output {
+            Some(output) if (output.1, output.2) == (width, height) => output,
+            Some(_) => return Err(format!("{input_path}: all images must have the same size").into()),
+            None => {
+                let colorimetry = guess::fill(&args.signalled()?, &Context::new(width, height, depth, sampling)).colorimetry;
+                eprintln!("{output_path}: {}", describe(&colorimetry));
+                output.insert((Output::create(output_path, width, height, sampling, depth, colorimetry.range)?, width, height, colorimetry))
+            },
+        };
+
+        let frame = YUVFrame::from_rgb(width, height, &pixels, rgb_depth, (colorimetry.range, colorimetry.matrix, depth), sampling)?;
+        out.write(&frame.as_frame_ref())?;
+        count += 1;
+    }
+    if let Some((out, ..)) = output {
+        out.finish()?;
+    }
+    eprintln!("{output_path}: {count} frames");
+    Ok(())
+}
+
+/// Encoded frames written to a `.y4m` file, or planar little-endian `.yuv`
+enum Output {
+    Y4m(Y4mWriter<BufWriter<File>>, Depth),
+    Raw(BufWriter<File>, Depth),
+}
+
+impl Output {
+    fn create(path: &str, width: usize, height: usize, sampling: ChromaSampling, depth: Depth, range: Range) -> Result<Self> {
+        let file = BufWriter::new(File::create(path).map_err(|e| format!("{path}: {e}"))?);
+        if path.ends_with(".y4m") {
+            let header = Y4mHeader { range: Some(range), ..Y4mHeader::new(width, height, sampling, depth) };
+            return Ok(Self::Y4m(Y4mWriter::new(file, header)?, depth));
+        }
+        Ok(Self::Raw(file, depth))
+    }
+
+    fn write(&mut self, frame: &YUVFrameRef<'_, u16>) -> Result<()> {
+        match self {
+            Self::Y4m(writer, Depth::Depth8) => {
+                let [y, u, v] = [frame.y, frame.u, frame.v].map(|plane| plane.iter().map(|&v| v as u8).collect::<Vec<_>>());
+                writer.write_frame(&YUVFrameRef::new(frame.width, frame.height, &y, &u, &v, frame.sampling)?)?;
+            },
+            Self::Y4m(writer, _) => writer.write_frame(frame)?,
+            Self::Raw(file, depth) => {
+                let samples = frame.y.iter().chain(frame.u).chain(frame.v);
+                let bytes: Vec<u8> = if *depth == Depth::Depth8 {
+                    samples.map(|&v| v as u8).collect()
+                } else {
+                    samples.flat_map(|v| v.to_le_bytes()).collect()
+                };
+                file.write_all(&bytes)?;
+            },
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Self::Y4m(writer, _) => writer.into_inner().flush(),
+            Self::Raw(mut file, _) => file.flush(),
+        }
+    }
+}
+
+fn stats(path: &str, args: &Args) -> Result<()> {
+    let mut out = io::stdout().lock();
+    let input = Input::open(path, args)?;
+    let guess = input.guess(args)?;
+    writeln!(out, "{path}: {}x{} {} {}-bit", input.width, input.height, input.sampling, input.depth.bits())?;
+    let c = &guess.colorimetry;
+    writeln!(out, "  matrix: {} ({:?})", c.matrix, guess.matrix)?;
+    writeln!(out, "  range: {} ({:?})", c.range, guess.range)?;
+    writeln!(out, "  primaries: {} ({:?})", c.primaries, guess.primaries)?;
+    writeln!(out, "  transfer: {} ({:?})", c.transfer, guess.transfer)?;
+    writeln!(out, "  chroma position: {} ({:?})", c.chroma_position.map_or("center", |p| p.name()), guess.chroma_position)?;
+
+    let mut total = [(u16::MAX, 0, 0., 0); 3];
+    for (i, frame) in input.frames.enumerate() {
+        let frame = frame?;
+        write!(out, "frame {i}:")?;
+        for ((name, plane), total) in ["Y", "U", "V"].into_iter().zip([&frame.y, &frame.u, &frame.v]).zip(&mut total) {
+            if plane.is_empty() {
+                continue;
+            }
+            let (min, max, sum) = plane.iter().fold((u16::MAX, 0, 0.), |(min, max, sum), &v| (min.min(v), max.max(v), sum + f64::from(v)));
+            write!(out, " {name} {min}..{max} mean {:.2}", sum / plane.len() as f64)?;
+            *total = (total.0.min(min), total.1.max(max), total.2 + sum, total.3 + plane.len());
+        }
+        writeln!(out)?;
+    }
+    write!(out, "all frames:")?;
+    for (name, (min, max, sum, len)) in ["Y", "U", "V"].into_iter().zip(total) {
+        if len > 0 {
+            write!(out, " {name} {min}..{max} mean {:.2}", sum / len as f64)?;
+        }
+    }
+    writeln!(out)?;
+    Ok(())
+}
+
+fn compare(a_path: &str, b_path: &str, args: &Args) -> Result<()> {
+    let mut out = io::stdout().lock();
+    let (a, b) = (Input::open(a_path, args)?, Input::open(b_path, args)?);
+    if (a.width, a.height, a.sampling, a.depth) != (b.width, b.height, b.sampling, b.depth) {
+        return Err(format!("{a_path} is {}x{} {} {}-bit, but {b_path} is {}x{} {} {}-bit",
+            a.width, a.height, a.sampling, a.depth.bits(), b.width, b.height, b.sampling, b.depth.bits()).into());
+    }
+    let max = f64::from(a.depth.max_value());
+    let psnr = |sse: f64, len: usize| if sse == 0. { f64::INFINITY } else { 10. * (max * max * len as f64 / sse).log10() };
+
+    let mut total = [(0., 0, 0); 3];
+    let mut count = 0;
+    let (mut frames_a, mut frames_b) = (a.frames, b.frames);
+    loop {
+        let (fa, fb) = match (frames_a.next(), frames_b.next()) {
+            (Some(fa), Some(fb)) => (fa?, fb?),
+            (None, None) => break,
+            (Some(_), None) => return Err(format!("{b_path} has only {count} frames, and {a_path} has more").into()),
+            (None, Some(_)) => return Err(format!("{a_path} has only {count} frames, and {b_path} has more").into()),
+        };
+        write!(out, "frame {count}:")?;
+        for ((name, pa, pb), total) in [("Y", &fa.y, &fb.y), ("U", &fa.u, &fb.u), ("V", &fa.v, &fb.v)].into_iter().zip(&mut total) {
+            if pa.is_empty() {
+                continue;
+            }
+            let (sse, max_diff) = pa.iter().zip(pb).fold((0., 0), |(sse, max_diff), (&a, &b)| {
+                let d = a.abs_diff(b);
+                (sse + f64::from(d) * f64::from(d), max_diff.max(d))
+            });
+            write!(out, " {name} {:.2} dB (max diff {max_diff})", psnr(sse, pa.len()))?;
+            *total = (total.0 + sse, total.1.max(max_diff), total.2 + pa.len());
+        }
+        writeln!(out)?;
+        count += 1;
+    }
+    write!(out, "{count} frames:")?;
+    for (name, (sse, max_diff, len)) in ["Y", "U", "V"].into_iter().zip(total) {
+        if len > 0 {
+            write!(out, " {name} {:.2} dB (max diff {max_diff})", psnr(sse, len))?;
+        }
+    }
+    writeln!(out)?;
+    Ok(())
+}
+
+/// Matrices that `convert` and `encode` support, as decided by the CPU converters, and transfer functions that `--transfer` and `--to-transfer` accept
+fn list() -> Result<()> {
+    let mut out = io::stdout().lock();
+    let yes_no = |ok: bool| if ok { "yes" } else { "no" };
+    writeln!(out, "{:<18} {:<8} encode", "matrix", "convert")?;
+    for matrix in MATRICES {
+        let to_rgb = RGBConvert::<u16>::new(Range::Limited, matrix, Depth::Depth10).is_ok();
+        let from_rgb = YUVConvert::<u16, u16>::new((Range::Full, MatrixCoefficients::Identity, Depth::Depth10), (Range::Limited, matrix, Depth::Depth10)).is_ok();
+        writeln!(out, "{:<18} {:<8} {}", matrix.name(), yes_no(to_rgb), yes_no(from_rgb))?;
+    }
+    writeln!(out, "transfers: {}", TRANSFERS.map(|tc| tc.name()).join(" "))?;
+    Ok(())
+}
+
+fn describe(c: &Colorimetry) -> String {
+    format!("matrix {}, range {}, primaries {}, transfer {}", c.matrix, c.range, c.primaries, c.transfer)
+}
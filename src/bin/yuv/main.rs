@@ -0,0 +1,15 @@
+//! Command-line tool for inspecting and converting YUV files. Run `yuv help` for usage.
+
+#[cfg(not(feature = "no_std"))]
+mod cli;
+
+#[cfg(not(feature = "no_std"))]
+fn main() -> std::process::ExitCode {
+    cli::main()
+}
+
+#[cfg(feature = "no_std")]
+fn main() {
+    eprintln!("yuv has been built with the deprecated no_std feature, which disables the command-line tool");
+    std::process::exit(1);
+}
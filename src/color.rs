@@ -12,6 +12,8 @@ pub enum ChromaSampling {
 }
 
 /// Range of allowed values for pixels
+///
+/// For other black and white levels, see [`RGBConvert::from_levels`](crate::convert::RGBConvert::from_levels).
 #[repr(C)]
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum Range {
@@ -101,6 +103,42 @@ pub enum Depth {
     Depth16 = 16,
 }
 
+/// Any bit depth from 8 to 16, e.g. 9 or 14 bits. [`Depth`] converts to it.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, PartialOrd, Ord, Hash)]
+pub struct BitDepth(u8);
+
+impl BitDepth {
+    /// Fails if `bits` isn't in `8..=16`
+    pub fn new(bits: u8) -> Result<Self, crate::Error> {
+        if (8..=16).contains(&bits) {
+            Ok(Self(bits))
+        } else {
+            Err(crate::Error::InvalidDepthRequested)
+        }
+    }
+
+    /// Number of bits per sample
+    #[inline]
+    #[must_use]
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// Largest value that fits in this many bits
+    #[inline]
+    #[must_use]
+    pub const fn max_value(self) -> u16 {
+        ((1u32 << self.0) - 1) as u16
+    }
+}
+
+impl From<Depth> for BitDepth {
+    #[inline]
+    fn from(depth: Depth) -> Self {
+        Self(depth as u8)
+    }
+}
+
 /// As defined by the “Matrix coefficients” section of ISO/IEC 23091-4/ITU-TH.273.
 #[repr(C)]
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
@@ -149,3 +187,14 @@ pub enum ChromaSamplePosition {
     /// Co-located with (0, 0) luma sample.
     Colocated,
 }
+
+/// All color characteristics of an image together
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Colorimetry {
+    pub matrix: MatrixCoefficients,
+    pub range: Range,
+    pub primaries: ColorPrimaries,
+    pub transfer: TransferCharacteristics,
+    /// `None` when chroma isn't subsampled (or for RGB)
+    pub chroma_position: Option<ChromaSamplePosition>,
+}
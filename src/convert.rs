@@ -1,9 +1,16 @@
 //! YUV -> RGB converter. See [`RGBConvert::new`]
-use crate::color::{Depth, MatrixCoefficients, Range};
+use crate::color::{BitDepth, MatrixCoefficients, Range};
 use crate::depth;
+use crate::dither::Dither;
+use crate::levels::Levels;
 use crate::range;
+use crate::simd;
 use crate::Error;
 use crate::YUV;
+pub use crate::fixed::FixedMatrix;
+pub use crate::transcode::{LevelConvert, YUVConvert};
+#[cfg(not(any(not(feature = "std"), feature = "no_std")))]
+pub use crate::lut::Lut;
 use rgb::Rgb;
 use rgb::prelude::*;
 use core::marker::PhantomData;
@@ -14,6 +21,236 @@ pub trait ToRGB<F = u8, T = u8> where T: Copy, F: Copy {
     fn to_rgb(&self, px: YUV<F>) -> Rgb<T>;
     /// Ignore UV channels, and just convert Y
     fn to_luma(&self, y: F) -> T;
+
+    /// Convert YUV to RGB, knowing position of the pixel in the image.
+    ///
+    /// Position is used only by converters that use ordered dithering (see [`Dither`]). By default it's the same as [`ToRGB::to_rgb`].
+    #[inline(always)]
+    fn to_rgb_at(&self, px: YUV<F>, _x: usize, _y: usize) -> Rgb<T> {
+        self.to_rgb(px)
+    }
+
+    /// Convert a row of planar pixels. All slices must have the same length, or it panics.
+    ///
+    /// The default implementation calls [`ToRGB::to_rgb`] for each pixel. Converters can override it with faster code.
+    #[inline]
+    fn to_rgb_row(&self, y: &[F], u: &[F], v: &[F], out: &mut [Rgb<T>]) {
+        assert!(y.len() == out.len() && u.len() == out.len() && v.len() == out.len(), "row lengths differ");
+        for (out, ((&y, &u), &v)) in out.iter_mut().zip(y.iter().zip(u).zip(v)) {
+            *out = self.to_rgb(YUV { y, u, v });
+        }
+    }
+
+    /// Convert a row of planar pixels that starts at position `x` in the `row` of the image. All slices must have the same length, or it panics.
+    ///
+    /// Frame conversions use it, so that converters with ordered dithering (see [`Dither`]) know where the pixels are. By default it's the same as [`ToRGB::to_rgb_row`].
+    #[inline]
+    fn to_rgb_row_at(&self, y: &[F], u: &[F], v: &[F], out: &mut [Rgb<T>], _x: usize, _row: usize) {
+        self.to_rgb_row(y, u, v, out);
+    }
+
+    /// Convert a slice of interleaved pixels. Both slices must have the same length, or it panics.
+    #[inline]
+    fn to_rgb_slice(&self, px: &[YUV<F>], out: &mut [Rgb<T>]) {
+        assert_eq!(px.len(), out.len());
+        for (out, &px) in out.iter_mut().zip(px) {
+            *out = self.to_rgb(px);
+        }
+    }
+
+    /// Convert a row of Y values to grayscale. Both slices must have the same length, or it panics.
+    #[inline]
+    fn to_luma_row(&self, y: &[F], out: &mut [T]) {
+        assert_eq!(y.len(), out.len());
+        for (out, &y) in out.iter_mut().zip(y) {
+            *out = self.to_luma(y);
+        }
+    }
+}
+
+/// Numeric types of YUV components supported by the converters: `u8`, `u16`, `f32`, and `f16` with the `half` feature
+pub trait Sample: Copy + Into<f32> + sealed::Sealed {
+    /// Largest bit depth of integer code values, 0 for floats
+    #[doc(hidden)]
+    const MAX_DEPTH: u8;
+
+    #[doc(hidden)]
+    #[inline(always)]
+    fn as_u8(_row: &[Self]) -> Option<&[u8]> {
+        None
+    }
+
+    #[doc(hidden)]
+    #[inline(always)]
+    fn as_u16(_row: &[Self]) -> Option<&[u16]> {
+        None
+    }
+
+    /// Weighted average. Weights add up to 16.
+    #[doc(hidden)]
+    fn blend(samples: [Self; 4], weights: [u8; 4]) -> Self;
+
+    /// Position in a lookup table
+    #[doc(hidden)]
+    fn index(self) -> usize;
+
+    /// From an integer already clamped to the output range
+    #[doc(hidden)]
+    fn from_code(code: i32) -> Self;
+
+    /// Returns number of pixels copied
+    #[doc(hidden)]
+    #[inline(always)]
+    fn copy_gbr_row(_y: &[Self], _u: &[Self], _v: &[Self], _out: &mut [Rgb<Self>]) -> usize {
+        0
+    }
+}
+
+mod sealed {
+    pub trait Sealed {}
+    impl Sealed for u8 {}
+    impl Sealed for u16 {}
+    impl Sealed for f32 {}
+    #[cfg(feature = "half")]
+    impl Sealed for half::f16 {}
+}
+
+impl Sample for u8 {
+    const MAX_DEPTH: u8 = 8;
+
+    #[inline(always)]
+    fn as_u8(row: &[Self]) -> Option<&[u8]> {
+        Some(row)
+    }
+
+    #[inline(always)]
+    fn blend(samples: [Self; 4], weights: [u8; 4]) -> Self {
+        let sum: u16 = samples.iter().zip(weights).map(|(&s, w)| u16::from(s) * u16::from(w)).sum();
+        ((sum + 8) >> 4) as u8
+    }
+
+    #[inline(always)]
+    fn index(self) -> usize {
+        self.into()
+    }
+
+    #[inline(always)]
+    fn from_code(code: i32) -> Self {
+        code as u8
+    }
+
+    #[inline]
+    fn copy_gbr_row(y: &[Self], u: &[Self], v: &[Self], out: &mut [Rgb<Self>]) -> usize {
+        simd::copy_gbr_u8(y, u, v, out)
+    }
+}
+
+impl Sample for u16 {
+    const MAX_DEPTH: u8 = 16;
+
+    #[inline(always)]
+    fn as_u16(row: &[Self]) -> Option<&[u16]> {
+        Some(row)
+    }
+
+    #[inline(always)]
+    fn blend(samples: [Self; 4], weights: [u8; 4]) -> Self {
+        let sum: u32 = samples.iter().zip(weights).map(|(&s, w)| u32::from(s) * u32::from(w)).sum();
+        ((sum + 8) >> 4) as u16
+    }
+
+    #[inline(always)]
+    fn index(self) -> usize {
+        self.into()
+    }
+
+    #[inline(always)]
+    fn from_code(code: i32) -> Self {
+        code as u16
+    }
+
+    #[inline]
+    fn copy_gbr_row(y: &[Self], u: &[Self], v: &[Self], out: &mut [Rgb<Self>]) -> usize {
+        simd::copy_gbr_u16(y, u, v, out)
+    }
+}
+
+impl Sample for f32 {
+    const MAX_DEPTH: u8 = 0;
+
+    #[inline(always)]
+    fn blend(samples: [Self; 4], weights: [u8; 4]) -> Self {
+        samples.iter().zip(weights).map(|(&s, w)| s * f32::from(w)).sum::<f32>() / 16.
+    }
+
+    #[inline(always)]
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    #[inline(always)]
+    fn from_code(code: i32) -> Self {
+        code as f32
+    }
+}
+
+#[cfg(feature = "half")]
+impl Sample for half::f16 {
+    const MAX_DEPTH: u8 = 0;
+
+    #[inline(always)]
+    fn blend(samples: [Self; 4], weights: [u8; 4]) -> Self {
+        half::f16::from_f32(f32::blend(samples.map(half::f16::to_f32), weights))
+    }
+
+    #[inline(always)]
+    fn index(self) -> usize {
+        self.to_f32() as usize
+    }
+
+    #[inline(always)]
+    fn from_code(code: i32) -> Self {
+        half::f16::from_f32(code as f32)
+    }
+}
+
+/// Converts the part of the row that the vectorized code has left
+#[inline]
+fn convert_row_tail<F: Copy, T: Copy>(conv: &impl ToRGB<F, T>, done: usize, y: &[F], u: &[F], v: &[F], out: &mut [Rgb<T>]) {
+    for (out, ((&y, &u), &v)) in out[done..].iter_mut().zip(y[done..].iter().zip(&u[done..]).zip(&v[done..])) {
+        *out = conv.to_rgb(YUV { y, u, v });
+    }
+}
+
+/// How float results are turned into integers
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum Rounding {
+    /// Round down. Fastest, but on average 0.5 LSB too dark (the old behavior).
+    #[default]
+    Truncate,
+    /// Round to the nearest integer, same as libavif and FFmpeg
+    Nearest,
+}
+
+/// What to do with YUV colors that don't fit in the RGB gamut
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum Clamp {
+    /// Clamp each channel independently (the old behavior). May shift hue of saturated colors.
+    #[default]
+    PerChannel,
+    /// Desaturate towards the luma until the color fits. Preserves hue and luma.
+    HuePreserving,
+    /// Keep values outside of the nominal range. Only float output can represent them, integer outputs saturate.
+    None,
+}
+
+/// Rounding and clipping behavior of converters. See [`RGBConvert::with_policy`]
+///
+/// The default is the same as in older versions of this crate. To match other decoders, use [`Rounding::Nearest`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub struct Policy {
+    pub rounding: Rounding,
+    pub clamp: Clamp,
 }
 
 /// Enum containing concrete type of converter used.
@@ -32,7 +269,7 @@ pub enum RGBConvert<T = u8> {
     IdentityScale(IdentityScale<T>),
 }
 
-fn coeffs_for_matrix(matrix_coeffs: MatrixCoefficients) -> Option<(f64, f64)> {
+pub(crate) fn coeffs_for_matrix(matrix_coeffs: MatrixCoefficients) -> Option<(f64, f64)> {
     Some(match matrix_coeffs {
         MatrixCoefficients::BT709 => (0.2126, 0.0722),
         MatrixCoefficients::FCC => (0.30, 0.11),
@@ -61,25 +298,91 @@ impl RGBConvert<u8> {
         }
         Err(Error::UnsupportedMatrixCoefficients)
     }
+
+    /// Like [`RGBConvert::new`], but with explicit black, white and chroma levels instead of a [`Range`].
+    ///
+    /// Colors outside of the levels are clamped, unless float output is used with [`Clamp::None`],
+    /// which keeps them below 0 and above 1 (e.g. for xvYCC or super-whites).
+    pub fn from_levels(levels: Levels, matrix: MatrixCoefficients) -> Result<Self, Error> {
+        let l = levels.check(255)?;
+        if let Some((kr, kb)) = coeffs_for_matrix(matrix) {
+            return Ok(Self::Matrix(Matrix::<u8>::from_levels(kr, kb, l)));
+        }
+        if matrix == MatrixCoefficients::Identity {
+            if levels == Levels::full(8) {
+                return Ok(Self::Copy(CopyGBR(PhantomData)));
+            }
+            return Ok(Self::IdentityScale(IdentityScale { min: l[0] as u8, range: (l[1] - l[0]) as u8, rounding: Rounding::Truncate }));
+        }
+        Err(Error::UnsupportedMatrixCoefficients)
+    }
 }
 
 impl RGBConvert<u16> {
     /// Use `RGBConvert::<u16>::new()` to call this method, because there's also a `u8` version
-    pub fn new(range: Range, matrix: MatrixCoefficients, depth: Depth) -> Result<Self, Error> {
+    pub fn new(range: Range, matrix: MatrixCoefficients, depth: impl Into<BitDepth>) -> Result<Self, Error> {
+        let depth = depth.into();
         if let Some((kr, kb)) = coeffs_for_matrix(matrix) {
             return Ok(Self::Matrix(Matrix::<u16>::new(kr, kb, range, depth)));
         }
         if matrix == MatrixCoefficients::Identity {
-            return Ok(match (range, depth) {
-                (Range::Full, Depth::Depth16) => Self::Copy(CopyGBR(PhantomData)),
+            return Ok(match (range, depth.bits()) {
+                (Range::Full, 16) => Self::Copy(CopyGBR(PhantomData)),
                 _ => Self::IdentityScale(IdentityScale::<u16>::new(range, depth)?),
             });
         }
         Err(Error::UnsupportedMatrixCoefficients)
     }
+
+    /// Like [`RGBConvert::new`], but with explicit black, white and chroma levels instead of a [`Range`] and depth.
+    ///
+    /// Colors outside of the levels are clamped, unless float output is used with [`Clamp::None`],
+    /// which keeps them below 0 and above 1 (e.g. for xvYCC or super-whites).
+    pub fn from_levels(levels: Levels, matrix: MatrixCoefficients) -> Result<Self, Error> {
+        let l = levels.check(u16::MAX)?;
+        if let Some((kr, kb)) = coeffs_for_matrix(matrix) {
+            return Ok(Self::Matrix(Matrix::<u16>::from_levels(kr, kb, l)));
+        }
+        if matrix == MatrixCoefficients::Identity {
+            if levels == Levels::full(16) {
+                return Ok(Self::Copy(CopyGBR(PhantomData)));
+            }
+            return Ok(Self::IdentityScale(IdentityScale { min: l[0], range: l[1] - l[0], rounding: Rounding::Truncate }));
+        }
+        Err(Error::UnsupportedMatrixCoefficients)
+    }
 }
 
-impl<T: Copy> RGBConvert<T> where Matrix<T>: ToRGB<T, T>, IdentityScale<T>: ToRGB<T, T> {
+#[cfg(not(any(not(feature = "std"), feature = "no_std")))]
+impl<T: Sample> RGBConvert<T> {
+    /// Coefficients for RGB in `0..=1`, whether the output is clamped, and the transfer function conversion
+    pub(crate) fn linear_parts(&self) -> (Coefficients, bool, Option<(crate::color::TransferCharacteristics, crate::color::TransferCharacteristics)>) {
+        // GBR planes scaled by `1/range`
+        let gbr = |min: f32, range: f32| Coefficients {
+            matrix: [[0., 0., 1. / range], [1. / range, 0., 0.], [0., 1. / range, 0.]],
+            offset: [-min / range; 3],
+        };
+        match self {
+            Self::Matrix(m) => (m.coefficients(), m.policy().clamp != Clamp::None, None),
+            Self::Copy(_) => (gbr(0., if T::MAX_DEPTH == 0 { 1. } else { ((1u32 << T::MAX_DEPTH) - 1) as f32 }), true, None),
+            Self::IdentityScale(s) => (gbr(s.min.into(), s.range.into()), false, None),
+        }
+    }
+}
+
+impl<T: Sample> RGBConvert<T> {
+    /// Dithering mode that [`RGBConvert::to_rgb_at`] will actually use. It's [`Dither::None`] when [`RGBConvert::with_dither`] doesn't apply.
+    #[inline]
+    #[must_use]
+    pub fn dither(&self) -> Dither {
+        match self {
+            Self::Matrix(m) if T::MAX_DEPTH == 8 => m.dither(),
+            _ => Dither::None,
+        }
+    }
+}
+
+impl<T: Sample> RGBConvert<T> where Matrix<T>: ToRGB<T, T>, IdentityScale<T>: ToRGB<T, T>, CopyGBR<T>: ToRGB<T, T> {
     /// Convert a single YUV pixel to an RGB pixel.
     ///
     /// This method has a `match` internally, which may or may not be the fastest way to do this (dependin on optimizer).
@@ -93,6 +396,16 @@ impl<T: Copy> RGBConvert<T> where Matrix<T>: ToRGB<T, T>, IdentityScale<T>: ToRG
         }
     }
 
+    /// Convert a single YUV pixel to an RGB pixel, applying dithering if it's been enabled with [`RGBConvert::with_dither`].
+    #[inline(always)]
+    pub fn to_rgb_at(&self, px: YUV<T>, x: usize, y: usize) -> Rgb<T> {
+        match self {
+            Self::Matrix(c) => c.to_rgb_at(px, x, y),
+            Self::Copy(c) => c.to_rgb(px),
+            Self::IdentityScale(c) => c.to_rgb(px),
+        }
+    }
+
     /// Convert a single Y (Luma) value to a grayscale value.
     #[inline(always)]
     pub fn to_luma(&self, px: T) -> T {
@@ -102,9 +415,79 @@ impl<T: Copy> RGBConvert<T> where Matrix<T>: ToRGB<T, T>, IdentityScale<T>: ToRG
             Self::IdentityScale(c) => c.to_luma(px),
         }
     }
+
+    /// Convert a row of planar pixels. All slices must have the same length, or it panics.
+    ///
+    /// Unlike [`RGBConvert::to_rgb`], this checks the type of the converter only once per row.
+    #[inline]
+    pub fn to_rgb_row(&self, y: &[T], u: &[T], v: &[T], out: &mut [Rgb<T>]) {
+        match self {
+            Self::Matrix(c) => c.to_rgb_row(y, u, v, out),
+            Self::Copy(c) => c.to_rgb_row(y, u, v, out),
+            Self::IdentityScale(c) => c.to_rgb_row(y, u, v, out),
+        }
+    }
+
+    /// Convert a row of planar pixels starting at position `x`, `row`, applying dithering if it's been enabled with [`RGBConvert::with_dither`].
+    #[inline]
+    pub fn to_rgb_row_at(&self, y: &[T], u: &[T], v: &[T], out: &mut [Rgb<T>], x: usize, row: usize) {
+        match self {
+            Self::Matrix(c) => c.to_rgb_row_at(y, u, v, out, x, row),
+            Self::Copy(c) => c.to_rgb_row(y, u, v, out),
+            Self::IdentityScale(c) => c.to_rgb_row(y, u, v, out),
+        }
+    }
+
+    /// Convert a slice of interleaved pixels. Both slices must have the same length, or it panics.
+    #[inline]
+    pub fn to_rgb_slice(&self, px: &[YUV<T>], out: &mut [Rgb<T>]) {
+        match self {
+            Self::Matrix(c) => c.to_rgb_slice(px, out),
+            Self::Copy(c) => c.to_rgb_slice(px, out),
+            Self::IdentityScale(c) => c.to_rgb_slice(px, out),
+        }
+    }
+
+    /// Convert a row of Y values to grayscale. Both slices must have the same length, or it panics.
+    #[inline]
+    pub fn to_luma_row(&self, y: &[T], out: &mut [T]) {
+        match self {
+            Self::Matrix(c) => c.to_luma_row(y, out),
+            Self::Copy(c) => c.to_luma_row(y, out),
+            Self::IdentityScale(c) => c.to_luma_row(y, out),
+        }
+    }
+}
+
+impl<T: Copy> RGBConvert<T> {
+    /// Set dithering mode used by [`RGBConvert::to_rgb_at`], [`RGBConvert::to_rgb_row_at`], and conversions of color frames like [`YUVFrameRef::convert`](crate::frame::YUVFrameRef::convert).
+    ///
+    /// It only applies to 8-bit (`u8`) RGB output of the `Matrix` variant. Other variants are lossless or have integer output as precise as the input,
+    /// and `u16` and float outputs aren't dithered, so for them this does nothing. Check [`RGBConvert::dither`] to see if it applies.
+    ///
+    /// `RGBConvert` keeps the bit depth, so to dither e.g. 10-bit YUV to 8-bit RGB, convert with the `Matrix` variant of `RGBConvert<u16>`, which implements `ToRGB<u16, u8>`.
+    #[inline]
+    #[must_use]
+    pub fn with_dither(self, dither: Dither) -> Self {
+        match self {
+            Self::Matrix(m) => Self::Matrix(m.with_dither(dither)),
+            other => other,
+        }
+    }
+
+    /// Set rounding and clipping behavior. Copying is lossless, so the `Copy` variant ignores it.
+    #[inline]
+    #[must_use]
+    pub fn with_policy(self, policy: Policy) -> Self {
+        match self {
+            Self::Matrix(m) => Self::Matrix(m.with_policy(policy)),
+            Self::IdentityScale(s) => Self::IdentityScale(s.with_rounding(policy.rounding)),
+            other @ Self::Copy(_) => other,
+        }
+    }
 }
 
-impl<T: Copy> ToRGB<T,T> for RGBConvert<T> where Matrix<T>: ToRGB<T, T>, IdentityScale<T>: ToRGB<T, T> {
+impl<T: Sample> ToRGB<T,T> for RGBConvert<T> where Matrix<T>: ToRGB<T, T>, IdentityScale<T>: ToRGB<T, T>, CopyGBR<T>: ToRGB<T, T> {
     /// Convert a single YUV pixel to an RGB pixel.
     ///
     /// This method has a `match` internally, which may or may not be the fastest way to do this (dependin on optimizer).
@@ -119,13 +502,38 @@ impl<T: Copy> ToRGB<T,T> for RGBConvert<T> where Matrix<T>: ToRGB<T, T>, Identit
     fn to_luma(&self, y: T) -> T {
         Self::to_luma(self, y)
     }
+
+    #[inline(always)]
+    fn to_rgb_at(&self, px: YUV<T>, x: usize, y: usize) -> Rgb<T> {
+        Self::to_rgb_at(self, px, x, y)
+    }
+
+    #[inline(always)]
+    fn to_rgb_row(&self, y: &[T], u: &[T], v: &[T], out: &mut [Rgb<T>]) {
+        Self::to_rgb_row(self, y, u, v, out);
+    }
+
+    #[inline(always)]
+    fn to_rgb_row_at(&self, y: &[T], u: &[T], v: &[T], out: &mut [Rgb<T>], x: usize, row: usize) {
+        Self::to_rgb_row_at(self, y, u, v, out, x, row);
+    }
+
+    #[inline(always)]
+    fn to_rgb_slice(&self, px: &[YUV<T>], out: &mut [Rgb<T>]) {
+        Self::to_rgb_slice(self, px, out);
+    }
+
+    #[inline(always)]
+    fn to_luma_row(&self, y: &[T], out: &mut [T]) {
+        Self::to_luma_row(self, y, out);
+    }
 }
 
 /// Fast path when no conversion needed for YUV -> GBR
 #[derive(Debug, Copy, Clone)]
 pub struct CopyGBR<T = u8>(PhantomData<T>);
 
-impl<T: Copy> ToRGB<T, T> for CopyGBR<T> {
+impl<T: Sample> ToRGB<T, T> for CopyGBR<T> {
     #[inline(always)]
     fn to_rgb(&self, px: YUV<T>) -> Rgb<T> {
         Rgb { r: px.v, g: px.y, b: px.u }
@@ -135,6 +543,13 @@ impl<T: Copy> ToRGB<T, T> for CopyGBR<T> {
     fn to_luma(&self, y: T) -> T {
         y
     }
+
+    #[inline]
+    fn to_rgb_row(&self, y: &[T], u: &[T], v: &[T], out: &mut [Rgb<T>]) {
+        assert!(y.len() == out.len() && u.len() == out.len() && v.len() == out.len(), "row lengths differ");
+        let done = T::copy_gbr_row(y, u, v, out);
+        convert_row_tail(self, done, y, u, v, out);
+    }
 }
 
 /// Rescaling bit range for YUV -> GBR
@@ -142,18 +557,27 @@ impl<T: Copy> ToRGB<T, T> for CopyGBR<T> {
 pub struct IdentityScale<T = u8> {
     min: T,
     range: T,
+    rounding: Rounding,
 }
 
 #[inline(always)]
-fn rescale16(v: u16, fmin: u16, frange: u16) -> u16 {
-    let v = (i32::from(v) - i32::from(fmin)).max(0) as u32 * 65536;
-    (v / u32::from(frange)).min(65535) as u16
+fn rescale16(v: u16, fmin: u16, frange: u16, rounding: Rounding) -> u16 {
+    let v = (i32::from(v) - i32::from(fmin)).max(0) as u32;
+    let frange = u32::from(frange);
+    match rounding {
+        Rounding::Truncate => (v * 65536 / frange).min(65535) as u16,
+        Rounding::Nearest => ((v * 65535 + frange / 2) / frange).min(65535) as u16,
+    }
 }
 
 #[inline(always)]
-fn rescale8(v: u8, fmin: u8, frange: u8) -> u8 {
-    let v = (i16::from(v) - i16::from(fmin)).max(0) as u16 * 256;
-    (v / u16::from(frange)).min(255) as u8
+fn rescale8(v: u8, fmin: u8, frange: u8, rounding: Rounding) -> u8 {
+    let v = (i16::from(v) - i16::from(fmin)).max(0) as u16;
+    let frange = u16::from(frange);
+    match rounding {
+        Rounding::Truncate => (v * 256 / frange).min(255) as u8,
+        Rounding::Nearest => ((v * 255 + frange / 2) / frange).min(255) as u8,
+    }
 }
 
 #[inline(always)]
@@ -161,6 +585,35 @@ fn new_scale<R: range::Range>() -> IdentityScale<R::Pixel> {
     IdentityScale {
         min: R::Y_MIN,
         range: R::Y_MAX - R::Y_MIN,
+        rounding: Rounding::Truncate,
+    }
+}
+
+impl<T: Copy + Into<u32>> IdentityScale<T> {
+    #[inline]
+    pub(crate) fn simd_kernel(&self, max: u32) -> simd::IdentityKernel {
+        let range = self.range.into();
+        let (mul, bias) = match self.rounding {
+            Rounding::Truncate => (max + 1, 0),
+            Rounding::Nearest => (max, range / 2),
+        };
+        simd::IdentityKernel {
+            min: self.min.into().into(),
+            range: range.into(),
+            mul: mul.into(),
+            bias: bias.into(),
+            max: max.into(),
+        }
+    }
+}
+
+impl<T> IdentityScale<T> {
+    /// Set rounding used when stretching the range
+    #[inline]
+    #[must_use]
+    pub fn with_rounding(mut self, rounding: Rounding) -> Self {
+        self.rounding = rounding;
+        self
     }
 }
 
@@ -173,15 +626,15 @@ impl IdentityScale<u8> {
 
 impl IdentityScale<u16> {
     #[inline(always)]
-    fn new(range: Range, depth: Depth) -> Result<Self, Error> {
-        Ok(match (range, depth) {
-            (Range::Limited, Depth::Depth10) => new_scale::<range::Limited<depth::Depth10>>(),
-            (Range::Limited, Depth::Depth12) => new_scale::<range::Limited<depth::Depth12>>(),
-            (Range::Limited, Depth::Depth16) => new_scale::<range::Limited<depth::Depth16>>(),
-            (Range::Full, Depth::Depth10) => new_scale::<range::Full<depth::Depth10>>(),
-            (Range::Full, Depth::Depth12) => new_scale::<range::Full<depth::Depth12>>(),
-            (Range::Full, Depth::Depth16) => new_scale::<range::Full<depth::Depth16>>(),
-            (_, Depth::Depth8) => return Err(Error::InvalidDepthRequested),
+    fn new(range: Range, depth: BitDepth) -> Result<Self, Error> {
+        if depth.bits() == 8 {
+            return Err(Error::InvalidDepthRequested);
+        }
+        let [y_min, y_max, ..] = range::levels(range, depth);
+        Ok(Self {
+            min: y_min,
+            range: y_max - y_min,
+            rounding: Rounding::Truncate,
         })
     }
 }
@@ -190,15 +643,22 @@ impl ToRGB<u8, u8> for IdentityScale<u8> {
     #[inline(always)]
     fn to_rgb(&self, px: YUV<u8>) -> Rgb<u8> {
         Rgb {
-            g: rescale8(px.y, self.min, self.range),
-            b: rescale8(px.u, self.min, self.range),
-            r: rescale8(px.v, self.min, self.range),
+            g: rescale8(px.y, self.min, self.range, self.rounding),
+            b: rescale8(px.u, self.min, self.range, self.rounding),
+            r: rescale8(px.v, self.min, self.range, self.rounding),
         }
     }
 
     #[inline(always)]
     fn to_luma(&self, y: u8) -> u8 {
-        rescale8(y, self.min, self.range)
+        rescale8(y, self.min, self.range, self.rounding)
+    }
+
+    #[inline]
+    fn to_rgb_row(&self, y: &[u8], u: &[u8], v: &[u8], out: &mut [Rgb<u8>]) {
+        assert!(y.len() == out.len() && u.len() == out.len() && v.len() == out.len(), "row lengths differ");
+        let done = simd::identity_row(&self.simd_kernel(255), y, u, v, out);
+        convert_row_tail(self, done, y, u, v, out);
     }
 }
 
@@ -206,15 +666,22 @@ impl ToRGB<u16, u16> for IdentityScale<u16> {
     #[inline(always)]
     fn to_rgb(&self, px: YUV<u16>) -> Rgb<u16> {
         Rgb {
-            g: rescale16(px.y, self.min, self.range),
-            b: rescale16(px.u, self.min, self.range),
-            r: rescale16(px.v, self.min, self.range),
+            g: rescale16(px.y, self.min, self.range, self.rounding),
+            b: rescale16(px.u, self.min, self.range, self.rounding),
+            r: rescale16(px.v, self.min, self.range, self.rounding),
         }
     }
 
     #[inline(always)]
     fn to_luma(&self, y: u16) -> u16 {
-        rescale16(y, self.min, self.range)
+        rescale16(y, self.min, self.range, self.rounding)
+    }
+
+    #[inline]
+    fn to_rgb_row(&self, y: &[u16], u: &[u16], v: &[u16], out: &mut [Rgb<u16>]) {
+        assert!(y.len() == out.len() && u.len() == out.len() && v.len() == out.len(), "row lengths differ");
+        let done = simd::identity_row(&self.simd_kernel(65535), y, u, v, out);
+        convert_row_tail(self, done, y, u, v, out);
     }
 }
 
@@ -223,14 +690,71 @@ impl ToRGB<u16, u16> for IdentityScale<u16> {
 pub struct Matrix<T = u8> {
     y_scale: range::RangeScale,
     uv_scale: range::RangeScale,
+    /// Max value of `to_rgbf` output
+    pub(crate) multiply: f32,
+    dither: Dither,
+    policy: Policy,
     _pixel: PhantomData<T>,
 
     // matrix coeffs preprocessed
     a: f32, b: f32, c: f32, d: f32,
 }
 
+/// Linear part of a [`Matrix`] conversion: `rgb = matrix × [y, u, v] + offset`, without rounding or clamping.
+///
+/// Input is in code values (e.g. `0..=1023` for 10-bit), and output RGB is in `0..=1`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Coefficients {
+    /// Rows for R, G, B; columns for Y, U, V
+    pub matrix: [[f32; 3]; 3],
+    pub offset: [f32; 3],
+}
+
+impl Coefficients {
+    /// Coefficients for input normalized to `0..=1` by dividing code values by `max`,
+    /// e.g. 255 for 8-bit textures, or 65535 for 10-bit values in 16-bit unorm textures
+    #[must_use]
+    pub fn with_input_max(self, max: f32) -> Self {
+        Self {
+            matrix: self.matrix.map(|row| row.map(|c| c * max)),
+            ..self
+        }
+    }
+
+    /// Coefficients for output RGB in `0..=max`, e.g. 255 for 8-bit output
+    #[must_use]
+    pub fn with_output_max(self, max: f32) -> Self {
+        Self {
+            matrix: self.matrix.map(|row| row.map(|c| c * max)),
+            offset: self.offset.map(|c| c * max),
+        }
+    }
+
+    /// Matrix and offset in fixed point with `frac_bits` fractional bits (Qn), rounded to nearest
+    #[must_use]
+    pub fn to_fixed(&self, frac_bits: u32) -> ([[i32; 3]; 3], [i32; 3]) {
+        let one = (1u64 << frac_bits) as f64;
+        let fixed = |c: f32| (f64::from(c) * one).round() as i32;
+        (self.matrix.map(|row| row.map(fixed)), self.offset.map(fixed))
+    }
+
+    /// Column-major 4×4 matrix for GPU uniforms (`mat4` in GLSL/WGSL, `column_major float4x4` in HLSL).
+    ///
+    /// The offset is in the 4th column, so `rgb = (m * vec4(yuv, 1.0)).rgb`.
+    #[must_use]
+    pub fn to_gpu_mat4(&self) -> [f32; 16] {
+        let m = &self.matrix;
+        [
+            m[0][0], m[1][0], m[2][0], 0.,
+            m[0][1], m[1][1], m[2][1], 0.,
+            m[0][2], m[1][2], m[2][2], 0.,
+            self.offset[0], self.offset[1], self.offset[2], 1.,
+        ]
+    }
+}
+
 impl<T: Copy> Matrix<T> {
-    fn new_internal(kr: f64, kb: f64, y_scale: range::RangeScale, uv_scale: range::RangeScale) -> Self {
+    fn new_internal(kr: f64, kb: f64, (y_scale, uv_scale): (range::RangeScale, range::RangeScale), multiply: f64) -> Self {
         let kg = 1. - kr - kb;
         assert!(kr > 0. && kg > 0. && kb > 0.);
         Self {
@@ -240,83 +764,496 @@ impl<T: Copy> Matrix<T> {
             d: (2. * (1. - kb)) as f32,
             y_scale,
             uv_scale,
+            multiply: multiply as f32,
+            dither: Dither::None,
+            policy: Policy::default(),
             _pixel: PhantomData,
         }
     }
 
+    /// Set rounding and clipping behavior
+    #[inline]
+    #[must_use]
+    pub fn with_policy(mut self, policy: Policy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// The matrix and offsets this converter uses, in float
+    #[must_use]
+    pub fn coefficients(&self) -> Coefficients {
+        let scale = 1. / self.multiply;
+        let (y, uv) = (self.y_scale, self.uv_scale);
+        Coefficients {
+            matrix: [
+                [y.mul, 0., uv.mul * self.a],
+                [y.mul, -uv.mul * self.b, -uv.mul * self.c],
+                [y.mul, uv.mul * self.d, 0.],
+            ].map(|row| row.map(|c| c * scale)),
+            offset: [
+                -y.sub - uv.sub * self.a,
+                -y.sub + uv.sub * (self.b + self.c),
+                -y.sub - uv.sub * self.d,
+            ].map(|c| c * scale),
+        }
+    }
+
+    /// Rounding and clipping set by [`Matrix::with_policy`]
+    #[inline(always)]
+    pub fn policy(&self) -> Policy {
+        self.policy
+    }
+
+    /// Set dithering mode used by [`ToRGB::to_rgb_at`], [`ToRGB::to_rgb_row_at`] (and so by frame conversions), and [`Ditherer`](crate::dither::Ditherer) when converting to 8-bit RGB.
+    ///
+    /// Frame conversions can't carry error between pixels, so [`Dither::FloydSteinberg`] rounds to nearest there.
+    ///
+    /// It's ignored by conversions to `u16` and float RGB.
+    #[inline]
+    #[must_use]
+    pub fn with_dither(mut self, dither: Dither) -> Self {
+        self.dither = dither;
+        self
+    }
+
+    /// Dithering mode set by [`Matrix::with_dither`]
+    #[inline(always)]
+    pub fn dither(&self) -> Dither {
+        self.dither
+    }
+
     /// Input is in its original range, NOT normalized
     /// Returns range or input `RangeScale` (roughly)
     #[inline(always)]
-    fn to_rgbf(&self, px: YUV<f32>) -> Rgb<f32> {
+    #[allow(clippy::wrong_self_convention)]
+    pub(crate) fn to_rgbf(&self, px: YUV<f32>) -> Rgb<f32> {
         let y = px.y * self.y_scale.mul - self.y_scale.sub;
-        Rgb {
-            r: (0_f32).max(y +  px.v * (self.uv_scale.mul * self.a) - (self.uv_scale.sub * self.a)),
-            b: (0_f32).max(y +  px.u * (self.uv_scale.mul * self.d) - (self.uv_scale.sub * self.d)),
-            g: (0_f32).max(y - (px.u * (self.uv_scale.mul * self.b) - (self.uv_scale.sub * self.b))
-                             - (px.v * (self.uv_scale.mul * self.c) - (self.uv_scale.sub * self.c))),
+        let rgb = Rgb {
+            r: y +  px.v * (self.uv_scale.mul * self.a) - (self.uv_scale.sub * self.a),
+            b: y +  px.u * (self.uv_scale.mul * self.d) - (self.uv_scale.sub * self.d),
+            g: y - (px.u * (self.uv_scale.mul * self.b) - (self.uv_scale.sub * self.b))
+                 - (px.v * (self.uv_scale.mul * self.c) - (self.uv_scale.sub * self.c)),
+        };
+        match self.policy.clamp {
+            Clamp::PerChannel => rgb.map(|c| (0_f32).max(c)),
+            Clamp::HuePreserving => clamp_hue_preserving(rgb, y, self.multiply),
+            Clamp::None => rgb,
+        }
+    }
+
+    /// Scale from `to_rgbf` output to `max`, and offset to add before truncating
+    #[inline(always)]
+    fn output_scale(&self, max: f32) -> (f32, f32) {
+        match self.policy.rounding {
+            Rounding::Truncate => ((max + 0.999) / self.multiply, 0.),
+            Rounding::Nearest => (max / self.multiply, 0.5),
+        }
+    }
+}
+
+impl<T: Sample> Matrix<T> {
+    /// Coefficients for the vectorized code and lookup tables, which don't support [`Clamp::HuePreserving`]
+    #[inline]
+    pub(crate) fn simd_kernel(&self, max: f32) -> Option<simd::MatrixKernel> {
+        let clamp = match self.policy.clamp {
+            Clamp::PerChannel => true,
+            Clamp::None => false,
+            Clamp::HuePreserving => return None,
+        };
+        let (scale, offset) = self.output_scale(max);
+        let uv = self.uv_scale;
+        Some(simd::MatrixKernel {
+            y_mul: self.y_scale.mul, y_sub: self.y_scale.sub,
+            rv_mul: uv.mul * self.a, rv_sub: uv.sub * self.a,
+            bu_mul: uv.mul * self.d, bu_sub: uv.sub * self.d,
+            gu_mul: uv.mul * self.b, gu_sub: uv.sub * self.b,
+            gv_mul: uv.mul * self.c, gv_sub: uv.sub * self.c,
+            clamp,
+            scale,
+            offset,
+            max,
+        })
+    }
+
+    /// Returns number of pixels converted
+    #[inline]
+    fn simd_row<O: simd::Store>(&self, y: &[T], u: &[T], v: &[T], out: &mut [Rgb<O>], max: f32) -> usize {
+        let Some(k) = self.simd_kernel(max) else {
+            return 0;
+        };
+        if let (Some(y), Some(u), Some(v)) = (T::as_u8(y), T::as_u8(u), T::as_u8(v)) {
+            simd::matrix_row(&k, y, u, v, out)
+        } else if let (Some(y), Some(u), Some(v)) = (T::as_u16(y), T::as_u16(u), T::as_u16(v)) {
+            simd::matrix_row(&k, y, u, v, out)
+        } else {
+            0
         }
     }
 }
 
+/// Moves color towards gray of the same luma until all channels fit in `0..=max`
+#[inline]
+fn clamp_hue_preserving(rgb: Rgb<f32>, y: f32, max: f32) -> Rgb<f32> {
+    let y = y.clamp(0., max);
+    let mut saturation = 1_f32;
+    for c in [rgb.r, rgb.g, rgb.b] {
+        if c > max {
+            saturation = saturation.min((max - y) / (c - y));
+        } else if c < 0. {
+            saturation = saturation.min(y / (y - c));
+        }
+    }
+    rgb.map(|c| (y + (c - y) * saturation).clamp(0., max))
+}
+
 impl Matrix<u8> {
     #[inline]
     fn new(kr: f64, kb: f64, yuv_range: Range) -> Self {
-        let (y_scale, uv_scale) = match yuv_range {
-            Range::Full => range::to_floats::<range::Full<depth::Depth8>>(255.999),
-            Range::Limited => range::to_floats::<range::Limited<depth::Depth8>>(255.999),
+        const MUL: f64 = 255.999;
+        let scales = match yuv_range {
+            Range::Full => range::to_floats::<range::Full<depth::Depth8>>(MUL),
+            Range::Limited => range::to_floats::<range::Limited<depth::Depth8>>(MUL),
         };
-        Self::new_internal(kr, kb, y_scale, uv_scale)
+        Self::new_internal(kr, kb, scales, MUL)
+    }
+
+    #[inline]
+    fn from_levels(kr: f64, kb: f64, levels: [u16; 4]) -> Self {
+        const MUL: f64 = 255.999;
+        Self::new_internal(kr, kb, range::scales(levels, MUL), MUL)
+    }
+
+    /// Matrix for custom luma weights of red (`kr`) and blue (`kb`), e.g. 0.2126 and 0.0722 for BT.709.
+    ///
+    /// Fails if any of `kr`, `kb` and `1 - kr - kb` isn't positive.
+    pub fn from_kr_kb(kr: f64, kb: f64, range: Range) -> Result<Self, Error> {
+        check_kr_kb(kr, kb)?;
+        Ok(Self::new(kr, kb, range))
+    }
+}
+
+fn check_kr_kb(kr: f64, kb: f64) -> Result<(), Error> {
+    if kr > 0. && kb > 0. && kr + kb < 1. {
+        Ok(())
+    } else {
+        Err(Error::UnsupportedMatrixCoefficients)
     }
 }
 
-impl<T: Copy> ToRGB<T, u8> for Matrix<T> where T: Into<f32> {
+impl<T: Sample> ToRGB<T, u8> for Matrix<T> {
     #[inline]
     fn to_rgb(&self, px: YUV<T>) -> Rgb<u8> {
+        // when the input is 8-bit too, the scale is exactly 1
+        let (scale, offset) = self.output_scale(255.);
         self.to_rgbf(YUV {
             y: px.y.into(),
             u: px.u.into(),
             v: px.v.into(),
         })
-        .map(|c| c.min(255.) as u8)
+        .map(|c| (c * scale + offset).min(255.) as u8)
     }
 
     #[inline]
     fn to_luma(&self, y: T) -> u8 {
-        (y.into() * self.y_scale.mul - self.y_scale.sub) as u8
+        let (scale, offset) = self.output_scale(255.);
+        ((y.into() * self.y_scale.mul - self.y_scale.sub) * scale + offset) as u8
+    }
+
+    #[inline]
+    fn to_rgb_at(&self, px: YUV<T>, x: usize, y: usize) -> Rgb<u8> {
+        let Some(threshold) = self.dither.threshold(x, y) else {
+            return self.to_rgb(px);
+        };
+        let scale = 255. / self.multiply;
+        self.to_rgbf(YUV {
+            y: px.y.into(),
+            u: px.u.into(),
+            v: px.v.into(),
+        })
+        .map(|c| (c * scale + threshold).min(255.) as u8)
+    }
+
+    #[inline]
+    fn to_rgb_row(&self, y: &[T], u: &[T], v: &[T], out: &mut [Rgb<u8>]) {
+        assert!(y.len() == out.len() && u.len() == out.len() && v.len() == out.len(), "row lengths differ");
+        let done = self.simd_row(y, u, v, out, 255.);
+        convert_row_tail(self, done, y, u, v, out);
+    }
+
+    #[inline]
+    fn to_rgb_row_at(&self, y: &[T], u: &[T], v: &[T], out: &mut [Rgb<u8>], x: usize, row: usize) {
+        if self.dither == Dither::None {
+            return self.to_rgb_row(y, u, v, out);
+        }
+        assert!(y.len() == out.len() && u.len() == out.len() && v.len() == out.len(), "row lengths differ");
+        for (i, (out, ((&y, &u), &v))) in out.iter_mut().zip(y.iter().zip(u).zip(v)).enumerate() {
+            *out = self.to_rgb_at(YUV { y, u, v }, x + i, row);
+        }
     }
 }
 
 impl Matrix<u16> {
     #[inline]
-    fn new(kr: f64, kb: f64, yuv_range: Range, depth: Depth) -> Self {
-        let (y_scale, uv_scale) = match (yuv_range, depth) {
-            (Range::Full, Depth::Depth8) => range::to_floats::<range::Full<depth::Depth8>>(65535.999),
-            (Range::Full, Depth::Depth10) => range::to_floats::<range::Full<depth::Depth10>>(65535.999),
-            (Range::Full, Depth::Depth12) => range::to_floats::<range::Full<depth::Depth12>>(65535.999),
-            (Range::Full, Depth::Depth16) => range::to_floats::<range::Full<depth::Depth16>>(65535.999),
-            (Range::Limited, Depth::Depth8) => range::to_floats::<range::Limited<depth::Depth8>>(65535.999),
-            (Range::Limited, Depth::Depth10) => range::to_floats::<range::Limited<depth::Depth10>>(65535.999),
-            (Range::Limited, Depth::Depth12) => range::to_floats::<range::Limited<depth::Depth12>>(65535.999),
-            (Range::Limited, Depth::Depth16) => range::to_floats::<range::Limited<depth::Depth16>>(65535.999),
-        };
-        Self::new_internal(kr, kb, y_scale, uv_scale)
+    fn new(kr: f64, kb: f64, yuv_range: Range, depth: impl Into<BitDepth>) -> Self {
+        Self::from_levels(kr, kb, range::levels(yuv_range, depth.into()))
+    }
+
+    #[inline]
+    fn from_levels(kr: f64, kb: f64, levels: [u16; 4]) -> Self {
+        const MUL: f64 = 65535.999;
+        Self::new_internal(kr, kb, range::scales(levels, MUL), MUL)
+    }
+
+    /// Matrix for custom luma weights of red (`kr`) and blue (`kb`), and input with `depth` bits.
+    ///
+    /// Fails if any of `kr`, `kb` and `1 - kr - kb` isn't positive.
+    pub fn from_kr_kb(kr: f64, kb: f64, range: Range, depth: impl Into<BitDepth>) -> Result<Self, Error> {
+        check_kr_kb(kr, kb)?;
+        Ok(Self::new(kr, kb, range, depth))
     }
 }
 
-impl<T: Copy> ToRGB<T, u16> for Matrix<T> where T: Into<f32> {
+impl<T: Sample> ToRGB<T, u16> for Matrix<T> {
     #[inline]
     fn to_rgb(&self, px: YUV<T>) -> Rgb<u16> {
+        let (scale, offset) = self.output_scale(65535.);
         self.to_rgbf(YUV {
             y: px.y.into(),
             u: px.u.into(),
             v: px.v.into(),
         })
-        .map(|c| c as u16)
+        .map(|c| (c * scale + offset) as u16)
     }
 
     #[inline]
     fn to_luma(&self, y: T) -> u16 {
-        (y.into() * self.y_scale.mul - self.y_scale.sub) as u16
+        let (scale, offset) = self.output_scale(65535.);
+        ((y.into() * self.y_scale.mul - self.y_scale.sub) * scale + offset) as u16
+    }
+
+    #[inline]
+    fn to_rgb_row(&self, y: &[T], u: &[T], v: &[T], out: &mut [Rgb<u16>]) {
+        assert!(y.len() == out.len() && u.len() == out.len() && v.len() == out.len(), "row lengths differ");
+        let done = self.simd_row(y, u, v, out, 65535.);
+        convert_row_tail(self, done, y, u, v, out);
+    }
+}
+
+impl Matrix<f32> {
+    /// Converter for normalized float input: Y in `0..=1`, and U/V in `-0.5..=0.5`.
+    ///
+    /// It doesn't clamp output by default. Values outside of `0..=1` are kept for float output.
+    ///
+    /// Fails if any of `kr`, `kb` and `1 - kr - kb` isn't positive.
+    #[inline]
+    pub fn new(kr: f64, kb: f64) -> Result<Self, Error> {
+        check_kr_kb(kr, kb)?;
+        Ok(Self::new_normalized(kr, kb))
+    }
+}
+
+#[cfg(feature = "half")]
+impl Matrix<half::f16> {
+    /// Converter for normalized half-float input: Y in `0..=1`, and U/V in `-0.5..=0.5`.
+    ///
+    /// It doesn't clamp output by default. Values outside of `0..=1` are kept for float output.
+    ///
+    /// Fails if any of `kr`, `kb` and `1 - kr - kb` isn't positive.
+    #[inline]
+    pub fn new(kr: f64, kb: f64) -> Result<Self, Error> {
+        check_kr_kb(kr, kb)?;
+        Ok(Self::new_normalized(kr, kb))
+    }
+}
+
+impl<T: Copy> Matrix<T> {
+    fn new_normalized(kr: f64, kb: f64) -> Self {
+        let identity = range::RangeScale { mul: 1., sub: 0. };
+        Self::new_internal(kr, kb, (identity, identity), 1.)
+            .with_policy(Policy { clamp: Clamp::None, ..Policy::default() })
+    }
+
+    #[inline(always)]
+    fn normalized_rgb(&self, px: YUV<f32>) -> Rgb<f32> {
+        let rgb = self.to_rgbf(px);
+        let scale = 1. / self.multiply;
+        match self.policy.clamp {
+            // to_rgbf only clamped the low end
+            Clamp::PerChannel => rgb.map(|c| (c * scale).min(1.)),
+            Clamp::HuePreserving | Clamp::None => rgb.map(|c| c * scale),
+        }
+    }
+}
+
+/// Output is in `0..=1` range, but if the converter has been set to [`Clamp::None`], it may be outside of it.
+/// Use the unclamped output for HDR compositing.
+impl<T: Copy> ToRGB<T, f32> for Matrix<T> where T: Into<f32> {
+    #[inline]
+    fn to_rgb(&self, px: YUV<T>) -> Rgb<f32> {
+        self.normalized_rgb(YUV {
+            y: px.y.into(),
+            u: px.u.into(),
+            v: px.v.into(),
+        })
+    }
+
+    #[inline]
+    fn to_luma(&self, y: T) -> f32 {
+        let y = (y.into() * self.y_scale.mul - self.y_scale.sub) / self.multiply;
+        match self.policy.clamp {
+            Clamp::PerChannel | Clamp::HuePreserving => y.clamp(0., 1.),
+            Clamp::None => y,
+        }
+    }
+}
+
+#[cfg(feature = "half")]
+impl<T: Copy> ToRGB<T, half::f16> for Matrix<T> where T: Into<f32> {
+    #[inline]
+    fn to_rgb(&self, px: YUV<T>) -> Rgb<half::f16> {
+        ToRGB::<T, f32>::to_rgb(self, px).map(half::f16::from_f32)
+    }
+
+    #[inline]
+    fn to_luma(&self, y: T) -> half::f16 {
+        half::f16::from_f32(ToRGB::<T, f32>::to_luma(self, y))
+    }
+}
+
+/// Output is in `0..=1` range. Studio-range footroom and headroom are kept as values outside of it.
+impl<T: Copy> ToRGB<T, f32> for IdentityScale<T> where T: Into<f32> {
+    #[inline]
+    fn to_rgb(&self, px: YUV<T>) -> Rgb<f32> {
+        Rgb {
+            g: self.to_luma(px.y),
+            b: self.to_luma(px.u),
+            r: self.to_luma(px.v),
+        }
+    }
+
+    #[inline]
+    fn to_luma(&self, y: T) -> f32 {
+        (y.into() - self.min.into()) / self.range.into()
+    }
+}
+
+impl ToRGB<u8, f32> for CopyGBR<u8> {
+    #[inline(always)]
+    fn to_rgb(&self, px: YUV<u8>) -> Rgb<f32> {
+        Rgb { r: px.v, g: px.y, b: px.u }.map(|c| f32::from(c) / 255.)
+    }
+
+    #[inline(always)]
+    fn to_luma(&self, y: u8) -> f32 {
+        f32::from(y) / 255.
+    }
+}
+
+impl ToRGB<u16, f32> for CopyGBR<u16> {
+    #[inline(always)]
+    fn to_rgb(&self, px: YUV<u16>) -> Rgb<f32> {
+        Rgb { r: px.v, g: px.y, b: px.u }.map(|c| f32::from(c) / 65535.)
+    }
+
+    #[inline(always)]
+    fn to_luma(&self, y: u16) -> f32 {
+        f32::from(y) / 65535.
+    }
+}
+
+impl ToRGB<u8, f32> for RGBConvert<u8> {
+    /// Convert to RGB in `0..=1` range (or beyond, see [`Clamp::None`])
+    #[inline(always)]
+    fn to_rgb(&self, px: YUV<u8>) -> Rgb<f32> {
+        match self {
+            Self::Matrix(c) => c.to_rgb(px),
+            Self::Copy(c) => c.to_rgb(px),
+            Self::IdentityScale(c) => c.to_rgb(px),
+        }
+    }
+
+    #[inline(always)]
+    fn to_luma(&self, y: u8) -> f32 {
+        match self {
+            Self::Matrix(c) => c.to_luma(y),
+            Self::Copy(c) => c.to_luma(y),
+            Self::IdentityScale(c) => c.to_luma(y),
+        }
+    }
+
+    #[inline]
+    fn to_rgb_row(&self, y: &[u8], u: &[u8], v: &[u8], out: &mut [Rgb<f32>]) {
+        match self {
+            Self::Matrix(c) => c.to_rgb_row(y, u, v, out),
+            Self::Copy(c) => c.to_rgb_row(y, u, v, out),
+            Self::IdentityScale(c) => c.to_rgb_row(y, u, v, out),
+        }
+    }
+
+    #[inline]
+    fn to_rgb_slice(&self, px: &[YUV<u8>], out: &mut [Rgb<f32>]) {
+        match self {
+            Self::Matrix(c) => c.to_rgb_slice(px, out),
+            Self::Copy(c) => c.to_rgb_slice(px, out),
+            Self::IdentityScale(c) => c.to_rgb_slice(px, out),
+        }
+    }
+
+    #[inline]
+    fn to_luma_row(&self, y: &[u8], out: &mut [f32]) {
+        match self {
+            Self::Matrix(c) => c.to_luma_row(y, out),
+            Self::Copy(c) => c.to_luma_row(y, out),
+            Self::IdentityScale(c) => c.to_luma_row(y, out),
+        }
+    }
+}
+
+impl ToRGB<u16, f32> for RGBConvert<u16> {
+    /// Convert to RGB in `0..=1` range (or beyond, see [`Clamp::None`])
+    #[inline(always)]
+    fn to_rgb(&self, px: YUV<u16>) -> Rgb<f32> {
+        match self {
+            Self::Matrix(c) => c.to_rgb(px),
+            Self::Copy(c) => c.to_rgb(px),
+            Self::IdentityScale(c) => c.to_rgb(px),
+        }
+    }
+
+    #[inline(always)]
+    fn to_luma(&self, y: u16) -> f32 {
+        match self {
+            Self::Matrix(c) => c.to_luma(y),
+            Self::Copy(c) => c.to_luma(y),
+            Self::IdentityScale(c) => c.to_luma(y),
+        }
+    }
+
+    #[inline]
+    fn to_rgb_row(&self, y: &[u16], u: &[u16], v: &[u16], out: &mut [Rgb<f32>]) {
+        match self {
+            Self::Matrix(c) => c.to_rgb_row(y, u, v, out),
+            Self::Copy(c) => c.to_rgb_row(y, u, v, out),
+            Self::IdentityScale(c) => c.to_rgb_row(y, u, v, out),
+        }
+    }
+
+    #[inline]
+    fn to_rgb_slice(&self, px: &[YUV<u16>], out: &mut [Rgb<f32>]) {
+        match self {
+            Self::Matrix(c) => c.to_rgb_slice(px, out),
+            Self::Copy(c) => c.to_rgb_slice(px, out),
+            Self::IdentityScale(c) => c.to_rgb_slice(px, out),
+        }
+    }
+
+    #[inline]
+    fn to_luma_row(&self, y: &[u16], out: &mut [f32]) {
+        match self {
+            Self::Matrix(c) => c.to_luma_row(y, out),
+            Self::Copy(c) => c.to_luma_row(y, out),
+            Self::IdentityScale(c) => c.to_luma_row(y, out),
+        }
     }
 }
 
@@ -334,6 +1271,8 @@ fn traits_all_the_way_down() {
 
 #[test]
 fn matrix_conv() {
+    use crate::color::Depth;
+
     let m = Matrix::<u8>::new(0.2126, 0.0722, Range::Full);
     let px = m.to_rgbf(YUV{y:222.,u:128.,v:128.}).map(|c| c.floor() as u8);
     assert_eq!(Rgb::new(222,222,222), px);
@@ -363,3 +1302,204 @@ fn matrix_conv() {
     assert_eq!(0u16, m.to_luma(0u16));
     assert_eq!(5592u16, m.to_luma(555u16));
 }
+
+#[test]
+fn arbitrary_depth() {
+    use crate::color::Depth;
+
+    assert!(BitDepth::new(7).is_err() && BitDepth::new(17).is_err());
+    let d14 = BitDepth::new(14).unwrap();
+    let m = RGBConvert::<u16>::new(Range::Limited, MatrixCoefficients::BT709, d14).unwrap();
+    assert_eq!(Rgb::new(0, 0, 0), m.to_rgb(YUV { y: 16 << 6, u: 128 << 6, v: 128 << 6 }));
+    assert_eq!(Rgb::new(65535, 65535, 65535), m.to_rgb(YUV { y: 235 << 6, u: 128 << 6, v: 128 << 6 }));
+    // same color as 10-bit
+    let m10 = RGBConvert::<u16>::new(Range::Limited, MatrixCoefficients::BT709, Depth::Depth10).unwrap();
+    let (a, b) = (m.to_rgb(YUV { y: 500 << 4, u: 300 << 4, v: 700 << 4 }), m10.to_rgb(YUV { y: 500, u: 300, v: 700 }));
+    assert!(a.r.abs_diff(b.r) <= 1 && a.g.abs_diff(b.g) <= 1 && a.b.abs_diff(b.b) <= 1, "{a:?} {b:?}");
+
+    let gbr = RGBConvert::<u16>::new(Range::Full, MatrixCoefficients::Identity, BitDepth::new(9).unwrap()).unwrap();
+    assert_eq!(Rgb::new(65535, 0, 32832), gbr.to_rgb(YUV { y: 0, u: 256, v: 511 }));
+}
+
+#[test]
+fn custom_levels() {
+    // xvYCC keeps values in footroom and headroom of the limited range
+    let RGBConvert::Matrix(m) = RGBConvert::<u8>::from_levels(Levels::limited(8), MatrixCoefficients::BT709).unwrap() else { panic!() };
+    let m = m.with_policy(Policy { clamp: Clamp::None, ..Policy::default() });
+    let white: Rgb<f32> = m.to_rgb(YUV { y: 254u8, u: 128, v: 128 });
+    assert!(white.r > 1.08 && white.g > 1.08 && white.b > 1.08, "{white:?}");
+    let black: Rgb<f32> = m.to_rgb(YUV { y: 1u8, u: 128, v: 128 });
+    assert!(black.r < -0.06, "{black:?}");
+    assert_eq!(ToRGB::<u8, f32>::to_luma(&m, 254), white.g);
+    let clamped = m.with_policy(Policy::default());
+    assert_eq!(Rgb::new(1., 1., 1.), ToRGB::<u8, f32>::to_rgb(&clamped, YUV { y: 254u8, u: 128, v: 128 }));
+
+    // same as the built-in range
+    let custom = RGBConvert::<u16>::from_levels(Levels::limited(10), MatrixCoefficients::BT709).unwrap();
+    let builtin = RGBConvert::<u16>::new(Range::Limited, MatrixCoefficients::BT709, crate::color::Depth::Depth10).unwrap();
+    let px = YUV { y: 500, u: 300, v: 700 };
+    assert_eq!(builtin.to_rgb(px), custom.to_rgb(px));
+
+    let gbr = RGBConvert::<u8>::from_levels(Levels::custom(10, 200, 10, 200).unwrap(), MatrixCoefficients::Identity).unwrap();
+    assert_eq!(Rgb::new(255, 0, 0), gbr.to_rgb(YUV { y: 10, u: 5, v: 200 }));
+
+    assert!(Levels::custom(100, 50, 0, 255).is_err());
+    assert!(RGBConvert::<u8>::from_levels(Levels::full(10), MatrixCoefficients::BT709).is_err());
+}
+
+#[test]
+fn exported_coefficients() {
+    use crate::color::Depth;
+
+    let m = Matrix::<u16>::from_kr_kb(0.2627, 0.0593, Range::Limited, Depth::Depth10).unwrap()
+        .with_policy(Policy { clamp: Clamp::None, ..Policy::default() });
+    let c = m.coefficients();
+    for px in [YUV { y: 64u16, u: 512, v: 512 }, YUV { y: 940, u: 100, v: 900 }, YUV { y: 500, u: 300, v: 700 }] {
+        let yuv = [px.y, px.u, px.v].map(f32::from);
+        let expected: Rgb<f32> = m.to_rgb(px);
+        let rgb = [0, 1, 2].map(|i| c.matrix[i].iter().zip(yuv).map(|(m, v)| m * v).sum::<f32>() + c.offset[i]);
+        assert!((rgb[0] - expected.r).abs() < 1e-5 && (rgb[1] - expected.g).abs() < 1e-5 && (rgb[2] - expected.b).abs() < 1e-5, "{rgb:?} {expected:?}");
+
+        let mat4 = c.with_input_max(1023.).to_gpu_mat4();
+        let n = yuv.map(|v| v / 1023.);
+        let r = mat4[0] * n[0] + mat4[4] * n[1] + mat4[8] * n[2] + mat4[12];
+        assert!((r - expected.r).abs() < 1e-5);
+    }
+
+    let c = Matrix::<u8>::from_kr_kb(0.2126, 0.0722, Range::Limited).unwrap().coefficients();
+    let (matrix, offset) = c.with_output_max(255.).to_fixed(14);
+    // 255/219 for luma, and 2*(1-0.2126)*255/224 for Cr
+    assert_eq!([19077, 0, 29372], matrix[0]);
+    assert!((offset[0] + 4_064_887).abs() <= 2, "{offset:?}");
+    assert_eq!(0, matrix[2][2]);
+
+    assert!(Matrix::<u8>::from_kr_kb(0.5, 0.5, Range::Full).is_err());
+    assert!(Matrix::<u8>::from_kr_kb(0., 0.1, Range::Full).is_err());
+}
+
+#[test]
+fn narrowing_dither() {
+    use crate::color::Depth;
+
+    let m = Matrix::<u16>::new(0.2126, 0.0722, Range::Full, Depth::Depth10);
+    assert_eq!(Rgb::new(255u8, 255, 255), m.to_rgb(YUV { y: 1023u16, u: 512, v: 512 }));
+    assert_eq!(127u8, m.to_luma(511u16));
+
+    // 10-bit gradient between two 8-bit levels should average out to the in-between value
+    let m = m.with_dither(Dither::Bayer);
+    let sum: u32 = (0..8).flat_map(|y| (0..8).map(move |x| (x, y)))
+        .map(|(x, y)| u32::from(ToRGB::<u16, u8>::to_rgb_at(&m, YUV { y: 514u16, u: 512, v: 512 }, x, y).g))
+        .sum();
+    assert!((sum as f32 / 64. - 514. * 255. / 1023.).abs() < 0.02);
+
+    // only 8-bit output of matrix conversions is dithered
+    assert_eq!(Dither::Bayer, RGBConvert::<u8>::new(Range::Full, MatrixCoefficients::BT709).unwrap().with_dither(Dither::Bayer).dither());
+    assert_eq!(Dither::None, RGBConvert::<u16>::new(Range::Full, MatrixCoefficients::BT709, Depth::Depth10).unwrap().with_dither(Dither::Bayer).dither());
+    assert_eq!(Dither::None, RGBConvert::<u8>::new(Range::Full, MatrixCoefficients::Identity).unwrap().with_dither(Dither::Bayer).dither());
+}
+
+#[test]
+#[cfg(not(any(not(feature = "std"), feature = "no_std")))]
+fn error_diffusion() {
+    use crate::color::Depth;
+
+    let m = Matrix::<u16>::new(0.2126, 0.0722, Range::Full, Depth::Depth10).with_dither(Dither::FloydSteinberg);
+    let y = [514u16; 64];
+    let uv = [512u16; 64];
+    let mut out = [Rgb::new(0u8, 0, 0); 64];
+    let mut d = crate::dither::Ditherer::new();
+    let mut sum = 0;
+    for _ in 0..64 {
+        d.convert_row(&m, &y, &uv, &uv, &mut out);
+        sum += out.iter().map(|px| u32::from(px.g)).sum::<u32>();
+    }
+    assert!((sum as f32 / (64. * 64.) - 514. * 255. / 1023.).abs() < 0.02, "{sum}");
+}
+
+#[test]
+fn rounding_policy() {
+    use crate::color::Depth;
+
+    let nearest = Policy { rounding: Rounding::Nearest, clamp: Clamp::PerChannel };
+    let m = Matrix::<u16>::new(0.2126, 0.0722, Range::Full, Depth::Depth10);
+    assert_eq!(0u8, m.to_luma(3u16));
+    let m = m.with_policy(nearest);
+    assert_eq!(1u8, m.to_luma(3u16));
+    assert_eq!(Rgb::new(128u8, 128, 128), m.to_rgb(YUV { y: 513u16, u: 512, v: 512 }));
+    assert_eq!(Rgb::new(255u8, 255, 255), m.to_rgb(YUV { y: 1023u16, u: 512, v: 512 }));
+
+    let s = IdentityScale::<u8>::new().with_rounding(Rounding::Nearest);
+    assert_eq!(0u8, s.to_luma(16u8));
+    assert_eq!(128u8, s.to_luma(126u8));
+    assert_eq!(255u8, s.to_luma(235u8));
+}
+
+#[test]
+fn hue_preserving_clamp() {
+    let m = Matrix::<u8>::new(0.2126, 0.0722, Range::Full).with_policy(Policy { rounding: Rounding::Nearest, clamp: Clamp::HuePreserving });
+    let px: Rgb<u8> = m.to_rgb(YUV { y: 128u8, u: 40, v: 160 });
+    let luma = 0.2126 * f32::from(px.r) + 0.7152 * f32::from(px.g) + 0.0722 * f32::from(px.b);
+    assert!((luma - 128.).abs() < 1., "{px:?} {luma}");
+    assert!(px.r > px.g && px.g > px.b);
+}
+
+#[test]
+fn float_output() {
+    use crate::color::Depth;
+
+    let m = Matrix::<u16>::new(0.2126, 0.0722, Range::Limited, Depth::Depth10);
+    let px: Rgb<f32> = m.to_rgb(YUV { y: 940u16, u: 512, v: 512 });
+    assert!(px.iter().all(|c| (c - 1.).abs() < 0.001), "{px:?}");
+
+    // super-white survives when unclamped
+    let m = m.with_policy(Policy { clamp: Clamp::None, ..Policy::default() });
+    let px: Rgb<f32> = m.to_rgb(YUV { y: 1019u16, u: 512, v: 512 });
+    assert!(px.iter().all(|c| c > 1.08), "{px:?}");
+
+    assert!(Matrix::<f32>::new(0.5, 0.6).is_err());
+    let m = Matrix::<f32>::new(0.2126, 0.0722).unwrap();
+    let px: Rgb<f32> = m.to_rgb(YUV { y: 0.5, u: 0., v: 0. });
+    assert!(px.iter().all(|c| (c - 0.5).abs() < 0.0001), "{px:?}");
+    let px: Rgb<f32> = m.to_rgb(YUV { y: 0.1, u: 0.5, v: -0.5 });
+    assert!(px.r < 0. && px.b > 1., "{px:?}");
+    let px: Rgb<u8> = m.to_rgb(YUV { y: 1., u: 0., v: 0. });
+    assert_eq!(Rgb::new(255, 255, 255), px);
+
+    let c = RGBConvert::<u8>::new(Range::Limited, MatrixCoefficients::Identity).unwrap();
+    let px: Rgb<f32> = ToRGB::to_rgb(&c, YUV { y: 16, u: 235, v: 126 });
+    assert_eq!(0., px.g);
+    assert_eq!(1., px.b);
+}
+
+#[test]
+fn rows_match_pixels() {
+    let converters = [
+        RGBConvert::<u8>::new(Range::Limited, MatrixCoefficients::BT601).unwrap(),
+        RGBConvert::<u8>::new(Range::Limited, MatrixCoefficients::Identity).unwrap(),
+        RGBConvert::<u8>::new(Range::Full, MatrixCoefficients::Identity).unwrap(),
+    ];
+    let y: [u8; 64] = core::array::from_fn(|i| (i * 4) as u8);
+    let u: [u8; 64] = core::array::from_fn(|i| (255 - i * 3) as u8);
+    let v: [u8; 64] = core::array::from_fn(|i| (i * 7) as u8);
+    let interleaved: [YUV<u8>; 64] = core::array::from_fn(|i| YUV { y: y[i], u: u[i], v: v[i] });
+    for c in converters {
+        let mut row = [Rgb::new(0, 0, 0); 64];
+        let mut slice = [Rgb::new(0, 0, 0); 64];
+        let mut luma = [0; 64];
+        c.to_rgb_row(&y, &u, &v, &mut row);
+        c.to_rgb_slice(&interleaved, &mut slice);
+        c.to_luma_row(&y, &mut luma);
+        for i in 0..64 {
+            assert_eq!(c.to_rgb(interleaved[i]), row[i]);
+            assert_eq!(row[i], slice[i]);
+            assert_eq!(c.to_luma(y[i]), luma[i]);
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "row lengths differ")]
+fn row_length_mismatch() {
+    let conv = RGBConvert::<u8>::new(Range::Full, MatrixCoefficients::BT709).unwrap();
+    conv.to_rgb_row(&[0; 4], &[0; 4], &[0; 3], &mut [Rgb::new(0, 0, 0); 4]);
+}
@@ -0,0 +1,178 @@
+//! Dithering for conversions that lose precision, e.g. 10-bit YUV to 8-bit RGB.
+//!
+//! Select the mode with [`Matrix::with_dither`](crate::convert::Matrix::with_dither) or [`RGBConvert::with_dither`](crate::convert::RGBConvert::with_dither).
+//! Ordered modes work per pixel via [`ToRGB::to_rgb_at`](crate::convert::ToRGB::to_rgb_at), and in conversions of color frames like [`YUVFrameRef::convert`](crate::frame::YUVFrameRef::convert).
+//! Error diffusion needs state carried between pixels, so it's available only via [`Ditherer`].
+//!
+//! All modes are deterministic: the same input at the same position always gives the same output.
+
+#[cfg(not(any(not(feature = "std"), feature = "no_std")))]
+use crate::convert::Matrix;
+#[cfg(not(any(not(feature = "std"), feature = "no_std")))]
+use crate::YUV;
+#[cfg(not(any(not(feature = "std"), feature = "no_std")))]
+use rgb::Rgb;
+#[cfg(not(any(not(feature = "std"), feature = "no_std")))]
+use rgb::prelude::*;
+
+/// How to hide quantization error when output has fewer bits than the input
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum Dither {
+    /// Truncate (the default, and the old behavior)
+    #[default]
+    None,
+    /// Ordered dithering with 8×8 Bayer matrix. Cheap, but has a visible cross-hatch pattern.
+    Bayer,
+    /// Ordered dithering with a 16×16 blue noise tile. Less visible pattern than Bayer.
+    BlueNoise,
+    /// Floyd–Steinberg error diffusion over a row or a frame. Needs [`Ditherer`].
+    ///
+    /// When used per pixel without a [`Ditherer`], it rounds to nearest.
+    FloydSteinberg,
+}
+
+const BAYER: [[u8; 8]; 8] = [
+    [ 0, 32,  8, 40,  2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44,  4, 36, 14, 46,  6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [ 3, 35, 11, 43,  1, 33,  9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47,  7, 39, 13, 45,  5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+/// Ranks from void-and-cluster algorithm
+const BLUE_NOISE: [[u8; 16]; 16] = [
+    [228,  85, 174,   9,  65, 249,  79, 119, 202,  23, 110, 160,  95, 133, 166,  67],
+    [182,  26, 109, 236, 147, 192, 169,  52, 244, 183,  62,  43, 251, 216, 107,  11],
+    [246, 135, 196,  53, 125,  17, 103,  32, 132,  87, 153, 206,  20,  77,  51, 154],
+    [ 42,  74, 218,  91,  36, 201, 231, 155, 214,   5, 230, 116, 138, 186, 224, 120],
+    [ 98, 163,   0, 179, 247, 134,  82,  59, 180, 105,  72,  35, 162,  88,   7, 203],
+    [ 29, 238, 141, 111,  70, 167,  10, 118, 255,  24, 173, 241, 198,  57, 234, 170],
+    [127, 194,  60,  19, 221,  46, 204, 144,  41, 209, 131,  94,  22, 112, 143,  71],
+    [ 44,  89, 229, 149, 185, 102, 233,  86, 190,  68, 152,  50, 220, 177,  15, 253],
+    [211, 175,  25,  76, 124,  33, 159,  14, 113, 232,   1, 248, 122,  80, 200, 104],
+    [  8, 117, 156, 250, 193,  64, 242, 136,  54, 165,  99, 184,  37, 161,  61, 145],
+    [ 83, 225,  49, 100,   4, 208,  92, 181, 222,  34, 207,  73, 137, 215,  28, 243],
+    [164,  31, 199, 140, 171,  45, 126,  18,  75, 148, 121,  21, 240, 101, 191, 128],
+    [217, 106,  66, 239,  84, 219, 157, 252, 108, 188, 235,  56, 172,  13,  78,  47],
+    [  2, 151, 189,  16, 114,  27,  63, 195,  48,  12,  90, 212, 150, 115, 223, 176],
+    [ 93, 254,  55, 130, 227, 178, 139,  96, 226, 168, 129,  39,  81, 245,  58, 142],
+    [123,  40, 210, 158,  97,  38, 213,   3, 146,  69, 237, 197,   6, 187,  30, 205],
+];
+
+impl Dither {
+    /// Value in 0..1 to add before truncating, or `None` if this mode doesn't use a threshold
+    #[inline(always)]
+    pub(crate) fn threshold(self, x: usize, y: usize) -> Option<f32> {
+        match self {
+            Self::None => None,
+            Self::Bayer => Some((f32::from(BAYER[y % 8][x % 8]) + 0.5) / 64.),
+            Self::BlueNoise => Some((f32::from(BLUE_NOISE[y % 16][x % 16]) + 0.5) / 256.),
+            Self::FloydSteinberg => Some(0.5),
+        }
+    }
+}
+
+/// Converts rows of pixels to 8-bit RGB, keeping track of the row number and diffused error.
+///
+/// Uses the dithering mode of the [`Matrix`] it's given. Call [`Ditherer::reset`] at the start of every frame
+/// (or every row, if error shouldn't be diffused across rows).
+#[cfg(not(any(not(feature = "std"), feature = "no_std")))]
+#[derive(Debug, Clone, Default)]
+pub struct Ditherer {
+    row: usize,
+    /// Error carried to the current row, offset by 1 to have room for the left neighbor
+    errors: Vec<Rgb<f32>>,
+    next_errors: Vec<Rgb<f32>>,
+}
+
+#[cfg(not(any(not(feature = "std"), feature = "no_std")))]
+impl Ditherer {
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forget the diffused error, and start counting rows from 0
+    pub fn reset(&mut self) {
+        self.row = 0;
+        self.errors.clear();
+        self.next_errors.clear();
+    }
+
+    /// Convert the next row. The `y`, `u`, `v` and `out` slices must have the same length, or it panics.
+    pub fn convert_row<T: crate::convert::Sample>(&mut self, matrix: &Matrix<T>, y: &[T], u: &[T], v: &[T], out: &mut [Rgb<u8>]) {
+        use crate::convert::ToRGB;
+
+        assert!(y.len() == out.len() && u.len() == out.len() && v.len() == out.len(), "row lengths differ");
+
+        let row = self.row;
+        self.row += 1;
+        let pixels = y.iter().zip(u).zip(v).map(|((&y, &u), &v)| YUV { y, u, v });
+
+        if matrix.dither() != Dither::FloydSteinberg {
+            for (x, (out, px)) in out.iter_mut().zip(pixels).enumerate() {
+                *out = matrix.to_rgb_at(px, x, row);
+            }
+            return;
+        }
+
+        let zero = Rgb::new(0., 0., 0.);
+        let width = out.len();
+        self.errors.resize(width + 2, zero);
+        self.next_errors.clear();
+        self.next_errors.resize(width + 2, zero);
+
+        let scale = 255. / matrix.multiply;
+        for (x, (out, px)) in out.iter_mut().zip(pixels).enumerate() {
+            let c = matrix.to_rgbf(YUV { y: px.y.into(), u: px.u.into(), v: px.v.into() });
+            let e = self.errors[x + 1];
+            let wanted = Rgb {
+                r: c.r * scale + e.r,
+                g: c.g * scale + e.g,
+                b: c.b * scale + e.b,
+            };
+            let q = wanted.map(|c| (c + 0.5).clamp(0., 255.) as u8);
+            *out = q;
+            let err = Rgb {
+                r: wanted.r - f32::from(q.r),
+                g: wanted.g - f32::from(q.g),
+                b: wanted.b - f32::from(q.b),
+            };
+            let spread = |e: &mut Rgb<f32>, weight: f32| {
+                e.r += err.r * weight;
+                e.g += err.g * weight;
+                e.b += err.b * weight;
+            };
+            spread(&mut self.errors[x + 2], 7. / 16.);
+            spread(&mut self.next_errors[x], 3. / 16.);
+            spread(&mut self.next_errors[x + 1], 5. / 16.);
+            spread(&mut self.next_errors[x + 2], 1. / 16.);
+        }
+        core::mem::swap(&mut self.errors, &mut self.next_errors);
+    }
+}
+
+#[test]
+fn thresholds_are_centered() {
+    for mode in [Dither::Bayer, Dither::BlueNoise] {
+        let (w, h) = if mode == Dither::Bayer { (8, 8) } else { (16, 16) };
+        let sum: f32 = (0..h).flat_map(|y| (0..w).map(move |x| mode.threshold(x, y).unwrap())).sum();
+        assert!((sum / (w * h) as f32 - 0.5).abs() < 0.0001);
+    }
+    assert_eq!(None, Dither::None.threshold(3, 4));
+}
+
+#[test]
+#[cfg(not(any(not(feature = "std"), feature = "no_std")))]
+#[should_panic(expected = "row lengths differ")]
+fn ditherer_row_length_mismatch() {
+    use crate::color::{Depth, MatrixCoefficients, Range};
+    use crate::convert::RGBConvert;
+
+    let RGBConvert::Matrix(m) = RGBConvert::<u16>::new(Range::Limited, MatrixCoefficients::BT709, Depth::Depth10).unwrap() else { panic!() };
+    Ditherer::new().convert_row(&m.with_dither(Dither::FloydSteinberg), &[64; 4], &[512; 4], &[512; 4], &mut [Rgb::new(0, 0, 0); 3]);
+}
@@ -11,6 +11,12 @@ pub enum Error {
     UnsupportedTransferCharacteristics,
     UnsupportedMatrixCoefficients,
     InvalidDepthRequested,
+    InvalidFrameSize,
+    InvalidLevels,
+    NoEquivalent,
+    UnknownName,
+    InvalidIcc,
+    InvalidHeader,
 }
 
 impl error::Error for Error {}
@@ -21,6 +27,12 @@ impl fmt::Display for Error {
             Self::UnsupportedTransferCharacteristics => "Unsupported color space (transfer characteristics)",
             Self::UnsupportedMatrixCoefficients => "Unsupported color space (matrix coefficients)",
             Self::InvalidDepthRequested => "16-bit converter was asked to convert 8-bit color",
+            Self::InvalidFrameSize => "Planes or output are too small for the frame size",
+            Self::InvalidLevels => "Black must be below white, and levels must fit in the bit depth",
+            Self::NoEquivalent => "The other API has no exact equivalent of this color space",
+            Self::UnknownName => "Unrecognized color space name",
+            Self::InvalidIcc => "Malformed or unsupported ICC profile (must be RGB with a matrix and curves)",
+            Self::InvalidHeader => "Invalid or unsupported file header",
         })
     }
 }
@@ -0,0 +1,331 @@
+use crate::color::{BitDepth, Depth, MatrixCoefficients, Range};
+use crate::convert::{coeffs_for_matrix, Rounding, ToRGB};
+use crate::range;
+use crate::Error;
+use crate::YUV;
+use core::marker::PhantomData;
+use rgb::Rgb;
+
+/// Integer-only converter for `YCbCr` color spaces. See [`FixedMatrix::<u8>::new`](FixedMatrix#method.new)
+///
+/// Coefficients are fixed-point numbers derived from the same KR/KB values as [`Matrix`](crate::convert::Matrix) uses,
+/// with 14 fractional bits for 8-bit input, and 13 for 16-bit input.
+/// The conversion doesn't use floats at all, so its results are identical on every platform, and it works without an FPU.
+/// Only the constructor does float math.
+///
+/// [`FixedMatrix::libyuv`] and [`FixedMatrix::swscale`] match the rounding of these libraries exactly.
+#[derive(Debug, Copy, Clone)]
+pub struct FixedMatrix<T = u8> {
+    /// `y1 = (y * y_rep * y_mul) >> y_shift`
+    y_rep: u32,
+    y_mul: u32,
+    y_shift: u32,
+    ub: i32, ug: i32, vg: i32, vr: i32,
+    /// Constant offsets (range, chroma center, rounding) folded together
+    bb: i32, bg: i32, br: i32,
+    shift: u32,
+    max: i32,
+    uv_center: i32,
+    _pixel: PhantomData<T>,
+}
+
+/// Round half away from zero, without needing `std`
+#[inline]
+fn round(x: f64) -> i32 {
+    if x >= 0. { (x + 0.5) as i32 } else { (x - 0.5) as i32 }
+}
+
+impl<T> FixedMatrix<T> {
+    fn from_levels(kr: f64, kb: f64, [y_min, y_max, uv_min, uv_max]: [u16; 4], out_max: u16, shift: u32, rounding: Rounding) -> Self {
+        let kg = 1. - kr - kb;
+        let one = f64::from(1u32 << shift);
+        let out_scale = f64::from(out_max) + match rounding {
+            Rounding::Truncate => 1.,
+            Rounding::Nearest => 0.,
+        };
+        let y_scale = out_scale / f64::from(y_max - y_min) * one;
+        let uv_scale = out_scale / f64::from(uv_max - uv_min) * one;
+        let uv_center = if uv_min == 0 { (i32::from(uv_max) + 1) / 2 } else { (i32::from(uv_min) + i32::from(uv_max)) / 2 };
+        let y_mul = round(y_scale);
+        let ub = round(2. * (1. - kb) * uv_scale);
+        let ug = round(2. * (1. - kb) * kb / kg * uv_scale);
+        let vg = round(2. * (1. - kr) * kr / kg * uv_scale);
+        let vr = round(2. * (1. - kr) * uv_scale);
+        let y_bias = -y_mul * i32::from(y_min) + match rounding {
+            Rounding::Truncate => 0,
+            Rounding::Nearest => 1 << (shift - 1),
+        };
+        Self {
+            y_rep: 1,
+            y_mul: y_mul as u32,
+            y_shift: 0,
+            ub, ug, vg, vr,
+            bb: ub * uv_center - y_bias,
+            bg: (ug + vg) * uv_center + y_bias,
+            br: vr * uv_center - y_bias,
+            shift,
+            max: i32::from(out_max),
+            uv_center,
+            _pixel: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    fn convert(&self, y: u32, u: i32, v: i32) -> Rgb<i32> {
+        let y1 = ((y * self.y_rep * self.y_mul) >> self.y_shift) as i32;
+        // swscale's sums can overflow 32 bits, and it relies on them wrapping
+        Rgb {
+            r: (y1.wrapping_add(v.wrapping_mul(self.vr)).wrapping_sub(self.br) >> self.shift).clamp(0, self.max),
+            g: (y1.wrapping_add(self.bg).wrapping_sub(u.wrapping_mul(self.ug).wrapping_add(v.wrapping_mul(self.vg))) >> self.shift).clamp(0, self.max),
+            b: (y1.wrapping_add(u.wrapping_mul(self.ub)).wrapping_sub(self.bb) >> self.shift).clamp(0, self.max),
+        }
+    }
+}
+
+impl FixedMatrix<u8> {
+    /// 8-bit input and output, with 14-bit fractions.
+    ///
+    /// Use [`Rounding::Nearest`] to match other decoders, or [`Rounding::Truncate`] to match the default of the float [`RGBConvert`](crate::convert::RGBConvert).
+    pub fn new(range: Range, matrix: MatrixCoefficients, rounding: Rounding) -> Result<Self, Error> {
+        let (kr, kb) = coeffs_for_matrix(matrix).ok_or(Error::UnsupportedMatrixCoefficients)?;
+        Ok(Self::from_levels(kr, kb, range::levels(range, Depth::Depth8.into()), 255, 14, rounding))
+    }
+
+    /// Bit-exact with libyuv's reference C implementation (`YuvPixel` in `row_common.cc`).
+    ///
+    /// It uses libyuv's 6-bit coefficients, which are derived from the matrix rounded to 3 decimal places,
+    /// and its trick of scaling luma by `0x0101`. Like stock libyuv, the blue coefficient is capped at 128
+    /// (libyuv only allows larger values when built with `LIBYUV_UNLIMITED_DATA`).
+    pub fn libyuv(range: Range, matrix: MatrixCoefficients) -> Result<Self, Error> {
+        let (kr, kb) = coeffs_for_matrix(matrix).ok_or(Error::UnsupportedMatrixCoefficients)?;
+        let kg = 1. - kr - kb;
+        let (y_scale, uv_scale, y_min) = match range {
+            Range::Limited => (255. / 219., 255. / 224., 16.),
+            Range::Full => (1., 1., 0.),
+        };
+        // libyuv writes its constants with 3 decimal digits
+        let decimal3 = |x: f64| f64::from(round(x * 1000.)) / 1000.;
+        let to_q6 = |x: f64| round(decimal3(x) * 64.);
+        let yg = round(decimal3(y_scale) * 64. * 65536. / 257.);
+        let yb = round(decimal3(y_scale) * 64. * -y_min + 32.);
+        // libyuv's SIMD code multiplies with unsigned 8-bit UB
+        let ub = to_q6(2. * (1. - kb) * uv_scale).min(128);
+        let ug = to_q6(2. * (1. - kb) * kb / kg * uv_scale);
+        let vg = to_q6(2. * (1. - kr) * kr / kg * uv_scale);
+        let vr = to_q6(2. * (1. - kr) * uv_scale);
+        Ok(Self {
+            y_rep: 0x0101,
+            y_mul: yg as u32,
+            y_shift: 16,
+            ub, ug, vg, vr,
+            bb: ub * 128 - yb,
+            bg: (ug + vg) * 128 + yb,
+            br: vr * 128 - yb,
+            shift: 6,
+            max: 255,
+            uv_center: 128,
+            _pixel: PhantomData,
+        })
+    }
+}
+
+impl FixedMatrix<u8> {
+    /// Bit-exact with FFmpeg's swscale C code (`yuv2rgb_write_full` in `output.c`) used with `SWS_FULL_CHR_H_INT | SWS_ACCURATE_RND | SWS_BITEXACT` flags,
+    /// for 4:4:4 input, and default brightness, contrast and saturation.
+    ///
+    /// It uses swscale's table of coefficients (`ff_yuv2rgb_coeffs`) instead of computing them, and scales them the same way as `ff_yuv2rgb_c_init_tables`.
+    /// Other swscale flags use lookup tables or SIMD code that round differently.
+    pub fn swscale(range: Range, matrix: MatrixCoefficients) -> Result<Self, Error> {
+        // crv, cbu, cgu, cgv in 16.16 fixed point, for limited range chroma
+        let [crv, cbu, cgu, cgv]: [i64; 4] = match matrix {
+            MatrixCoefficients::BT709 => [117489, 138438, 13975, 34925],
+            MatrixCoefficients::FCC => [104448, 132798, 24759, 53109],
+            MatrixCoefficients::BT470BG | MatrixCoefficients::BT601 => [104597, 132201, 25675, 53279],
+            MatrixCoefficients::SMPTE240 => [117579, 136230, 16907, 35559],
+            MatrixCoefficients::BT2020NCL => [110013, 140363, 12277, 42626],
+            _ => return Err(Error::UnsupportedMatrixCoefficients),
+        };
+        // swscale negates the green coefficients before scaling them, and C division truncates towards zero like Rust's
+        let (cy, oy, [crv, cbu, cgu, cgv]) = match range {
+            Range::Limited => ((1 << 16) * 255 / 219, 16 << 16, [crv, cbu, -cgu, -cgv]),
+            Range::Full => (1 << 16, 0, [crv, cbu, -cgu, -cgv].map(|c| c * 224 / 255)),
+        };
+        // roundToInt16()
+        let round16 = |f: i64| ((f + (1 << 15)) >> 16).clamp(-0x8000, 0x7FFF) as i32;
+        let y_coeff = round16(cy << 13);
+        let y_bias = (1 << 21) - round16(oy << 9) * y_coeff;
+        // Y, U and V are scaled to 17 bits by the scaler
+        let [vr, ub, ug, vg] = [crv, cbu, cgu, cgv].map(|c| round16(c << 13) << 9);
+        let (ug, vg) = (-ug, -vg);
+        Ok(Self {
+            y_rep: 1 << 9,
+            y_mul: y_coeff as u32,
+            y_shift: 0,
+            ub, ug, vg, vr,
+            bb: ub * 128 - y_bias,
+            bg: (ug + vg) * 128 + y_bias,
+            br: vr * 128 - y_bias,
+            shift: 22,
+            max: 255,
+            uv_center: 128,
+            _pixel: PhantomData,
+        })
+    }
+}
+
+impl FixedMatrix<u16> {
+    /// Input with 8 to 16 bits of precision, and 16-bit output, with 13-bit fractions.
+    ///
+    /// Use [`Rounding::Nearest`] to match other decoders, or [`Rounding::Truncate`] to match the default of the float [`RGBConvert`](crate::convert::RGBConvert).
+    pub fn new(range: Range, matrix: MatrixCoefficients, depth: impl Into<BitDepth>, rounding: Rounding) -> Result<Self, Error> {
+        let (kr, kb) = coeffs_for_matrix(matrix).ok_or(Error::UnsupportedMatrixCoefficients)?;
+        Ok(Self::from_levels(kr, kb, range::levels(range, depth.into()), 65535, 13, rounding))
+    }
+}
+
+impl ToRGB<u8, u8> for FixedMatrix<u8> {
+    #[inline]
+    fn to_rgb(&self, px: YUV<u8>) -> Rgb<u8> {
+        let c = self.convert(px.y.into(), px.u.into(), px.v.into());
+        Rgb { r: c.r as u8, g: c.g as u8, b: c.b as u8 }
+    }
+
+    #[inline]
+    fn to_luma(&self, y: u8) -> u8 {
+        self.convert(y.into(), self.uv_center, self.uv_center).g as u8
+    }
+}
+
+impl ToRGB<u16, u16> for FixedMatrix<u16> {
+    #[inline]
+    fn to_rgb(&self, px: YUV<u16>) -> Rgb<u16> {
+        let c = self.convert(px.y.into(), px.u.into(), px.v.into());
+        Rgb { r: c.r as u16, g: c.g as u16, b: c.b as u16 }
+    }
+
+    #[inline]
+    fn to_luma(&self, y: u16) -> u16 {
+        self.convert(y.into(), self.uv_center, self.uv_center).g as u16
+    }
+}
+
+#[test]
+fn fixed_matches_float() {
+    use crate::convert::{Policy, RGBConvert};
+    let policy = Policy { rounding: Rounding::Nearest, ..Policy::default() };
+    for range in [Range::Full, Range::Limited] {
+        let float = RGBConvert::<u8>::new(range, MatrixCoefficients::BT709).unwrap().with_policy(policy);
+        let fixed = FixedMatrix::<u8>::new(range, MatrixCoefficients::BT709, Rounding::Nearest).unwrap();
+        for y in (0..=255).step_by(3) {
+            for u in (0..=255).step_by(5) {
+                for v in (0..=255).step_by(7) {
+                    let px = YUV { y, u, v };
+                    let a = float.to_rgb(px);
+                    let b = fixed.to_rgb(px);
+                    assert!(a.r.abs_diff(b.r) <= 1 && a.g.abs_diff(b.g) <= 1 && a.b.abs_diff(b.b) <= 1, "{px:?} {a:?} {b:?}");
+                }
+            }
+        }
+    }
+
+    let float = RGBConvert::<u16>::new(Range::Limited, MatrixCoefficients::BT2020NCL, Depth::Depth10).unwrap().with_policy(policy);
+    let fixed = FixedMatrix::<u16>::new(Range::Limited, MatrixCoefficients::BT2020NCL, Depth::Depth10, Rounding::Nearest).unwrap();
+    for y in (0..1024).step_by(7) {
+        for uv in (0..1024).step_by(11) {
+            let px = YUV { y, u: uv, v: 1023 - uv };
+            let a = float.to_rgb(px);
+            let b = fixed.to_rgb(px);
+            assert!(a.r.abs_diff(b.r) <= 1 && a.g.abs_diff(b.g) <= 1 && a.b.abs_diff(b.b) <= 1, "{px:?} {a:?} {b:?}");
+        }
+    }
+}
+
+#[test]
+fn libyuv_constants() {
+    let m = FixedMatrix::<u8>::libyuv(Range::Limited, MatrixCoefficients::BT601).unwrap();
+    assert_eq!((18997, 128, 25, 52, 102), (m.y_mul, m.ub, m.ug, m.vg, m.vr));
+    assert_eq!(128 * 128 + 1160, m.bb);
+    let m = FixedMatrix::<u8>::libyuv(Range::Limited, MatrixCoefficients::BT709).unwrap();
+    assert_eq!((18997, 128, 14, 34, 115), (m.y_mul, m.ub, m.ug, m.vg, m.vr));
+    let m = FixedMatrix::<u8>::libyuv(Range::Full, MatrixCoefficients::BT601).unwrap();
+    assert_eq!((16320, 113, 22, 46, 90), (m.y_mul, m.ub, m.ug, m.vg, m.vr));
+    assert_eq!(Rgb::new(0, 0, 0), m.to_rgb(YUV { y: 0, u: 128, v: 128 }));
+    assert_eq!(Rgb::new(255, 255, 255), m.to_rgb(YUV { y: 255, u: 128, v: 128 }));
+}
+
+#[test]
+fn libyuv_pixels() {
+    // libyuv's YuvPixel from row_common.cc, with the constants as written there (YG, YB, UB, UG, VG, VR)
+    fn yuv_pixel([yg, yb, ub, ug, vg, vr]: [i32; 6], YUV { y, u, v }: YUV<u8>) -> Rgb<u8> {
+        let (y, u, v) = (i32::from(y), i32::from(u), i32::from(v));
+        let y1 = ((y * 0x0101 * yg) as u32 >> 16) as i32;
+        let clamp = |x: i32| (x >> 6).clamp(0, 255) as u8;
+        Rgb {
+            r: clamp(y1 + v * vr - (vr * 128 - yb)),
+            g: clamp(y1 + (ug * 128 + vg * 128 + yb) - (u * ug + v * vg)),
+            b: clamp(y1 + u * ub - (ub * 128 - yb)),
+        }
+    }
+    for (range, matrix, consts) in [
+        (Range::Limited, MatrixCoefficients::BT601, [18997, -1160, 128, 25, 52, 102]),
+        (Range::Limited, MatrixCoefficients::BT709, [18997, -1160, 128, 14, 34, 115]),
+        (Range::Full, MatrixCoefficients::BT601, [16320, 32, 113, 22, 46, 90]),
+        (Range::Full, MatrixCoefficients::BT709, [16320, 32, 119, 12, 30, 101]),
+    ] {
+        let m = FixedMatrix::<u8>::libyuv(range, matrix).unwrap();
+        for y in 0..=255 {
+            for u in (0..=255).step_by(3) {
+                for v in (0..=255).step_by(5) {
+                    let px = YUV { y, u, v };
+                    assert_eq!(yuv_pixel(consts, px), m.to_rgb(px), "{px:?} {range:?} {matrix:?}");
+                }
+            }
+        }
+    }
+
+    // I420ToARGB results for 75% color bars
+    let m = FixedMatrix::<u8>::libyuv(Range::Limited, MatrixCoefficients::BT601).unwrap();
+    assert_eq!(Rgb::new(0, 0, 0), m.to_rgb(YUV { y: 16, u: 128, v: 128 }));
+    assert_eq!(Rgb::new(255, 255, 255), m.to_rgb(YUV { y: 235, u: 128, v: 128 }));
+    assert_eq!(Rgb::new(191, 0, 1), m.to_rgb(YUV { y: 65, u: 100, v: 212 }));
+}
+
+#[test]
+fn swscale_pixels() {
+    // swscale's yuv2rgb_write_full, with yuv2rgb_y_coeff, y_offset, v2r, v2g, u2g and u2b coefficients as initialized by it
+    fn write_full([y_coeff, y_offset, v2r, v2g, u2g, u2b]: [i32; 6], YUV { y, u, v }: YUV<u8>) -> Rgb<u8> {
+        let (y, u, v) = (i32::from(y) << 9, (i32::from(u) - 128) << 9, (i32::from(v) - 128) << 9);
+        let y = ((y - y_offset) * y_coeff + (1 << 21)) as u32;
+        let [r, g, b] = [
+            y.wrapping_add((v as u32).wrapping_mul(v2r as u32)),
+            y.wrapping_add((v as u32).wrapping_mul(v2g as u32)).wrapping_add((u as u32).wrapping_mul(u2g as u32)),
+            y.wrapping_add((u as u32).wrapping_mul(u2b as u32)),
+        ].map(|c| c as i32);
+        // av_clip_uintp2(c, 30)
+        let clip = |c: i32| if c & !((1 << 30) - 1) != 0 { ((!c) >> 31) & ((1 << 30) - 1) } else { c };
+        Rgb { r: (clip(r) >> 22) as u8, g: (clip(g) >> 22) as u8, b: (clip(b) >> 22) as u8 }
+    }
+    for (range, matrix, consts) in [
+        (Range::Limited, MatrixCoefficients::BT601, [9539, 8192, 13075, -6660, -3209, 16525]),
+        (Range::Limited, MatrixCoefficients::BT709, [9539, 8192, 14686, -4366, -1747, 17305]),
+        (Range::Limited, MatrixCoefficients::BT2020NCL, [9539, 8192, 13752, -5328, -1535, 17545]),
+        (Range::Full, MatrixCoefficients::BT601, [8192, 0, 11485, -5850, -2819, 14516]),
+        (Range::Full, MatrixCoefficients::BT709, [8192, 0, 12901, -3835, -1534, 15201]),
+    ] {
+        let m = FixedMatrix::<u8>::swscale(range, matrix).unwrap();
+        for y in 0..=255 {
+            for u in (0..=255).step_by(3) {
+                for v in (0..=255).step_by(5) {
+                    let px = YUV { y, u, v };
+                    assert_eq!(write_full(consts, px), m.to_rgb(px), "{px:?} {range:?} {matrix:?}");
+                }
+            }
+        }
+    }
+
+    let m = FixedMatrix::<u8>::swscale(Range::Limited, MatrixCoefficients::BT601).unwrap();
+    assert_eq!(Rgb::new(0, 0, 0), m.to_rgb(YUV { y: 16, u: 128, v: 128 }));
+    assert_eq!(Rgb::new(255, 255, 255), m.to_rgb(YUV { y: 235, u: 128, v: 128 }));
+    assert_eq!(128, m.to_luma(126));
+    assert!(FixedMatrix::<u8>::swscale(Range::Full, MatrixCoefficients::YCgCo).is_err());
+}
@@ -0,0 +1,427 @@
+//! Planar frames, and conversion of whole frames to RGB with chroma upsampling.
+//!
+//! Subsampled chroma is upsampled with bilinear interpolation, taking [`ChromaSamplePosition`] into account.
+
+use crate::color::{ChromaSamplePosition, ChromaSampling};
+#[cfg(not(any(not(feature = "std"), feature = "no_std")))]
+use crate::color::{BitDepth, MatrixCoefficients, Range};
+use crate::convert::{Sample, ToRGB};
+#[cfg(not(any(not(feature = "std"), feature = "no_std")))]
+use crate::convert::YUVConvert;
+use crate::Error;
+use rgb::Rgb;
+
+/// Borrowed planar YUV image, e.g. from an AV1 or JPEG decoder.
+///
+/// Chroma planes are half the width (rounded up) for 4:2:2 and 4:2:0, and half the height too for 4:2:0.
+/// Strides are in samples, not bytes.
+#[derive(Debug, Copy, Clone)]
+pub struct YUVFrameRef<'a, T> {
+    pub width: usize,
+    pub height: usize,
+    pub y: &'a [T],
+    pub y_stride: usize,
+    /// Ignored for [`ChromaSampling::Monochrome`]
+    pub u: &'a [T],
+    /// Ignored for [`ChromaSampling::Monochrome`]
+    pub v: &'a [T],
+    pub uv_stride: usize,
+    pub sampling: ChromaSampling,
+    /// Where subsampled chroma samples are sited. `None` means centered between luma samples, as in JPEG.
+    pub chroma_position: Option<ChromaSamplePosition>,
+}
+
+/// Owned planar YUV image, with planes without padding between rows, e.g. read from a file.
+///
+/// Use [`YUVFrame::as_frame_ref`] to convert it.
+#[cfg(not(any(not(feature = "std"), feature = "no_std")))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct YUVFrame<T> {
+    pub width: usize,
+    pub height: usize,
+    pub y: Vec<T>,
+    /// Empty for [`ChromaSampling::Monochrome`]
+    pub u: Vec<T>,
+    /// Empty for [`ChromaSampling::Monochrome`]
+    pub v: Vec<T>,
+    pub sampling: ChromaSampling,
+    /// Where subsampled chroma samples are sited. `None` means centered between luma samples, as in JPEG.
+    pub chroma_position: Option<ChromaSamplePosition>,
+}
+
+#[cfg(not(any(not(feature = "std"), feature = "no_std")))]
+impl<T: Sample> YUVFrame<T> {
+    /// Borrow the planes
+    #[inline]
+    #[must_use]
+    pub fn as_frame_ref(&self) -> YUVFrameRef<'_, T> {
+        YUVFrameRef {
+            width: self.width,
+            height: self.height,
+            y: &self.y,
+            y_stride: self.width,
+            u: &self.u,
+            v: &self.v,
+            uv_stride: chroma_size(self.width, self.height, self.sampling).0,
+            sampling: self.sampling,
+            chroma_position: self.chroma_position,
+        }
+    }
+}
+
+#[cfg(not(any(not(feature = "std"), feature = "no_std")))]
+impl From<YUVFrame<u8>> for YUVFrame<u16> {
+    /// Widens the samples without scaling them, so they keep their 8-bit code values
+    fn from(frame: YUVFrame<u8>) -> Self {
+        Self {
+            y: frame.y.into_iter().map(u16::from).collect(),
+            u: frame.u.into_iter().map(u16::from).collect(),
+            v: frame.v.into_iter().map(u16::from).collect(),
+            width: frame.width,
+            height: frame.height,
+            sampling: frame.sampling,
+            chroma_position: frame.chroma_position,
+        }
+    }
+}
+
+#[cfg(not(any(not(feature = "std"), feature = "no_std")))]
+impl YUVFrame<u16> {
+    /// Converts RGB pixels with `rgb_depth` bits, in rows without padding, to YUV with `(range, matrix, depth)` of the `to`, for encoding.
+    ///
+    /// Subsampled chroma is the average of the pixels it covers, and is centered.
+    pub fn from_rgb(width: usize, height: usize, rgb: &[Rgb<u16>], rgb_depth: impl Into<BitDepth>, to: (Range, MatrixCoefficients, impl Into<BitDepth>), sampling: ChromaSampling) -> Result<Self, Error> {
+        if width.checked_mul(height) != Some(rgb.len()) {
+            return Err(Error::InvalidFrameSize);
+        }
+        // GBR planes are YUV with the identity matrix
+        let [mut g, mut b, mut r] = [0, 1, 2].map(|_| Vec::with_capacity(rgb.len()));
+        for px in rgb {
+            r.push(px.r);
+            g.push(px.g);
+            b.push(px.b);
+        }
+        let conv = YUVConvert::<u16, u16>::new((Range::Full, MatrixCoefficients::Identity, rgb_depth), to)?;
+        let [mut y, mut u, mut v] = [0, 1, 2].map(|_| vec![0; rgb.len()]);
+        conv.convert_frame(&YUVFrameRef::new(width, height, &g, &b, &r, ChromaSampling::Cs444)?, &mut y, &mut u, &mut v)?;
+        Ok(Self {
+            width,
+            height,
+            y,
+            u: subsample(&u, width, height, sampling),
+            v: subsample(&v, width, height, sampling),
+            sampling,
+            chroma_position: None,
+        })
+    }
+}
+
+/// Averages of the 4:4:4 chroma covered by each subsampled chroma sample (centered siting)
+#[cfg(not(any(not(feature = "std"), feature = "no_std")))]
+fn subsample(plane: &[u16], width: usize, height: usize, sampling: ChromaSampling) -> Vec<u16> {
+    let (bw, bh) = match sampling {
+        ChromaSampling::Cs420 => (2, 2),
+        ChromaSampling::Cs422 => (2, 1),
+        ChromaSampling::Cs444 => return plane.to_vec(),
+        ChromaSampling::Monochrome => return Vec::new(),
+    };
+    let (cw, ch) = chroma_size(width, height, sampling);
+    let mut out = Vec::with_capacity(cw * ch);
+    for cy in (0..height).step_by(bh) {
+        for cx in (0..width).step_by(bw) {
+            let (mut sum, mut n) = (0, 0);
+            for row in cy..(cy + bh).min(height) {
+                for &v in &plane[row * width..][cx..(cx + bw).min(width)] {
+                    sum += u32::from(v);
+                    n += 1;
+                }
+            }
+            out.push(((sum + n / 2) / n) as u16);
+        }
+    }
+    out
+}
+
+/// Whether `len` samples have room for `height` rows of `width` samples, `stride` apart. Sizes that overflow don't fit.
+fn plane_fits(len: usize, stride: usize, width: usize, height: usize) -> bool {
+    height == 0 || (stride >= width && stride.checked_mul(height - 1).and_then(|n| n.checked_add(width)).is_some_and(|n| len >= n))
+}
+
+/// Rows converted by one parallel task. Even, so that 4:2:0 chroma row pairs aren't split.
+#[cfg(feature = "rayon")]
+const BAND_ROWS: usize = 16;
+
+/// Pixels upsampled at a time, into buffers on the stack
+const CHUNK: usize = 128;
+
+impl<'a, T: Sample> YUVFrameRef<'a, T> {
+    /// Planes without padding between rows, and chroma sited in the center
+    pub fn new(width: usize, height: usize, y: &'a [T], u: &'a [T], v: &'a [T], sampling: ChromaSampling) -> Result<Self, Error> {
+        let frame = Self {
+            width,
+            height,
+            y,
+            y_stride: width,
+            u,
+            v,
+            uv_stride: chroma_size(width, height, sampling).0,
+            sampling,
+            chroma_position: None,
+        };
+        frame.check()?;
+        Ok(frame)
+    }
+
+    /// Set where subsampled chroma is sited (e.g. from AV1's `chroma_sample_position`)
+    #[inline]
+    #[must_use]
+    pub fn with_chroma_position(mut self, chroma_position: Option<ChromaSamplePosition>) -> Self {
+        self.chroma_position = chroma_position;
+        self
+    }
+
+    /// Width and height of the U and V planes
+    #[inline]
+    #[must_use]
+    pub fn chroma_size(&self) -> (usize, usize) {
+        chroma_size(self.width, self.height, self.sampling)
+    }
+
+    pub(crate) fn check(&self) -> Result<(), Error> {
+        let fits = |plane: &[T], stride: usize, (width, height): (usize, usize)| plane_fits(plane.len(), stride, width, height);
+        if !fits(self.y, self.y_stride, (self.width, self.height)) {
+            return Err(Error::InvalidFrameSize);
+        }
+        let chroma = self.chroma_size();
+        if !fits(self.u, self.uv_stride, chroma) || !fits(self.v, self.uv_stride, chroma) {
+            return Err(Error::InvalidFrameSize);
+        }
+        Ok(())
+    }
+
+    fn check_output<O>(&self, out: &[Rgb<O>], out_stride: usize) -> Result<(), Error> {
+        self.check()?;
+        if !plane_fits(out.len(), out_stride, self.width, self.height) {
+            return Err(Error::InvalidFrameSize);
+        }
+        Ok(())
+    }
+
+    /// Convert the whole frame. `out` has `out_stride` pixels per row.
+    pub fn convert<O: Copy>(&self, conv: &impl ToRGB<T, O>, out: &mut [Rgb<O>], out_stride: usize) -> Result<(), Error> {
+        self.check_output(out, out_stride)?;
+        if self.width == 0 {
+            return Ok(());
+        }
+        for (row, out) in out.chunks_mut(out_stride).take(self.height).enumerate() {
+            self.convert_row(conv, row, out);
+        }
+        Ok(())
+    }
+
+    /// Convert the whole frame using all cores. The result is identical to [`YUVFrameRef::convert`].
+    ///
+    /// The frame is split into bands of rows. Every band reads the chroma rows it needs to upsample its edges,
+    /// even if the neighboring band reads them too, so bands don't depend on each other.
+    #[cfg(feature = "rayon")]
+    pub fn par_convert<O: Copy + Send>(&self, conv: &(impl ToRGB<T, O> + Sync), out: &mut [Rgb<O>], out_stride: usize) -> Result<(), Error> where T: Sync {
+        use rayon::prelude::*;
+
+        self.check_output(out, out_stride)?;
+        if self.width == 0 || self.height == 0 {
+            return Ok(());
+        }
+        let out = &mut out[..out_stride * self.height - (out_stride - self.width)];
+        out.par_chunks_mut(out_stride * BAND_ROWS).enumerate().for_each(|(band, out)| {
+            for (i, out) in out.chunks_mut(out_stride).enumerate() {
+                self.convert_row(conv, band * BAND_ROWS + i, out);
+            }
+        });
+        Ok(())
+    }
+
+    fn convert_row<O: Copy>(&self, conv: &impl ToRGB<T, O>, row: usize, out: &mut [Rgb<O>]) {
+        let out = &mut out[..self.width];
+        let y = &self.y[row * self.y_stride..][..self.width];
+        match self.sampling {
+            ChromaSampling::Monochrome => {
+                for (out, &y) in out.iter_mut().zip(y) {
+                    let l = conv.to_luma(y);
+                    *out = Rgb { r: l, g: l, b: l };
+                }
+            },
+            ChromaSampling::Cs444 => {
+                let u = &self.u[row * self.uv_stride..][..self.width];
+                let v = &self.v[row * self.uv_stride..][..self.width];
+                conv.to_rgb_row_at(y, u, v, out, 0, row);
+            },
+            ChromaSampling::Cs422 | ChromaSampling::Cs420 => {
+                let mut u_buf = [y[0]; CHUNK];
+                let mut v_buf = [y[0]; CHUNK];
+                for (chunk, (y, out)) in y.chunks(CHUNK).zip(out.chunks_mut(CHUNK)).enumerate() {
+                    let n = y.len();
+                    self.upsample_chroma(row, chunk * CHUNK, &mut u_buf[..n], &mut v_buf[..n]);
+                    conv.to_rgb_row_at(y, &u_buf[..n], &v_buf[..n], out, chunk * CHUNK, row);
+                }
+            },
+        }
+    }
+
+    /// Chroma for luma pixels `x..x + u.len()` in the `row` of a 4:2:2 or 4:2:0 frame
+    pub(crate) fn upsample_chroma(&self, row: usize, x: usize, u: &mut [T], v: &mut [T]) {
+        let (chroma_width, chroma_height) = self.chroma_size();
+        let (row0, row1, [vw0, vw1]) = if self.sampling == ChromaSampling::Cs420 {
+            taps(row, chroma_height, self.chroma_position == Some(ChromaSamplePosition::Colocated))
+        } else {
+            (row, row, [4, 0])
+        };
+        let h_cosited = self.chroma_position.is_some();
+        let rows = |plane: &'a [T]| (&plane[row0 * self.uv_stride..][..chroma_width], &plane[row1 * self.uv_stride..][..chroma_width]);
+        let (u0, u1) = rows(self.u);
+        let (v0, v1) = rows(self.v);
+
+        for (i, (u, v)) in u.iter_mut().zip(v).enumerate() {
+            let (c0, c1, [hw0, hw1]) = taps(x + i, chroma_width, h_cosited);
+            let weights = [hw0 * vw0, hw1 * vw0, hw0 * vw1, hw1 * vw1];
+            *u = T::blend([u0[c0], u0[c1], u1[c0], u1[c1]], weights);
+            *v = T::blend([v0[c0], v0[c1], v1[c0], v1[c1]], weights);
+        }
+    }
+}
+
+pub(crate) fn chroma_size(width: usize, height: usize, sampling: ChromaSampling) -> (usize, usize) {
+    match sampling {
+        ChromaSampling::Cs444 => (width, height),
+        ChromaSampling::Cs422 => (width.div_ceil(2), height),
+        ChromaSampling::Cs420 => (width.div_ceil(2), height.div_ceil(2)),
+        ChromaSampling::Monochrome => (0, 0),
+    }
+}
+
+/// Two nearest chroma samples for luma sample `i` along a subsampled axis, and their weights in quarters
+#[inline]
+fn taps(i: usize, chroma_len: usize, cosited: bool) -> (usize, usize, [u8; 2]) {
+    let c = i / 2;
+    let next = (c + 1).min(chroma_len - 1);
+    match (cosited, i % 2 == 0) {
+        (true, true) => (c, c, [4, 0]),
+        (true, false) => (c, next, [2, 2]),
+        // the chroma sample is between luma samples 2c and 2c+1
+        (false, true) => (c, c.saturating_sub(1), [3, 1]),
+        (false, false) => (c, next, [3, 1]),
+    }
+}
+
+#[test]
+fn upsampling() {
+    use crate::color::{MatrixCoefficients, Range};
+    use crate::convert::RGBConvert;
+
+    // GBR makes it easy to see the chroma values
+    let conv = RGBConvert::<u8>::new(Range::Full, MatrixCoefficients::Identity).unwrap();
+    let y = [100; 4 * 2];
+    let u = [0, 80];
+    let v = [40, 40];
+    let frame = YUVFrameRef::new(4, 2, &y, &u, &v, ChromaSampling::Cs420).unwrap();
+    let mut out = [Rgb::new(0, 0, 0); 4 * 2];
+    frame.convert(&conv, &mut out, 4).unwrap();
+    assert_eq!([0, 20, 60, 80], [0, 1, 2, 3].map(|x| out[x].b));
+    assert_eq!(out[..4], out[4..]);
+    assert!(out.iter().all(|px| px.r == 40 && px.g == 100));
+
+    let mut out = [Rgb::new(0, 0, 0); 4 * 2];
+    frame.with_chroma_position(Some(ChromaSamplePosition::Vertical)).convert(&conv, &mut out, 4).unwrap();
+    assert_eq!([0, 40, 80, 80], [0, 1, 2, 3].map(|x| out[x].b));
+
+    let y = [0_u8; 5 * 3];
+    let uv = [0_u8; 3 * 2];
+    assert!(YUVFrameRef::new(5, 3, &y, &uv, &uv, ChromaSampling::Cs420).is_ok());
+    assert!(YUVFrameRef::new(5, 3, &y, &uv[1..], &uv, ChromaSampling::Cs420).is_err());
+    let frame = YUVFrameRef::new(5, 3, &y, &uv, &uv, ChromaSampling::Cs420).unwrap();
+    assert!(frame.convert(&conv, &mut [Rgb::new(0, 0, 0); 5 * 3 - 1], 5).is_err());
+}
+
+#[cfg(all(feature = "rayon", not(any(not(feature = "std"), feature = "no_std"))))]
+#[test]
+fn parallel_is_identical() {
+    use crate::color::{Depth, MatrixCoefficients, Range};
+    use crate::convert::RGBConvert;
+
+    let (width, height) = (301, 77);
+    let y: Vec<u16> = (0..width * height).map(|i| (i * 7 % 1024) as u16).collect();
+    let u: Vec<u16> = (0..width * height).map(|i| (i * 13 % 1024) as u16).collect();
+    let v: Vec<u16> = (0..width * height).map(|i| (i * 29 % 1024) as u16).collect();
+    let conv = RGBConvert::<u16>::new(Range::Limited, MatrixCoefficients::BT2020NCL, Depth::Depth10).unwrap();
+    for sampling in [ChromaSampling::Cs420, ChromaSampling::Cs422, ChromaSampling::Cs444, ChromaSampling::Monochrome] {
+        for position in [None, Some(ChromaSamplePosition::Colocated)] {
+            let frame = YUVFrameRef::new(width, height, &y, &u, &v, sampling).unwrap().with_chroma_position(position);
+            let mut serial = vec![Rgb::new(0, 0, 0); (width + 3) * height];
+            let mut parallel = serial.clone();
+            frame.convert(&conv, &mut serial, width + 3).unwrap();
+            frame.par_convert(&conv, &mut parallel, width + 3).unwrap();
+            assert!(serial == parallel);
+        }
+    }
+
+    let empty = YUVFrameRef::new(4, 0, &y[..0], &u[..0], &v[..0], ChromaSampling::Cs420).unwrap();
+    let (mut serial, mut parallel) = (Vec::<Rgb<u16>>::new(), Vec::<Rgb<u16>>::new());
+    empty.convert(&conv, &mut serial, 8).unwrap();
+    empty.par_convert(&conv, &mut parallel, 8).unwrap();
+}
+
+#[test]
+#[cfg(not(any(not(feature = "std"), feature = "no_std")))]
+fn rgb_to_frame() {
+    let plane = [0, 2, 10, 4, 6, 10];
+    assert_eq!(subsample(&plane, 3, 2, ChromaSampling::Cs420), [3, 10]);
+    assert_eq!(subsample(&plane, 3, 2, ChromaSampling::Cs422), [1, 10, 5, 10]);
+
+    use crate::color::Depth;
+    let gray = [Rgb::new(1000u16, 1000, 1000); 6];
+    let frame = YUVFrame::from_rgb(3, 2, &gray, Depth::Depth10, (Range::Limited, MatrixCoefficients::BT709, Depth::Depth10), ChromaSampling::Cs420).unwrap();
+    assert_eq!((frame.u.len(), frame.v.len()), (2, 2));
+    assert!(frame.u.iter().chain(&frame.v).all(|&c| c == 512));
+    assert!(YUVFrame::from_rgb(3, 3, &gray, Depth::Depth10, (Range::Full, MatrixCoefficients::BT709, Depth::Depth8), ChromaSampling::Cs444).is_err());
+}
+
+#[test]
+#[cfg(not(any(not(feature = "std"), feature = "no_std")))]
+fn frame_dithering() {
+    use crate::color::{Depth, MatrixCoefficients, Range};
+    use crate::convert::RGBConvert;
+    use crate::dither::Dither;
+    use crate::YUV;
+
+    let RGBConvert::Matrix(m) = RGBConvert::<u16>::new(Range::Limited, MatrixCoefficients::BT709, Depth::Depth10).unwrap() else { panic!() };
+    let m = m.with_dither(Dither::Bayer);
+    let (width, height) = (70, 3);
+    let y: Vec<u16> = (0..width * height).map(|i| 300 + (i % 7) as u16).collect();
+    let uv = vec![512u16; width * height];
+    let frame = YUVFrameRef::new(width, height, &y, &uv, &uv, ChromaSampling::Cs444).unwrap();
+    let mut out = vec![Rgb::new(0u8, 0, 0); width * height];
+    frame.convert(&m, &mut out, width).unwrap();
+    for (i, out) in out.iter().enumerate() {
+        let (x, row) = (i % width, i / width);
+        assert_eq!(m.to_rgb_at(YUV { y: y[i], u: 512, v: 512 }, x, row), *out, "{x},{row}");
+    }
+    let mut plain = out.clone();
+    frame.convert(&m.with_dither(Dither::None), &mut plain, width).unwrap();
+    assert_ne!(out, plain);
+}
+
+#[test]
+fn overflowing_strides() {
+    use crate::color::{MatrixCoefficients, Range};
+    use crate::convert::RGBConvert;
+
+    let plane = [16u8; 4];
+    let mut frame = YUVFrameRef::new(1, 1, &plane, &plane, &plane, ChromaSampling::Cs444).unwrap();
+    frame.height = 3;
+    frame.y_stride = usize::MAX / 2 + 1;
+    assert!(frame.check().is_err());
+
+    let frame = YUVFrameRef::new(1, 3, &plane[..3], &plane[..3], &plane[..3], ChromaSampling::Cs444).unwrap();
+    let conv = RGBConvert::<u8>::new(Range::Full, MatrixCoefficients::BT709).unwrap();
+    let mut out = [Rgb::new(0, 0, 0); 4];
+    assert!(frame.convert(&conv, &mut out, usize::MAX / 2 + 1).is_err());
+}
@@ -0,0 +1,246 @@
+//! Fills in colorimetry that a file or stream left unspecified, the way players commonly guess it.
+//!
+//! The rules follow FFmpeg/mpv for video (SD is BT.601 with 525 or 625-line primaries picked by height, HD is BT.709),
+//! and libavif and browsers for still images (BT.601 matrix with sRGB primaries and transfer, full range for JPEG).
+//! Every filled-in value comes with the [`Rule`] that picked it, so it can be logged.
+//!
+//! ```
+//! # use yuv::color::*;
+//! # use yuv::guess::*;
+//! let signalled = Signalled { matrix: Some(MatrixCoefficients::BT2020NCL), ..Signalled::default() };
+//! let context = Context::new(3840, 2160, Depth::Depth10, ChromaSampling::Cs420);
+//! let guess = fill(&signalled, &context);
+//! assert_eq!(guess.colorimetry.primaries, ColorPrimaries::BT2020);
+//! assert_eq!(guess.primaries, Rule::FromMatrix);
+//! ```
+
+use crate::color::{ChromaSamplePosition, ChromaSampling, Colorimetry, ColorPrimaries, BitDepth, MatrixCoefficients, Range, TransferCharacteristics};
+
+/// Values that were present in the file. `None` for "unspecified" (CICP code 2) or missing.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct Signalled {
+    pub matrix: Option<MatrixCoefficients>,
+    pub range: Option<Range>,
+    pub primaries: Option<ColorPrimaries>,
+    pub transfer: Option<TransferCharacteristics>,
+    pub chroma_position: Option<ChromaSamplePosition>,
+}
+
+/// Kind of file the image came from
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub enum Container {
+    /// Video stream or raw frames
+    #[default]
+    Video,
+    /// JPEG (JFIF)
+    Jpeg,
+    /// Lossy WebP (VP8)
+    WebP,
+    /// AVIF
+    Avif,
+    /// HEIF or HEIC
+    Heif,
+}
+
+impl Container {
+    fn is_image(self) -> bool {
+        self != Self::Video
+    }
+}
+
+/// Properties of the image known apart from its colorimetry
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Context {
+    pub width: usize,
+    pub height: usize,
+    pub depth: BitDepth,
+    pub chroma_sampling: ChromaSampling,
+    pub container: Container,
+}
+
+impl Context {
+    /// A video frame
+    #[must_use]
+    pub fn new(width: usize, height: usize, depth: impl Into<BitDepth>, chroma_sampling: ChromaSampling) -> Self {
+        Self { width, height, depth: depth.into(), chroma_sampling, container: Container::Video }
+    }
+
+    /// Set what kind of file it's from
+    #[must_use]
+    pub fn with_container(mut self, container: Container) -> Self {
+        self.container = container;
+        self
+    }
+
+    /// mpv's rule: at least 1280 wide, or taller than 576 lines
+    fn is_hd(&self) -> bool {
+        self.width >= 1280 || self.height > 576
+    }
+}
+
+/// Why a value has been chosen
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Rule {
+    /// It was in the file
+    Signalled,
+    /// Convention of the [`Container`]: JFIF, and libavif and browsers for unspecified values in AVIF, HEIF and WebP
+    Container,
+    /// HD size (at least 1280 wide, or taller than 576 lines) is BT.709
+    HdSize,
+    /// SD with 576 or 288 lines is 625-line (PAL) BT.601
+    Sd625,
+    /// Other SD sizes are 525-line (NTSC) BT.601
+    Sd525,
+    /// Follows the signalled matrix coefficients
+    FromMatrix,
+    /// Follows the signalled color primaries
+    FromPrimaries,
+    /// RGB ([`MatrixCoefficients::Identity`]) is full range
+    Rgb,
+    /// FFmpeg's assumption: limited range, the BT.709 transfer function, and MPEG-2 (left) chroma siting
+    VideoDefault,
+    /// Chroma isn't subsampled, so there's no chroma position
+    NotSubsampled,
+}
+
+/// Complete colorimetry, and the rule behind each of its values
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Guess {
+    pub colorimetry: Colorimetry,
+    pub matrix: Rule,
+    pub range: Rule,
+    pub primaries: Rule,
+    pub transfer: Rule,
+    pub chroma_position: Rule,
+}
+
+/// Keeps the signalled values, and guesses the rest from the context.
+///
+/// Chroma position is `None` for images from still image containers, which use centered chroma (not representable by [`ChromaSamplePosition`]).
+#[must_use]
+pub fn fill(signalled: &Signalled, context: &Context) -> Guess {
+    let image = context.container.is_image();
+
+    let by_size = || {
+        if context.is_hd() {
+            (ColorPrimaries::BT709, Rule::HdSize)
+        } else if context.height == 576 || context.height == 288 {
+            (ColorPrimaries::BT470BG, Rule::Sd625)
+        } else {
+            (ColorPrimaries::BT601, Rule::Sd525)
+        }
+    };
+
+    let (primaries, primaries_rule) = match (signalled.primaries, signalled.matrix) {
+        (Some(p), _) => (p, Rule::Signalled),
+        (None, _) if image => (ColorPrimaries::BT709, Rule::Container),
+        (None, Some(MatrixCoefficients::BT2020NCL | MatrixCoefficients::BT2020CL)) => (ColorPrimaries::BT2020, Rule::FromMatrix),
+        (None, Some(MatrixCoefficients::BT709)) => (ColorPrimaries::BT709, Rule::FromMatrix),
+        (None, Some(MatrixCoefficients::BT470BG)) => (ColorPrimaries::BT470BG, Rule::FromMatrix),
+        (None, _) => by_size(),
+    };
+
+    let (matrix, matrix_rule) = match signalled.matrix {
+        Some(m) => (m, Rule::Signalled),
+        None if image => (MatrixCoefficients::BT601, Rule::Container),
+        None => {
+            let m = match primaries {
+                ColorPrimaries::BT2020 => MatrixCoefficients::BT2020NCL,
+                ColorPrimaries::BT470BG => MatrixCoefficients::BT470BG,
+                ColorPrimaries::BT601 => MatrixCoefficients::BT601,
+                ColorPrimaries::BT709 => MatrixCoefficients::BT709,
+                _ => if context.is_hd() { MatrixCoefficients::BT709 } else { MatrixCoefficients::BT601 },
+            };
+            (m, if primaries_rule == Rule::Signalled { Rule::FromPrimaries } else { primaries_rule })
+        },
+    };
+
+    let (range, range_rule) = match signalled.range {
+        Some(r) => (r, Rule::Signalled),
+        None if matrix == MatrixCoefficients::Identity => (Range::Full, Rule::Rgb),
+        None if matches!(context.container, Container::Jpeg | Container::WebP) => (Range::Full, Rule::Container),
+        None => (Range::Limited, Rule::VideoDefault),
+    };
+
+    let (transfer, transfer_rule) = match signalled.transfer {
+        Some(t) => (t, Rule::Signalled),
+        None if image => (TransferCharacteristics::SRGB, Rule::Container),
+        None if primaries == ColorPrimaries::BT2020 => (if context.depth.bits() >= 12 {
+            TransferCharacteristics::BT2020_12Bit
+        } else {
+            TransferCharacteristics::BT2020_10Bit
+        }, if primaries_rule == Rule::Signalled { Rule::FromPrimaries } else { primaries_rule }),
+        None => (TransferCharacteristics::BT709, Rule::VideoDefault),
+    };
+
+    let (chroma_position, chroma_position_rule) = match signalled.chroma_position {
+        Some(p) => (Some(p), Rule::Signalled),
+        None if matches!(context.chroma_sampling, ChromaSampling::Cs444 | ChromaSampling::Monochrome) => (None, Rule::NotSubsampled),
+        None if image => (None, Rule::Container),
+        None => (Some(ChromaSamplePosition::Vertical), Rule::VideoDefault),
+    };
+
+    Guess {
+        colorimetry: Colorimetry { matrix, range, primaries, transfer, chroma_position },
+        matrix: matrix_rule,
+        range: range_rule,
+        primaries: primaries_rule,
+        transfer: transfer_rule,
+        chroma_position: chroma_position_rule,
+    }
+}
+
+#[test]
+fn guesses() {
+    use crate::color::Depth;
+
+    let none = Signalled::default();
+
+    let g = fill(&none, &Context::new(720, 576, Depth::Depth8, ChromaSampling::Cs420));
+    assert_eq!((g.colorimetry.primaries, g.primaries), (ColorPrimaries::BT470BG, Rule::Sd625));
+    assert_eq!((g.colorimetry.matrix, g.matrix), (MatrixCoefficients::BT470BG, Rule::Sd625));
+    assert_eq!((g.colorimetry.range, g.range), (Range::Limited, Rule::VideoDefault));
+    assert_eq!(g.colorimetry.chroma_position, Some(ChromaSamplePosition::Vertical));
+
+    let g = fill(&none, &Context::new(720, 480, Depth::Depth8, ChromaSampling::Cs420));
+    assert_eq!((g.colorimetry.matrix, g.matrix), (MatrixCoefficients::BT601, Rule::Sd525));
+
+    let g = fill(&none, &Context::new(1920, 1080, Depth::Depth8, ChromaSampling::Cs422));
+    assert_eq!(g.colorimetry.matrix, MatrixCoefficients::BT709);
+    assert_eq!((g.colorimetry.transfer, g.transfer), (TransferCharacteristics::BT709, Rule::VideoDefault));
+
+    // signalled values win, and others follow them
+    let signalled = Signalled { matrix: Some(MatrixCoefficients::BT2020NCL), ..none };
+    let g = fill(&signalled, &Context::new(1920, 1080, Depth::Depth12, ChromaSampling::Cs420));
+    assert_eq!((g.colorimetry.matrix, g.matrix), (MatrixCoefficients::BT2020NCL, Rule::Signalled));
+    assert_eq!((g.colorimetry.primaries, g.primaries), (ColorPrimaries::BT2020, Rule::FromMatrix));
+    assert_eq!(g.colorimetry.transfer, TransferCharacteristics::BT2020_12Bit);
+    let signalled = Signalled { primaries: Some(ColorPrimaries::BT709), ..none };
+    let g = fill(&signalled, &Context::new(640, 480, Depth::Depth8, ChromaSampling::Cs420));
+    assert_eq!((g.colorimetry.matrix, g.matrix), (MatrixCoefficients::BT709, Rule::FromPrimaries));
+
+    let g = fill(&none, &Context::new(640, 480, Depth::Depth8, ChromaSampling::Cs420).with_container(Container::Jpeg));
+    assert_eq!(g.colorimetry, Colorimetry {
+        matrix: MatrixCoefficients::BT601,
+        range: Range::Full,
+        primaries: ColorPrimaries::BT709,
+        transfer: TransferCharacteristics::SRGB,
+        chroma_position: None,
+    });
+    assert_eq!(g.range, Rule::Container);
+
+    let g = fill(&none, &Context::new(4000, 3000, Depth::Depth10, ChromaSampling::Cs444).with_container(Container::Avif));
+    assert_eq!((g.colorimetry.range, g.range), (Range::Limited, Rule::VideoDefault));
+    assert_eq!(g.chroma_position, Rule::NotSubsampled);
+
+    let signalled = Signalled { matrix: Some(MatrixCoefficients::Identity), ..none };
+    let g = fill(&signalled, &Context::new(64, 64, Depth::Depth8, ChromaSampling::Cs444));
+    assert_eq!((g.colorimetry.range, g.range), (Range::Full, Rule::Rgb));
+
+    // no guessing BT.2020 from the size alone
+    let g = fill(&none, &Context::new(3840, 2160, BitDepth::new(10).unwrap(), ChromaSampling::Cs420));
+    assert_eq!((g.colorimetry.primaries, g.primaries), (ColorPrimaries::BT709, Rule::HdSize));
+    let signalled = Signalled { primaries: Some(ColorPrimaries::BT2020), ..none };
+    let g = fill(&signalled, &Context::new(3840, 2160, BitDepth::new(14).unwrap(), ChromaSampling::Cs420));
+    assert_eq!(g.colorimetry.transfer, TransferCharacteristics::BT2020_12Bit);
+}
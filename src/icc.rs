@@ -0,0 +1,466 @@
+//! ICC v4 display profiles for CICP color spaces, for formats that don't support CICP (e.g. PNG or JPEG without `cICP`),
+//! and [`analyze`] to find CICP values of existing ICC profiles.
+//!
+//! The profile is a matrix/TRC profile with the primaries chromatically adapted to D50 (Bradford).
+//! Transfer functions are parametric curves where ICC can express them, and sampled curves otherwise (PQ, HLG, logarithmic).
+//! PQ is decoded with 10000 cd/m² as the maximum, and HLG without the OOTF, so these profiles are a fallback
+//! for software that doesn't read the `cicp` tag.
+//!
+//! ```
+//! # use yuv::color::*;
+//! # use yuv::icc::IccProfile;
+//! let icc = IccProfile::new(ColorPrimaries::BT2020, TransferCharacteristics::SMPTE2084).with_cicp(true).to_bytes();
+//! assert_eq!(&icc[36..40], b"acsp");
+//! ```
+
+use crate::color::{ColorPrimaries, TransferCharacteristics};
+use crate::primaries::{self, Mat3};
+use crate::transfer::{self, BT709_ALPHA, BT709_BETA};
+use crate::Error;
+
+/// ICC profile connection space illuminant
+const D50: [f64; 3] = [0.9642, 1.0, 0.8249];
+
+/// Number of entries in sampled curves
+const CURVE_SIZE: usize = 4096;
+
+/// Builder of an ICC v4 RGB display profile
+#[derive(Debug, Clone)]
+pub struct IccProfile {
+    primaries: ColorPrimaries,
+    transfer: TransferCharacteristics,
+    cicp: bool,
+    description: Option<String>,
+}
+
+impl IccProfile {
+    /// RGB with the given primaries and transfer function
+    #[must_use]
+    pub fn new(primaries: ColorPrimaries, transfer: TransferCharacteristics) -> Self {
+        Self { primaries, transfer, cicp: false, description: None }
+    }
+
+    /// Add the ICC v4.4 `cicp` tag, which lets newer software use the exact CICP values instead of the approximation
+    #[must_use]
+    pub fn with_cicp(mut self, cicp: bool) -> Self {
+        self.cicp = cicp;
+        self
+    }
+
+    /// Profile description. By default it's made of the names of primaries and transfer function, e.g. `bt709 iec61966-2-1`.
+    #[must_use]
+    pub fn with_description(mut self, description: &str) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Serialized profile
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let description = self.description.clone().unwrap_or_else(|| format!("{} {}", self.primaries, self.transfer));
+
+        let rgb_to_xyz = primaries::rgb_to_xyz(self.primaries);
+        let white = rgb_to_xyz.map(|row| row.iter().sum::<f64>());
+        let chad = primaries::bradford(white, D50);
+        let colorants = primaries::mul(&chad, &rgb_to_xyz);
+        let column = |c: usize| xyz_type([colorants[0][c], colorants[1][c], colorants[2][c]]);
+        let trc = curve(self.transfer);
+
+        let mut tags: Vec<(&[u8; 4], Vec<u8>)> = vec![
+            (b"desc", mluc(&description)),
+            (b"cprt", mluc("No copyright, use freely")),
+            (b"wtpt", xyz_type(D50)),
+            (b"chad", sf32(&chad)),
+            (b"rXYZ", column(0)),
+            (b"gXYZ", column(1)),
+            (b"bXYZ", column(2)),
+            (b"rTRC", trc.clone()),
+            (b"gTRC", trc.clone()),
+            (b"bTRC", trc),
+        ];
+        if self.cicp {
+            // RGB, full range
+            tags.push((b"cicp", [b"cicp".as_slice(), &[0; 4], &[self.primaries as u8, self.transfer as u8, 0, 1]].concat()));
+        }
+
+        let mut out = header();
+        out.extend_from_slice(&(tags.len() as u32).to_be_bytes());
+        let table_start = out.len();
+        out.resize(table_start + 12 * tags.len(), 0);
+        let mut written: Vec<(usize, usize, &[u8])> = Vec::with_capacity(tags.len());
+        for (i, (sig, data)) in tags.iter().enumerate() {
+            // identical data (like the TRCs) is stored once
+            let (offset, len) = if let Some(&(offset, len, _)) = written.iter().find(|w| w.2 == data.as_slice()) {
+                (offset, len)
+            } else {
+                let offset = out.len();
+                out.extend_from_slice(data);
+                out.resize(out.len().next_multiple_of(4), 0);
+                written.push((offset, data.len(), data));
+                (offset, data.len())
+            };
+            let entry = &mut out[table_start + 12 * i..][..12];
+            entry[..4].copy_from_slice(*sig);
+            entry[4..8].copy_from_slice(&(offset as u32).to_be_bytes());
+            entry[8..].copy_from_slice(&(len as u32).to_be_bytes());
+        }
+        let size = out.len() as u32;
+        out[..4].copy_from_slice(&size.to_be_bytes());
+        out
+    }
+}
+
+fn s15f16(v: f64) -> [u8; 4] {
+    ((v * 65536.).round() as i32).to_be_bytes()
+}
+
+fn header() -> Vec<u8> {
+    let mut h = vec![0u8; 128];
+    h[8..12].copy_from_slice(&[4, 0x40, 0, 0]);
+    h[12..16].copy_from_slice(b"mntr");
+    h[16..20].copy_from_slice(b"RGB ");
+    h[20..24].copy_from_slice(b"XYZ ");
+    // fixed date, so that the output is reproducible
+    for (i, v) in [2022u16, 5, 1, 0, 0, 0].into_iter().enumerate() {
+        h[24 + 2 * i..][..2].copy_from_slice(&v.to_be_bytes());
+    }
+    h[36..40].copy_from_slice(b"acsp");
+    for (i, &v) in D50.iter().enumerate() {
+        h[68 + 4 * i..][..4].copy_from_slice(&s15f16(v));
+    }
+    h
+}
+
+fn xyz_type(xyz: [f64; 3]) -> Vec<u8> {
+    let mut out = b"XYZ \0\0\0\0".to_vec();
+    xyz.iter().for_each(|&v| out.extend_from_slice(&s15f16(v)));
+    out
+}
+
+fn sf32(m: &Mat3) -> Vec<u8> {
+    let mut out = b"sf32\0\0\0\0".to_vec();
+    m.iter().flatten().for_each(|&v| out.extend_from_slice(&s15f16(v)));
+    out
+}
+
+/// `multiLocalizedUnicodeType` with one English string
+fn mluc(text: &str) -> Vec<u8> {
+    let utf16: Vec<u8> = text.encode_utf16().flat_map(u16::to_be_bytes).collect();
+    let mut out = b"mluc\0\0\0\0".to_vec();
+    for v in [1u32, 12] {
+        out.extend_from_slice(&v.to_be_bytes());
+    }
+    out.extend_from_slice(b"enUS");
+    for v in [utf16.len() as u32, 28] {
+        out.extend_from_slice(&v.to_be_bytes());
+    }
+    out.extend_from_slice(&utf16);
+    out
+}
+
+/// `parametricCurveType` of function type 0, 1 or 3, chosen by the number of parameters
+fn para(params: &[f64]) -> Vec<u8> {
+    let function: u16 = match params.len() { 1 => 0, 3 => 1, _ => 3 };
+    let mut out = b"para\0\0\0\0".to_vec();
+    out.extend_from_slice(&function.to_be_bytes());
+    out.extend_from_slice(&[0; 2]);
+    params.iter().for_each(|&v| out.extend_from_slice(&s15f16(v)));
+    out
+}
+
+/// Parameters of type 3: `(a·x + b)^g` above `d`, `c·x` below
+fn gamma(alpha: f64, beta: f64, slope: f64) -> [f64; 5] {
+    [1. / 0.45, 1. / alpha, (alpha - 1.) / alpha, 1. / slope, beta * slope]
+}
+
+#[allow(deprecated)]
+fn curve(tc: TransferCharacteristics) -> Vec<u8> {
+    use TransferCharacteristics as TC;
+    match tc {
+        // IEC 61966-2-4 differs only for negative values, which don't exist in ICC
+        TC::BT709 | TC::BT601 | TC::BT2020_10Bit | TC::BT2020_12Bit | TC::BT1361 | TC::IEC61966 => para(&gamma(BT709_ALPHA, BT709_BETA, 4.5)),
+        TC::SMPTE240 => para(&gamma(1.1115, 0.0228, 4.)),
+        TC::SRGB => para(&[2.4, 1. / 1.055, 0.055 / 1.055, 1. / 12.92, 0.04045]),
+        TC::BT470M => para(&[2.2]),
+        TC::BT470BG => para(&[2.8]),
+        TC::Linear => para(&[1.]),
+        TC::SMPTE428 => para(&[2.6, (52.37_f64 / 48.).powf(1. / 2.6), 0.]),
+        TC::Log100 | TC::Log100Sqrt10 | TC::SMPTE2084 | TC::HLG => {
+            let mut out = b"curv\0\0\0\0".to_vec();
+            out.extend_from_slice(&(CURVE_SIZE as u32).to_be_bytes());
+            for i in 0..CURVE_SIZE {
+                let v = transfer::to_linear(tc, i as f64 / (CURVE_SIZE - 1) as f64);
+                out.extend_from_slice(&((v.clamp(0., 1.) * 65535.).round() as u16).to_be_bytes());
+            }
+            out
+        },
+    }
+}
+
+/// Closest known value, and how far it is from the profile
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Candidate<T> {
+    pub value: T,
+    /// Within the tolerance of rounding in ICC profiles
+    pub exact: bool,
+    /// Largest difference found: of CIE xy coordinates for primaries, and of linear values for curves
+    pub error: f64,
+}
+
+/// CICP values recovered from an ICC profile. See [`analyze`]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct IccAnalysis {
+    pub primaries: Candidate<ColorPrimaries>,
+    /// The worst match of the three channels' curves
+    pub transfer: Candidate<TransferCharacteristics>,
+    /// Raw `cicp` tag (primaries, transfer, matrix, full range flag), if the profile has one
+    pub cicp: Option<[u8; 4]>,
+}
+
+/// Tolerance for CIE xy coordinates
+const XY_TOLERANCE: f64 = 0.002;
+/// Tolerance for linear values of curves
+const CURVE_TOLERANCE: f64 = 0.002;
+
+/// Reads colorants (`rXYZ`/`gXYZ`/`bXYZ`), white point (`wtpt`), chromatic adaptation (`chad`) and curves (`rTRC`, etc.) of an RGB matrix/TRC profile,
+/// and finds the closest [`ColorPrimaries`] and [`TransferCharacteristics`].
+///
+/// Fails for profiles that aren't RGB matrix/TRC (e.g. LUT-based or CMYK).
+pub fn analyze(icc: &[u8]) -> Result<IccAnalysis, Error> {
+    let be32 = |pos: usize| -> Result<u32, Error> {
+        Ok(u32::from_be_bytes(icc.get(pos..pos + 4).ok_or(Error::InvalidIcc)?.try_into().unwrap()))
+    };
+    if icc.len() < 132 || &icc[36..40] != b"acsp" || &icc[16..20] != b"RGB " || &icc[20..24] != b"XYZ " {
+        return Err(Error::InvalidIcc);
+    }
+    let count = be32(128)? as usize;
+    let tag = |sig: &[u8; 4]| -> Result<Option<&[u8]>, Error> {
+        for i in 0..count.min(icc.len() / 12) {
+            let entry = 132 + 12 * i;
+            if icc.get(entry..entry + 4) == Some(sig.as_slice()) {
+                let (offset, len) = (be32(entry + 4)? as usize, be32(entry + 8)? as usize);
+                return icc.get(offset..offset.checked_add(len).ok_or(Error::InvalidIcc)?).map(Some).ok_or(Error::InvalidIcc);
+            }
+        }
+        Ok(None)
+    };
+    let required = |sig| tag(sig)?.ok_or(Error::InvalidIcc);
+
+    let columns = [required(b"rXYZ")?, required(b"gXYZ")?, required(b"bXYZ")?].map(read_xyz);
+    let colorants: Mat3 = core::array::from_fn(|i| core::array::from_fn(|j| columns[j].map(|c| c[i]).unwrap_or(f64::NAN)));
+    // v4 profiles have colorants adapted to D50 with `chad`. v2 profiles have the actual white in `wtpt`, and are assumed to use Bradford.
+    let rgb_to_xyz = if let Some(chad) = tag(b"chad")? {
+        primaries::mul(&primaries::invert(&read_sf32(chad)?), &colorants)
+    } else {
+        let white = tag(b"wtpt")?.map(read_xyz).transpose()?.unwrap_or(D50);
+        primaries::mul(&primaries::bradford(D50, white), &colorants)
+    };
+    if rgb_to_xyz.iter().flatten().any(|v| !v.is_finite()) {
+        return Err(Error::InvalidIcc);
+    }
+    let xy = |xyz: [f64; 3]| {
+        let sum = xyz.iter().sum::<f64>();
+        [xyz[0] / sum, xyz[1] / sum]
+    };
+    let white = rgb_to_xyz.map(|row| row.iter().sum::<f64>());
+    let found = [0, 1, 2].map(|c| xy([rgb_to_xyz[0][c], rgb_to_xyz[1][c], rgb_to_xyz[2][c]]));
+    let found = [found[0], found[1], found[2], xy(white)];
+    let primaries = nearest(KNOWN_PRIMARIES, XY_TOLERANCE, |p| {
+        primaries::chromaticities(p).iter().zip(&found).flat_map(|(a, b)| [(a[0] - b[0]).abs(), (a[1] - b[1]).abs()]).fold(0., f64::max)
+    });
+
+    let curves = [required(b"rTRC")?, required(b"gTRC")?, required(b"bTRC")?].map(Curve::read);
+    let mut transfer: Option<Candidate<TransferCharacteristics>> = None;
+    for curve in curves {
+        let curve = curve?;
+        let c = nearest(KNOWN_TRANSFERS, CURVE_TOLERANCE, |tc| {
+            (0..=256).map(|i| {
+                let x = f64::from(i) / 256.;
+                (curve.eval(x).clamp(0., 1.) - transfer::to_linear(tc, x).clamp(0., 1.)).abs()
+            }).fold(0., f64::max)
+        });
+        if transfer.is_none_or(|t| t.error < c.error) {
+            transfer = Some(c);
+        }
+    }
+
+    let cicp = tag(b"cicp")?.and_then(|t| t.get(8..12)).map(|t| [t[0], t[1], t[2], t[3]]);
+    Ok(IccAnalysis { primaries, transfer: transfer.ok_or(Error::InvalidIcc)?, cicp })
+}
+
+const KNOWN_PRIMARIES: [ColorPrimaries; 10] = [
+    ColorPrimaries::BT709, ColorPrimaries::BT470M, ColorPrimaries::BT470BG, ColorPrimaries::BT601, ColorPrimaries::GenericFilm,
+    ColorPrimaries::BT2020, ColorPrimaries::XYZ, ColorPrimaries::SMPTE431, ColorPrimaries::SMPTE432, ColorPrimaries::EBU3213,
+];
+
+/// Without variants that have identical curves. Earlier ones are preferred.
+#[allow(deprecated)]
+const KNOWN_TRANSFERS: [TransferCharacteristics; 11] = {
+    use TransferCharacteristics as TC;
+    [TC::SRGB, TC::BT709, TC::Linear, TC::BT470M, TC::BT470BG, TC::SMPTE240, TC::SMPTE2084, TC::HLG, TC::SMPTE428, TC::Log100, TC::Log100Sqrt10]
+};
+
+fn nearest<T: Copy>(candidates: impl IntoIterator<Item = T>, tolerance: f64, error: impl Fn(T) -> f64) -> Candidate<T> {
+    let mut candidates = candidates.into_iter().map(|value| (value, error(value)));
+    let first = candidates.next().unwrap();
+    let (value, error) = candidates.fold(first, |best, c| if c.1 < best.1 { c } else { best });
+    Candidate { value, exact: error <= tolerance, error }
+}
+
+fn read_s15f16(data: &[u8]) -> f64 {
+    f64::from(i32::from_be_bytes([data[0], data[1], data[2], data[3]])) / 65536.
+}
+
+fn read_xyz(data: &[u8]) -> Result<[f64; 3], Error> {
+    if data.len() < 20 || &data[..4] != b"XYZ " {
+        return Err(Error::InvalidIcc);
+    }
+    Ok([0, 1, 2].map(|i| read_s15f16(&data[8 + 4 * i..])))
+}
+
+fn read_sf32(data: &[u8]) -> Result<Mat3, Error> {
+    if data.len() < 44 || &data[..4] != b"sf32" {
+        return Err(Error::InvalidIcc);
+    }
+    Ok(core::array::from_fn(|i| core::array::from_fn(|j| read_s15f16(&data[8 + 12 * i + 4 * j..]))))
+}
+
+/// Tone curve of `curveType` or `parametricCurveType`
+enum Curve {
+    Gamma(f64),
+    Table(Vec<f64>),
+    /// Function type, and parameters `g, a, b, c, d, e, f`
+    Parametric(u16, [f64; 7]),
+}
+
+impl Curve {
+    fn read(data: &[u8]) -> Result<Self, Error> {
+        let u16_at = |pos: usize| data.get(pos..pos + 2).map(|d| u16::from_be_bytes([d[0], d[1]])).ok_or(Error::InvalidIcc);
+        match data.get(..4) {
+            Some(b"curv") => {
+                let count = data.get(8..12).map(|d| u32::from_be_bytes([d[0], d[1], d[2], d[3]])).ok_or(Error::InvalidIcc)? as usize;
+                match count {
+                    0 => Ok(Self::Gamma(1.)),
+                    1 => Ok(Self::Gamma(f64::from(u16_at(12)?) / 256.)),
+                    _ => (0..count).map(|i| Ok(f64::from(u16_at(12 + 2 * i)?) / 65535.)).collect::<Result<_, _>>().map(Self::Table),
+                }
+            },
+            Some(b"para") => {
+                let function = u16_at(8)?;
+                let len = [1, 3, 4, 5, 7].get(usize::from(function)).ok_or(Error::InvalidIcc)?;
+                let params = data.get(12..12 + 4 * len).ok_or(Error::InvalidIcc)?;
+                let mut p = [0.; 7];
+                p.iter_mut().zip(params.chunks_exact(4)).for_each(|(p, d)| *p = read_s15f16(d));
+                Ok(Self::Parametric(function, p))
+            },
+            _ => Err(Error::InvalidIcc),
+        }
+    }
+
+    fn eval(&self, x: f64) -> f64 {
+        match self {
+            Self::Gamma(g) => x.powf(*g),
+            Self::Table(t) => {
+                let pos = x * (t.len() - 1) as f64;
+                let i = (pos as usize).min(t.len() - 2);
+                t[i] + (t[i + 1] - t[i]) * (pos - i as f64)
+            },
+            &Self::Parametric(function, [g, a, b, c, d, e, f]) => {
+                let pow = |x: f64| (a * x + b).max(0.).powf(g);
+                match function {
+                    0 => x.powf(g),
+                    1 => if a * x + b >= 0. { pow(x) } else { 0. },
+                    2 => if a * x + b >= 0. { pow(x) + c } else { c },
+                    3 => if x >= d { pow(x) } else { c * x },
+                    _ => if x >= d { pow(x) + e } else { c * x + f },
+                }
+            },
+        }
+    }
+}
+
+#[test]
+fn srgb_profile() {
+    let icc = IccProfile::new(ColorPrimaries::BT709, TransferCharacteristics::SRGB).to_bytes();
+    assert_eq!(icc.len(), u32::from_be_bytes(icc[..4].try_into().unwrap()) as usize);
+    assert_eq!(0, icc.len() % 4);
+    assert_eq!(&icc[16..20], b"RGB ");
+
+    // should be the same as the built-in sRGB
+    let profile = qcms::Profile::new_from_slice(&icc, false).unwrap();
+    let xfm = qcms::Transform::new(&profile, &qcms::Profile::new_sRGB(), qcms::DataType::RGB8, qcms::Intent::RelativeColorimetric).unwrap();
+    let src = [0u8, 0, 0, 255, 255, 255, 255, 0, 0, 0, 255, 0, 0, 0, 255, 30, 128, 200];
+    let mut dst = [0; 18];
+    xfm.convert(&src, &mut dst);
+    for (a, b) in src.iter().zip(dst) {
+        assert!(a.abs_diff(b) <= 1, "{src:?} {dst:?}");
+    }
+}
+
+#[test]
+fn hdr_profiles() {
+    for (p, tc) in [(ColorPrimaries::BT2020, TransferCharacteristics::SMPTE2084), (ColorPrimaries::BT2020, TransferCharacteristics::HLG), (ColorPrimaries::SMPTE432, TransferCharacteristics::BT709)] {
+        let icc = IccProfile::new(p, tc).with_cicp(true).to_bytes();
+        let profile = qcms::Profile::new_from_slice(&icc, false).unwrap_or_else(|| panic!("{p:?} {tc:?}"));
+        let xfm = qcms::Transform::new(&profile, &qcms::Profile::new_sRGB(), qcms::DataType::RGB8, qcms::Intent::RelativeColorimetric).unwrap();
+        let mut px = [255, 255, 255, 0, 0, 0];
+        xfm.apply(&mut px);
+        // white and black stay neutral
+        assert!(px[0].abs_diff(px[1]) <= 1 && px[1].abs_diff(px[2]) <= 1 && px[3] == 0, "{px:?}");
+
+        let cicp = icc.windows(8).position(|w| w == b"cicp\0\0\0\0").unwrap();
+        assert_eq!(icc[cicp + 8..cicp + 12], [p as u8, tc as u8, 0, 1]);
+    }
+}
+
+#[test]
+#[allow(deprecated)]
+fn analyze_round_trip() {
+    for p in KNOWN_PRIMARIES {
+        for tc in [TransferCharacteristics::SRGB, TransferCharacteristics::BT2020_10Bit, TransferCharacteristics::BT470M, TransferCharacteristics::SMPTE2084, TransferCharacteristics::HLG, TransferCharacteristics::Log100] {
+            let a = analyze(&IccProfile::new(p, tc).to_bytes()).unwrap();
+            assert_eq!(a.primaries.value, p);
+            assert!(a.primaries.exact, "{p:?} {a:?}");
+            assert!(a.transfer.exact, "{tc:?} {a:?}");
+            // BT.2020 has the same curve as BT.709
+            let tc = if tc == TransferCharacteristics::BT2020_10Bit { TransferCharacteristics::BT709 } else { tc };
+            assert_eq!(a.transfer.value, tc);
+            assert_eq!(a.cicp, None);
+        }
+    }
+    let a = analyze(&IccProfile::new(ColorPrimaries::BT2020, TransferCharacteristics::HLG).with_cicp(true).to_bytes()).unwrap();
+    assert_eq!(a.cicp, Some([9, 18, 0, 1]));
+    assert!(analyze(b"not a profile").is_err());
+}
+
+#[test]
+fn analyze_v2_and_inexact() {
+    let tag_offset = |icc: &[u8], sig: &[u8]| {
+        let entry = icc.windows(4).position(|w| w == sig).unwrap();
+        u32::from_be_bytes(icc[entry + 4..entry + 8].try_into().unwrap()) as usize
+    };
+
+    // v2-style: no `chad`, and the actual white point in `wtpt`
+    let mut icc = IccProfile::new(ColorPrimaries::BT709, TransferCharacteristics::SRGB).to_bytes();
+    let chad = icc[132..].windows(4).position(|w| w == b"chad").unwrap() + 132;
+    icc[chad..chad + 4].copy_from_slice(b"xxxx");
+    let wtpt = tag_offset(&icc, b"wtpt");
+    icc[wtpt..wtpt + 20].copy_from_slice(&xyz_type([0.9505, 1., 1.089]));
+    let a = analyze(&icc).unwrap();
+    assert_eq!(a.primaries.value, ColorPrimaries::BT709);
+    assert!(a.primaries.exact, "{a:?}");
+
+    // slightly off red, and gamma 2.4 isn't any of the known curves
+    let mut icc = IccProfile::new(ColorPrimaries::BT709, TransferCharacteristics::SRGB).to_bytes();
+    let r = tag_offset(&icc, b"rXYZ");
+    let x = read_s15f16(&icc[r + 8..]);
+    icc[r + 8..r + 12].copy_from_slice(&s15f16(x + 0.02));
+    let trc = tag_offset(&icc, b"rTRC");
+    icc[trc + 8..trc + 12].copy_from_slice(&[0, 0, 0, 0]);
+    icc[trc + 12..trc + 16].copy_from_slice(&s15f16(2.4));
+    let a = analyze(&icc).unwrap();
+    assert_eq!(a.primaries.value, ColorPrimaries::BT709);
+    assert!(!a.primaries.exact);
+    assert!(!a.transfer.exact);
+    assert!(a.transfer.error > 0.01, "{a:?}");
+}
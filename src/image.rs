@@ -0,0 +1,94 @@
+//! Conversion of frames to and from the [`image`](https://lib.rs/crates/image) crate's buffers. Requires the `image` feature.
+//!
+//! ```
+//! # use yuv::color::*;
+//! # use yuv::convert::RGBConvert;
+//! # use yuv::frame::YUVFrameRef;
+//! let frame = YUVFrameRef::new(2, 2, &[235u8; 4], &[128], &[128], ChromaSampling::Cs420)?;
+//! let conv = RGBConvert::<u8>::new(Range::Limited, MatrixCoefficients::BT709)?;
+//! let img = yuv::image::to_rgb_image(&frame, &conv)?;
+//! assert_eq!(img.get_pixel(1, 1).0, [255, 255, 255]);
+//! # Ok::<_, yuv::Error>(())
+//! ```
+
+use crate::color::{ChromaSampling, Depth, MatrixCoefficients, Range};
+use crate::convert::{Sample, ToRGB};
+use crate::frame::{YUVFrame, YUVFrameRef};
+use crate::Error;
+use image::{DynamicImage, ImageBuffer, Pixel, Rgb32FImage, RgbImage};
+use rgb::Rgb;
+
+/// 16-bit RGBA buffer (the `image` crate doesn't export a name for it)
+pub type Rgba16Image = ImageBuffer<image::Rgba<u16>, Vec<u16>>;
+
+/// Converts the frame with the `conv`, e.g. [`RGBConvert`](crate::convert::RGBConvert) or [`Lut`](crate::convert::Lut)
+pub fn to_rgb_image<T: Sample>(frame: &YUVFrameRef<'_, T>, conv: &impl ToRGB<T, u8>) -> Result<RgbImage, Error> {
+    to_image(frame, conv, |px| [px.r, px.g, px.b])
+}
+
+/// Converts the frame with the `conv` to RGB in `0..=1` (or beyond, see [`Clamp::None`](crate::convert::Clamp::None))
+pub fn to_rgb32f_image<T: Sample>(frame: &YUVFrameRef<'_, T>, conv: &impl ToRGB<T, f32>) -> Result<Rgb32FImage, Error> {
+    to_image(frame, conv, |px| [px.r, px.g, px.b])
+}
+
+/// Converts the frame with the `conv`, which must have 16-bit output, and makes it opaque
+pub fn to_rgba16_image<T: Sample>(frame: &YUVFrameRef<'_, T>, conv: &impl ToRGB<T, u16>) -> Result<Rgba16Image, Error> {
+    to_image(frame, conv, |px| [px.r, px.g, px.b, u16::MAX])
+}
+
+fn to_image<T: Sample, O: Copy + Default, P: Pixel<Subpixel = O>, const N: usize>(frame: &YUVFrameRef<'_, T>, conv: &impl ToRGB<T, O>, px: impl Fn(Rgb<O>) -> [O; N]) -> Result<ImageBuffer<P, Vec<O>>, Error> {
+    let (width, height) = (u32::try_from(frame.width), u32::try_from(frame.height));
+    let (Ok(width), Ok(height)) = (width, height) else {
+        return Err(Error::InvalidFrameSize);
+    };
+    let mut rgb = vec![Rgb::new(O::default(), O::default(), O::default()); frame.width * frame.height];
+    frame.convert(conv, &mut rgb, frame.width)?;
+    ImageBuffer::from_raw(width, height, rgb.into_iter().flat_map(px).collect()).ok_or(Error::InvalidFrameSize)
+}
+
+/// Converts the image to YUV with the given range and matrix, for encoding. See [`YUVFrame::from_rgb`].
+///
+/// 8-bit images give 8-bit frames, and 16-bit and float images give [`Depth::Depth16`]. Gray images are treated as RGB, and alpha is ignored.
+pub fn to_yuv_frame(image: &DynamicImage, range: Range, matrix: MatrixCoefficients, sampling: ChromaSampling) -> Result<(YUVFrame<u16>, Depth), Error> {
+    let color = image.color();
+    let depth = if color.bytes_per_pixel() == color.channel_count() { Depth::Depth8 } else { Depth::Depth16 };
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let rgb: Vec<u16> = if depth == Depth::Depth8 {
+        image.to_rgb8().into_raw().into_iter().map(u16::from).collect()
+    } else {
+        image.to_rgb16().into_raw()
+    };
+
+    let pixels: Vec<_> = rgb.chunks_exact(3).map(|px| Rgb::new(px[0], px[1], px[2])).collect();
+    let frame = YUVFrame::from_rgb(width, height, &pixels, depth, (range, matrix, depth), sampling)?;
+    Ok((frame, depth))
+}
+
+#[test]
+fn image_round_trip() {
+    use crate::convert::RGBConvert;
+
+    let rgb = RgbImage::from_fn(5, 3, |x, y| image::Rgb([x as u8 * 50, y as u8 * 100, 128]));
+    let (frame, depth) = to_yuv_frame(&DynamicImage::ImageRgb8(rgb.clone()), Range::Full, MatrixCoefficients::BT709, ChromaSampling::Cs444).unwrap();
+    assert_eq!(depth, Depth::Depth8);
+    let frame8 = |plane: &[u16]| plane.iter().map(|&v| v as u8).collect::<Vec<u8>>();
+    let (y, u, v) = (frame8(&frame.y), frame8(&frame.u), frame8(&frame.v));
+    let conv = RGBConvert::<u8>::new(Range::Full, MatrixCoefficients::BT709).unwrap();
+    let back = to_rgb_image(&YUVFrameRef::new(5, 3, &y, &u, &v, ChromaSampling::Cs444).unwrap(), &conv).unwrap();
+    for (a, b) in rgb.pixels().zip(back.pixels()) {
+        assert!(a.0.iter().zip(b.0).all(|(&a, b)| a.abs_diff(b) <= 1), "{a:?} {b:?}");
+    }
+
+    let gray = DynamicImage::ImageLuma16(ImageBuffer::from_pixel(3, 3, image::Luma([40000u16])));
+    let (frame, depth) = to_yuv_frame(&gray, Range::Limited, MatrixCoefficients::BT601, ChromaSampling::Cs420).unwrap();
+    assert_eq!((depth, frame.u.len()), (Depth::Depth16, 4));
+    let center = crate::levels::Levels::new(Range::Limited, depth).uv_center();
+    assert!(frame.u.iter().chain(&frame.v).all(|&c| c == center));
+    let conv = RGBConvert::<u16>::new(Range::Limited, MatrixCoefficients::BT601, depth).unwrap();
+    let frame = frame.as_frame_ref();
+    let back = to_rgba16_image(&frame, &conv).unwrap();
+    let px = back.get_pixel(2, 2).0;
+    assert!(px[..3].iter().all(|&c| c.abs_diff(40000) <= 2) && px[3] == u16::MAX, "{px:?}");
+    let back = to_rgb32f_image(&frame, &conv).unwrap();
+    assert!((back.get_pixel(0, 0).0[1] - 40000. / 65535.).abs() < 0.0001);
+}
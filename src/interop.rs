@@ -0,0 +1,360 @@
+//! Each API has its own numbering of color spaces. These functions map the [`color`](crate::color) enums to the APIs' constants and back.
+//!
+//! Values that the other side can't represent exactly give [`Error::NoEquivalent`](crate::Error::NoEquivalent),
+//! rather than a close-enough guess. Variants that H.273 defines as the same (e.g. BT.601 and BT.709 transfer functions) are treated as equal.
+
+#![allow(deprecated)]
+
+use crate::color::{ChromaSamplePosition, Colorimetry, ColorPrimaries, MatrixCoefficients, Range, TransferCharacteristics};
+use crate::Error;
+
+/// Vulkan `VkSamplerYcbcrModelConversion`, `VkSamplerYcbcrRange` and `VkChromaLocation`
+pub mod vulkan {
+    use super::*;
+
+    /// `VkSamplerYcbcrModelConversion`. [`MatrixCoefficients::Identity`] is `RGB_IDENTITY`, which doesn't expand the range.
+    pub fn model_from_matrix(matrix: MatrixCoefficients) -> Result<u32, Error> {
+        Ok(match matrix {
+            MatrixCoefficients::Identity => 0,
+            MatrixCoefficients::BT709 => 2,
+            MatrixCoefficients::BT470BG | MatrixCoefficients::BT601 => 3,
+            MatrixCoefficients::BT2020NCL => 4,
+            _ => return Err(Error::NoEquivalent),
+        })
+    }
+
+    /// From `VkSamplerYcbcrModelConversion`. `YCBCR_IDENTITY` (range expansion only) has no equivalent.
+    pub fn matrix_from_model(model: u32) -> Result<MatrixCoefficients, Error> {
+        Ok(match model {
+            0 => MatrixCoefficients::Identity,
+            2 => MatrixCoefficients::BT709,
+            3 => MatrixCoefficients::BT601,
+            4 => MatrixCoefficients::BT2020NCL,
+            _ => return Err(Error::NoEquivalent),
+        })
+    }
+
+    /// `VkSamplerYcbcrRange`
+    #[must_use]
+    pub fn ycbcr_range_from_range(range: Range) -> u32 {
+        match range {
+            Range::Full => 0,
+            Range::Limited => 1,
+        }
+    }
+
+    /// From `VkSamplerYcbcrRange`
+    pub fn range_from_ycbcr_range(range: u32) -> Result<Range, Error> {
+        match range {
+            0 => Ok(Range::Full),
+            1 => Ok(Range::Limited),
+            _ => Err(Error::NoEquivalent),
+        }
+    }
+
+    /// `VkChromaLocation` for `[x, y]`
+    #[must_use]
+    pub fn chroma_location_from_position(position: ChromaSamplePosition) -> [u32; 2] {
+        match position {
+            ChromaSamplePosition::Vertical => [0, 1],
+            ChromaSamplePosition::Colocated => [0, 0],
+        }
+    }
+
+    /// From `VkChromaLocation` of `[x, y]`. Horizontal `MIDPOINT` has no equivalent.
+    pub fn position_from_chroma_location(location: [u32; 2]) -> Result<ChromaSamplePosition, Error> {
+        match location {
+            [0, 1] => Ok(ChromaSamplePosition::Vertical),
+            [0, 0] => Ok(ChromaSamplePosition::Colocated),
+            _ => Err(Error::NoEquivalent),
+        }
+    }
+}
+
+/// Video4Linux `v4l2_colorspace`, `v4l2_ycbcr_encoding`, `v4l2_quantization` and `v4l2_xfer_func`
+pub mod v4l2 {
+    use super::*;
+
+    /// `v4l2_colorspace`. It also implies the transfer function and encoding, but only its primaries are mapped here.
+    pub fn colorspace_from_primaries(primaries: ColorPrimaries) -> Result<u32, Error> {
+        Ok(match primaries {
+            ColorPrimaries::BT601 => 1,
+            ColorPrimaries::BT709 => 3,
+            ColorPrimaries::BT470M => 5,
+            ColorPrimaries::BT470BG => 6,
+            ColorPrimaries::BT2020 => 10,
+            ColorPrimaries::SMPTE431 => 12,
+            _ => return Err(Error::NoEquivalent),
+        })
+    }
+
+    /// From `v4l2_colorspace`. `DEFAULT`, `RAW` and `OPRGB` have no equivalent.
+    pub fn primaries_from_colorspace(colorspace: u32) -> Result<ColorPrimaries, Error> {
+        Ok(match colorspace {
+            // SMPTE 170M, SMPTE 240M and BT.878
+            1 | 2 | 4 => ColorPrimaries::BT601,
+            // REC709, JPEG and SRGB
+            3 | 7 | 8 => ColorPrimaries::BT709,
+            5 => ColorPrimaries::BT470M,
+            6 => ColorPrimaries::BT470BG,
+            10 => ColorPrimaries::BT2020,
+            12 => ColorPrimaries::SMPTE431,
+            _ => return Err(Error::NoEquivalent),
+        })
+    }
+
+    /// `v4l2_ycbcr_encoding`
+    pub fn ycbcr_encoding_from_matrix(matrix: MatrixCoefficients) -> Result<u32, Error> {
+        Ok(match matrix {
+            MatrixCoefficients::BT470BG | MatrixCoefficients::BT601 => 1,
+            MatrixCoefficients::BT709 => 2,
+            MatrixCoefficients::BT2020NCL => 6,
+            MatrixCoefficients::BT2020CL => 7,
+            MatrixCoefficients::SMPTE240 => 8,
+            _ => return Err(Error::NoEquivalent),
+        })
+    }
+
+    /// From `v4l2_ycbcr_encoding`. The xvYCC encodings give their matrix, and `SYCC` is BT.601.
+    pub fn matrix_from_ycbcr_encoding(encoding: u32) -> Result<MatrixCoefficients, Error> {
+        Ok(match encoding {
+            1 | 3 | 5 => MatrixCoefficients::BT601,
+            2 | 4 => MatrixCoefficients::BT709,
+            6 => MatrixCoefficients::BT2020NCL,
+            7 => MatrixCoefficients::BT2020CL,
+            8 => MatrixCoefficients::SMPTE240,
+            _ => return Err(Error::NoEquivalent),
+        })
+    }
+
+    /// `v4l2_quantization`
+    #[must_use]
+    pub fn quantization_from_range(range: Range) -> u32 {
+        match range {
+            Range::Full => 1,
+            Range::Limited => 2,
+        }
+    }
+
+    /// From `v4l2_quantization`. `DEFAULT` depends on the colorspace, and has no equivalent.
+    pub fn range_from_quantization(quantization: u32) -> Result<Range, Error> {
+        match quantization {
+            1 => Ok(Range::Full),
+            2 => Ok(Range::Limited),
+            _ => Err(Error::NoEquivalent),
+        }
+    }
+
+    /// `v4l2_xfer_func`
+    pub fn xfer_func_from_transfer(transfer: TransferCharacteristics) -> Result<u32, Error> {
+        Ok(match transfer {
+            TransferCharacteristics::BT709 | TransferCharacteristics::BT601 |
+            TransferCharacteristics::BT2020_10Bit | TransferCharacteristics::BT2020_12Bit => 1,
+            TransferCharacteristics::SRGB => 2,
+            TransferCharacteristics::SMPTE240 => 4,
+            TransferCharacteristics::Linear => 5,
+            TransferCharacteristics::SMPTE2084 => 7,
+            _ => return Err(Error::NoEquivalent),
+        })
+    }
+
+    /// From `v4l2_xfer_func`. `DEFAULT`, `OPRGB` and `DCI_P3` have no equivalent.
+    pub fn transfer_from_xfer_func(xfer_func: u32) -> Result<TransferCharacteristics, Error> {
+        Ok(match xfer_func {
+            1 => TransferCharacteristics::BT709,
+            2 => TransferCharacteristics::SRGB,
+            4 => TransferCharacteristics::SMPTE240,
+            5 => TransferCharacteristics::Linear,
+            7 => TransferCharacteristics::SMPTE2084,
+            _ => return Err(Error::NoEquivalent),
+        })
+    }
+}
+
+/// Linux KMS plane properties `COLOR_ENCODING` and `COLOR_RANGE` (`drm_color_encoding` and `drm_color_range`)
+pub mod drm {
+    use super::*;
+
+    /// `drm_color_encoding`
+    pub fn color_encoding_from_matrix(matrix: MatrixCoefficients) -> Result<u32, Error> {
+        Ok(match matrix {
+            MatrixCoefficients::BT470BG | MatrixCoefficients::BT601 => 0,
+            MatrixCoefficients::BT709 => 1,
+            MatrixCoefficients::BT2020NCL => 2,
+            _ => return Err(Error::NoEquivalent),
+        })
+    }
+
+    /// From `drm_color_encoding`
+    pub fn matrix_from_color_encoding(encoding: u32) -> Result<MatrixCoefficients, Error> {
+        Ok(match encoding {
+            0 => MatrixCoefficients::BT601,
+            1 => MatrixCoefficients::BT709,
+            2 => MatrixCoefficients::BT2020NCL,
+            _ => return Err(Error::NoEquivalent),
+        })
+    }
+
+    /// `drm_color_range`
+    #[must_use]
+    pub fn color_range_from_range(range: Range) -> u32 {
+        match range {
+            Range::Limited => 0,
+            Range::Full => 1,
+        }
+    }
+
+    /// From `drm_color_range`
+    pub fn range_from_color_range(range: u32) -> Result<Range, Error> {
+        match range {
+            0 => Ok(Range::Limited),
+            1 => Ok(Range::Full),
+            _ => Err(Error::NoEquivalent),
+        }
+    }
+}
+
+/// DirectX `DXGI_COLOR_SPACE_TYPE`
+///
+/// RGB color spaces have [`MatrixCoefficients::Identity`] and no chroma position.
+/// `G22` is sRGB for RGB, and the BT.709 transfer function for YCbCr.
+///
+/// `G24` is an exception to lossy mappings being errors: it's the BT.1886 display gamma, which is how content with the BT.709 transfer function is meant to be shown,
+/// so it's mapped to [`TransferCharacteristics::BT709`] too. In the other direction, the `G22` color space is preferred.
+pub mod dxgi {
+    use super::*;
+
+    use ChromaSamplePosition::{Colocated as TopLeft, Vertical as Left};
+    use ColorPrimaries as P;
+    use MatrixCoefficients as M;
+    use TransferCharacteristics as T;
+
+    const fn c(matrix: M, range: Range, primaries: P, transfer: T, chroma_position: Option<ChromaSamplePosition>) -> Colorimetry {
+        Colorimetry { matrix, range, primaries, transfer, chroma_position }
+    }
+
+    /// In order of preference when converting to DXGI
+    const COLOR_SPACES: [(u32, Colorimetry); 24] = [
+        (0, c(M::Identity, Range::Full, P::BT709, T::SRGB, None)),
+        (1, c(M::Identity, Range::Full, P::BT709, T::Linear, None)),
+        (2, c(M::Identity, Range::Limited, P::BT709, T::SRGB, None)),
+        (3, c(M::Identity, Range::Limited, P::BT2020, T::SRGB, None)),
+        (5, c(M::BT601, Range::Full, P::BT709, T::BT709, None)),
+        (6, c(M::BT601, Range::Limited, P::BT601, T::BT709, Some(Left))),
+        (7, c(M::BT601, Range::Full, P::BT601, T::BT709, Some(Left))),
+        (8, c(M::BT709, Range::Limited, P::BT709, T::BT709, Some(Left))),
+        (9, c(M::BT709, Range::Full, P::BT709, T::BT709, Some(Left))),
+        (10, c(M::BT2020NCL, Range::Limited, P::BT2020, T::BT709, Some(Left))),
+        (11, c(M::BT2020NCL, Range::Full, P::BT2020, T::BT709, Some(Left))),
+        (12, c(M::Identity, Range::Full, P::BT2020, T::SMPTE2084, None)),
+        (13, c(M::BT2020NCL, Range::Limited, P::BT2020, T::SMPTE2084, Some(Left))),
+        (14, c(M::Identity, Range::Limited, P::BT2020, T::SMPTE2084, None)),
+        (15, c(M::BT2020NCL, Range::Limited, P::BT2020, T::BT709, Some(TopLeft))),
+        (16, c(M::BT2020NCL, Range::Limited, P::BT2020, T::SMPTE2084, Some(TopLeft))),
+        (17, c(M::Identity, Range::Full, P::BT2020, T::SRGB, None)),
+        (18, c(M::BT2020NCL, Range::Limited, P::BT2020, T::HLG, Some(TopLeft))),
+        (19, c(M::BT2020NCL, Range::Full, P::BT2020, T::HLG, Some(TopLeft))),
+        (20, c(M::Identity, Range::Limited, P::BT709, T::BT709, None)),
+        (21, c(M::Identity, Range::Limited, P::BT2020, T::BT709, None)),
+        (22, c(M::BT709, Range::Limited, P::BT709, T::BT709, Some(Left))),
+        (23, c(M::BT2020NCL, Range::Limited, P::BT2020, T::BT709, Some(Left))),
+        (24, c(M::BT2020NCL, Range::Limited, P::BT2020, T::BT709, Some(TopLeft))),
+    ];
+
+    /// `DXGI_COLOR_SPACE_TYPE`. Chroma position `None` of YCbCr matches any siting.
+    pub fn color_space_from_colorimetry(colorimetry: &Colorimetry) -> Result<u32, Error> {
+        let want = normalized(colorimetry);
+        COLOR_SPACES.iter().find(|(_, c)| {
+            want.matrix == c.matrix && want.range == c.range && want.primaries == c.primaries && want.transfer == c.transfer &&
+            (want.chroma_position == c.chroma_position || (want.chroma_position.is_none() && c.matrix != M::Identity))
+        }).map(|&(n, _)| n).ok_or(Error::NoEquivalent)
+    }
+
+    /// From `DXGI_COLOR_SPACE_TYPE`. `CUSTOM` and reserved values have no equivalent. `G24` gives the BT.709 transfer function (see [the module docs](self)).
+    pub fn colorimetry_from_color_space(color_space: u32) -> Result<Colorimetry, Error> {
+        COLOR_SPACES.iter().find(|&&(n, _)| n == color_space).map(|&(_, c)| c).ok_or(Error::NoEquivalent)
+    }
+
+    /// Variants that H.273 defines as identical
+    fn normalized(c: &Colorimetry) -> Colorimetry {
+        Colorimetry {
+            matrix: match c.matrix { M::BT470BG => M::BT601, m => m },
+            transfer: match c.transfer { T::BT601 | T::BT2020_10Bit | T::BT2020_12Bit => T::BT709, t => t },
+            ..*c
+        }
+    }
+}
+
+#[test]
+fn round_trips() {
+    for m in [MatrixCoefficients::Identity, MatrixCoefficients::BT709, MatrixCoefficients::BT601, MatrixCoefficients::BT2020NCL] {
+        assert_eq!(m, vulkan::matrix_from_model(vulkan::model_from_matrix(m).unwrap()).unwrap());
+    }
+    assert_eq!(3, vulkan::model_from_matrix(MatrixCoefficients::BT470BG).unwrap());
+    assert!(vulkan::model_from_matrix(MatrixCoefficients::YCgCo).is_err());
+    assert!(vulkan::matrix_from_model(1).is_err());
+    for p in [ChromaSamplePosition::Vertical, ChromaSamplePosition::Colocated] {
+        assert_eq!(p, vulkan::position_from_chroma_location(vulkan::chroma_location_from_position(p)).unwrap());
+    }
+    assert!(vulkan::position_from_chroma_location([1, 1]).is_err());
+
+    for r in [Range::Full, Range::Limited] {
+        assert_eq!(r, vulkan::range_from_ycbcr_range(vulkan::ycbcr_range_from_range(r)).unwrap());
+        assert_eq!(r, v4l2::range_from_quantization(v4l2::quantization_from_range(r)).unwrap());
+        assert_eq!(r, drm::range_from_color_range(drm::color_range_from_range(r)).unwrap());
+    }
+    assert!(v4l2::range_from_quantization(0).is_err());
+
+    for p in [ColorPrimaries::BT709, ColorPrimaries::BT470M, ColorPrimaries::BT470BG, ColorPrimaries::BT601, ColorPrimaries::BT2020, ColorPrimaries::SMPTE431] {
+        assert_eq!(p, v4l2::primaries_from_colorspace(v4l2::colorspace_from_primaries(p).unwrap()).unwrap());
+    }
+    assert!(v4l2::colorspace_from_primaries(ColorPrimaries::XYZ).is_err());
+    for m in [MatrixCoefficients::BT709, MatrixCoefficients::BT601, MatrixCoefficients::BT2020NCL, MatrixCoefficients::BT2020CL, MatrixCoefficients::SMPTE240] {
+        assert_eq!(m, v4l2::matrix_from_ycbcr_encoding(v4l2::ycbcr_encoding_from_matrix(m).unwrap()).unwrap());
+    }
+    assert!(v4l2::ycbcr_encoding_from_matrix(MatrixCoefficients::Identity).is_err());
+    for t in [TransferCharacteristics::BT709, TransferCharacteristics::SRGB, TransferCharacteristics::SMPTE240, TransferCharacteristics::Linear, TransferCharacteristics::SMPTE2084] {
+        assert_eq!(t, v4l2::transfer_from_xfer_func(v4l2::xfer_func_from_transfer(t).unwrap()).unwrap());
+    }
+    assert!(v4l2::xfer_func_from_transfer(TransferCharacteristics::HLG).is_err());
+    assert!(v4l2::transfer_from_xfer_func(6).is_err());
+
+    for m in [MatrixCoefficients::BT709, MatrixCoefficients::BT601, MatrixCoefficients::BT2020NCL] {
+        assert_eq!(m, drm::matrix_from_color_encoding(drm::color_encoding_from_matrix(m).unwrap()).unwrap());
+    }
+    assert!(drm::color_encoding_from_matrix(MatrixCoefficients::FCC).is_err());
+}
+
+#[test]
+fn dxgi_color_spaces() {
+    for n in 0..=24 {
+        let Ok(c) = dxgi::colorimetry_from_color_space(n) else {
+            assert_eq!(4, n);
+            continue;
+        };
+        let back = dxgi::color_space_from_colorimetry(&c).unwrap();
+        // G24 duplicates of G22 come back as the G22 ones
+        assert_eq!(c, dxgi::colorimetry_from_color_space(back).unwrap());
+        assert!(back == n || n >= 22);
+    }
+
+    let hdr10 = Colorimetry {
+        matrix: MatrixCoefficients::BT2020NCL,
+        range: Range::Limited,
+        primaries: ColorPrimaries::BT2020,
+        transfer: TransferCharacteristics::SMPTE2084,
+        chroma_position: None,
+    };
+    assert_eq!(13, dxgi::color_space_from_colorimetry(&hdr10).unwrap());
+    assert_eq!(16, dxgi::color_space_from_colorimetry(&Colorimetry { chroma_position: Some(ChromaSamplePosition::Colocated), ..hdr10 }).unwrap());
+    let sdr = Colorimetry { transfer: TransferCharacteristics::BT2020_10Bit, ..hdr10 };
+    assert_eq!(10, dxgi::color_space_from_colorimetry(&sdr).unwrap());
+    assert!(dxgi::color_space_from_colorimetry(&Colorimetry { primaries: ColorPrimaries::BT709, ..hdr10 }).is_err());
+
+    // YCbCr G22 is the BT.709 transfer function, and G24 is the exception mapped to it too
+    for n in [5, 6, 7, 8, 9, 10, 11, 15, 22, 23, 24] {
+        assert_eq!(TransferCharacteristics::BT709, dxgi::colorimetry_from_color_space(n).unwrap().transfer, "{n}");
+    }
+    let sdr = Colorimetry { transfer: TransferCharacteristics::BT709, primaries: ColorPrimaries::BT709, matrix: MatrixCoefficients::BT709, ..hdr10 };
+    assert_eq!(8, dxgi::color_space_from_colorimetry(&sdr).unwrap());
+}
@@ -0,0 +1,160 @@
+//! Nominal black, white and chroma levels of [`Range`]s at every bit depth.
+//!
+//! Use [`Levels::new`] when range and depth are known only at run time,
+//! or [`FullRange`] and [`LimitedRange`] types to have them as constants.
+
+use crate::color::{BitDepth, Range};
+use crate::range;
+use crate::Error;
+
+/// Code values of nominal black and white luma, and of the chroma extremes
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Levels {
+    pub y_min: u16,
+    pub y_max: u16,
+    pub uv_min: u16,
+    pub uv_max: u16,
+}
+
+impl Levels {
+    /// Levels used by the converters in this crate
+    #[inline]
+    #[must_use]
+    pub fn new(range: Range, depth: impl Into<BitDepth>) -> Self {
+        let [y_min, y_max, uv_min, uv_max] = range::levels(range, depth.into());
+        Self { y_min, y_max, uv_min, uv_max }
+    }
+
+    /// Full range with `bits` per sample (8 to 16)
+    #[must_use]
+    pub const fn full(bits: u8) -> Self {
+        assert!(bits >= 8 && bits <= 16);
+        let max = ((1u32 << bits) - 1) as u16;
+        Self { y_min: 0, y_max: max, uv_min: 0, uv_max: max }
+    }
+
+    /// Limited (studio) range with `bits` per sample (8 to 16).
+    ///
+    /// The 8-bit levels are shifted left for higher depths, except 16 bits, where they're multiplied by 257 (e.g. black is 4112).
+    #[must_use]
+    pub const fn limited(bits: u8) -> Self {
+        assert!(bits >= 8 && bits <= 16);
+        if bits == 16 {
+            Self { y_min: 16 * 257, y_max: 235 * 257, uv_min: 16 * 257, uv_max: 240 * 257 }
+        } else {
+            let shift = bits - 8;
+            Self { y_min: 16 << shift, y_max: 235 << shift, uv_min: 16 << shift, uv_max: 240 << shift }
+        }
+    }
+
+    /// Custom levels, e.g. to keep footroom and headroom with [`Clamp::None`](crate::convert::Clamp::None)
+    pub fn custom(y_min: u16, y_max: u16, uv_min: u16, uv_max: u16) -> Result<Self, Error> {
+        let levels = Self { y_min, y_max, uv_min, uv_max };
+        levels.check(u16::MAX)?;
+        Ok(levels)
+    }
+
+    /// Fails if black isn't below white, or levels are above `max`
+    pub(crate) fn check(&self, max: u16) -> Result<[u16; 4], Error> {
+        if self.y_min >= self.y_max || self.uv_min >= self.uv_max || self.y_max > max || self.uv_max > max {
+            return Err(Error::InvalidLevels);
+        }
+        Ok([self.y_min, self.y_max, self.uv_min, self.uv_max])
+    }
+
+    /// Code value of neutral (gray) chroma
+    #[inline]
+    #[must_use]
+    pub const fn uv_center(&self) -> u16 {
+        if self.uv_min == 0 {
+            self.uv_max.div_ceil(2)
+        } else {
+            ((self.uv_min as u32 + self.uv_max as u32) / 2) as u16
+        }
+    }
+
+    /// Luma code value to `0..=1` (black to white). Values outside of the nominal range aren't clamped.
+    #[inline]
+    #[must_use]
+    pub fn luma_to_normalized(&self, y: f32) -> f32 {
+        (y - f32::from(self.y_min)) / f32::from(self.y_max - self.y_min)
+    }
+
+    /// `0..=1` to luma code value, not rounded
+    #[inline]
+    #[must_use]
+    pub fn luma_from_normalized(&self, y: f32) -> f32 {
+        y * f32::from(self.y_max - self.y_min) + f32::from(self.y_min)
+    }
+
+    /// Chroma code value to `-0.5..=0.5`, with neutral chroma at 0. Values outside of the nominal range aren't clamped.
+    #[inline]
+    #[must_use]
+    pub fn chroma_to_normalized(&self, uv: f32) -> f32 {
+        (uv - f32::from(self.uv_center())) / f32::from(self.uv_max - self.uv_min)
+    }
+
+    /// `-0.5..=0.5` to chroma code value, not rounded
+    #[inline]
+    #[must_use]
+    pub fn chroma_from_normalized(&self, uv: f32) -> f32 {
+        uv * f32::from(self.uv_max - self.uv_min) + f32::from(self.uv_center())
+    }
+}
+
+/// Levels of a range and depth known at compile time
+pub trait NominalLevels {
+    const LEVELS: Levels;
+    const Y_MIN: u16 = Self::LEVELS.y_min;
+    const Y_MAX: u16 = Self::LEVELS.y_max;
+    const UV_MIN: u16 = Self::LEVELS.uv_min;
+    const UV_MAX: u16 = Self::LEVELS.uv_max;
+    const UV_CENTER: u16 = Self::LEVELS.uv_center();
+}
+
+/// Full range with `BITS` per sample, e.g. `FullRange::<10>::Y_MAX` is 1023
+#[derive(Debug, Copy, Clone, Default)]
+pub struct FullRange<const BITS: u8>;
+
+/// Limited (studio) range with `BITS` per sample, e.g. `LimitedRange::<10>::Y_MIN` is 64
+#[derive(Debug, Copy, Clone, Default)]
+pub struct LimitedRange<const BITS: u8>;
+
+impl<const BITS: u8> NominalLevels for FullRange<BITS> {
+    const LEVELS: Levels = Levels::full(BITS);
+}
+
+impl<const BITS: u8> NominalLevels for LimitedRange<BITS> {
+    const LEVELS: Levels = Levels::limited(BITS);
+}
+
+#[test]
+fn levels_match_converters() {
+    use crate::color::Depth;
+    use crate::depth::{Depth10, Depth12, Depth16, Depth8};
+    use crate::range::{Full, Limited};
+
+    fn get<R: range::Range>() -> Levels where R::Pixel: Into<u16> {
+        Levels { y_min: R::Y_MIN.into(), y_max: R::Y_MAX.into(), uv_min: R::UV_MIN.into(), uv_max: R::UV_MAX.into() }
+    }
+    assert_eq!(get::<Full<Depth8>>(), Levels::new(Range::Full, Depth::Depth8));
+    assert_eq!(get::<Full<Depth12>>(), Levels::new(Range::Full, Depth::Depth12));
+    assert_eq!(get::<Limited<Depth8>>(), Levels::new(Range::Limited, Depth::Depth8));
+    assert_eq!(get::<Limited<Depth10>>(), Levels::new(Range::Limited, Depth::Depth10));
+    assert_eq!(get::<Limited<Depth12>>(), Levels::new(Range::Limited, Depth::Depth12));
+    assert_eq!(get::<Limited<Depth16>>(), Levels::new(Range::Limited, Depth::Depth16));
+    assert_eq!(Levels::limited(14), Levels::new(Range::Limited, BitDepth::new(14).unwrap()));
+    assert_eq!(64, LimitedRange::<10>::Y_MIN);
+    assert_eq!(3840, LimitedRange::<12>::UV_MAX);
+    assert_eq!(2048, LimitedRange::<12>::UV_CENTER);
+    assert_eq!(128, FullRange::<8>::UV_CENTER);
+    assert_eq!(16383, FullRange::<14>::Y_MAX);
+
+    let l = Levels::new(Range::Limited, Depth::Depth10);
+    assert_eq!(0., l.luma_to_normalized(64.));
+    assert_eq!(1., l.luma_to_normalized(940.));
+    assert_eq!(-0.5, l.chroma_to_normalized(64.));
+    assert_eq!(0.5, l.chroma_to_normalized(960.));
+    assert_eq!(512., l.chroma_from_normalized(0.));
+    assert_eq!(940., l.luma_from_normalized(1.));
+}
@@ -8,16 +8,59 @@
 /// Enums describing color characteristics (color space, gamma, range)
 ///
 /// The numbers should be compatible with ISO/IEC 23091-4/ITU-T H.273
+///
+/// The enums implement `FromStr` and `Display` using FFmpeg's names, e.g. `bt709`, `tv` or `smpte2084`.
+/// Parsing also accepts common aliases from FFmpeg options, mpv and GStreamer (e.g. `bt.709`, `limited`, `pq`), case-insensitively.
+/// With the `serde` feature they're serialized as these names.
 pub mod color;
 
 pub mod convert;
 
+pub mod dither;
+
+pub mod frame;
+
+pub mod guess;
+
+#[cfg(not(any(not(feature = "std"), feature = "no_std")))]
+pub mod icc;
+
+#[cfg(all(feature = "image", not(feature = "no_std")))]
+pub mod image;
+
+/// Numeric constants of graphics and video APIs (Vulkan, DXGI, V4L2, DRM) for the [`color`] enums
+pub mod interop;
+
+pub mod levels;
+
+#[cfg(not(any(not(feature = "std"), feature = "no_std")))]
+pub mod pnm;
+
+#[cfg(not(any(not(feature = "std"), feature = "no_std")))]
+pub mod raw;
+
+#[cfg(not(any(not(feature = "std"), feature = "no_std")))]
+pub mod shader;
+
+#[cfg(not(any(not(feature = "std"), feature = "no_std")))]
+pub mod transfer;
+
+#[cfg(not(any(not(feature = "std"), feature = "no_std")))]
+pub mod y4m;
+
 mod error;
 pub use error::Error;
 
 /// These are internal
 mod depth;
+mod fixed;
+#[cfg(not(any(not(feature = "std"), feature = "no_std")))]
+mod lut;
+mod names;
+mod primaries;
 mod range;
+mod simd;
+mod transcode;
 
 /// A generic 3-component pixel, which is usually luma + chroma
 ///
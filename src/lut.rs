@@ -0,0 +1,258 @@
+use crate::color::{BitDepth, MatrixCoefficients, Range, TransferCharacteristics};
+use crate::convert::{Clamp, Coefficients, Matrix, Policy, RGBConvert, Rounding, Sample, ToRGB};
+use crate::simd::MatrixKernel;
+use crate::transfer;
+use crate::Error;
+use crate::YUV;
+use core::marker::PhantomData;
+use rgb::Rgb;
+use rgb::prelude::*;
+use std::sync::Arc;
+
+/// Fractional bits of the table entries
+const FRAC: u32 = 13;
+/// Resolution of the transfer function curve
+const CURVE_STEPS: usize = 4096;
+
+/// Converter using tables precomputed for every input value. See [`Lut::<u8>::new`](Lut#method.new)
+///
+/// It replaces the float math of [`Matrix`] with table lookups and integer additions,
+/// and can also convert between transfer functions in the same step.
+/// It pays off for 8-bit and 10-bit input, where the tables are small.
+///
+/// It's a standalone converter like [`FixedMatrix`](crate::convert::FixedMatrix), and works anywhere a [`ToRGB`] does, e.g. [`YUVFrameRef::convert`](crate::frame::YUVFrameRef::convert).
+///
+/// The tables are shared, so cloning is cheap, and one converter can be used from many threads.
+/// Tables are built once from the [`Matrix`], so its dithering isn't used,
+/// and [`Clamp::HuePreserving`] is treated as [`Clamp::PerChannel`].
+///
+/// It doesn't convert color primaries, because that mixes channels in linear light, which per-channel tables can't do.
+/// [`Shader::from_lut`](crate::shader::Shader::from_lut) with [`Shader::with_primaries`](crate::shader::Shader::with_primaries) can.
+#[derive(Debug, Clone)]
+pub struct Lut<T = u8> {
+    tables: Arc<Tables>,
+    _pixel: PhantomData<T>,
+}
+
+#[derive(Debug)]
+struct Tables {
+    /// Y, U and V products in fixed point, with output scale and offset baked in
+    y: Vec<i32>,
+    rv: Vec<i32>,
+    gu: Vec<i32>,
+    gv: Vec<i32>,
+    bu: Vec<i32>,
+    /// Maps `0..=CURVE_STEPS` to output values with 8 fractional bits, if there's a transfer function conversion
+    curve: Option<Vec<i32>>,
+    max: i32,
+    /// What the tables have been made from
+    coefficients: Coefficients,
+    transfer: Option<(TransferCharacteristics, TransferCharacteristics)>,
+}
+
+impl Tables {
+    fn new(kernel: &MatrixKernel, inputs: usize, offset: f32, max: i32, curve: Option<Vec<i32>>, coefficients: Coefficients, transfer: Option<(TransferCharacteristics, TransferCharacteristics)>) -> Self {
+        let one = f64::from(1 << FRAC);
+        let table = |mul: f32, sub: f32, offset: f32| -> Vec<i32> {
+            (0..inputs).map(|i| {
+                let c = (i as f64 * f64::from(mul) - f64::from(sub)) * f64::from(kernel.scale) + f64::from(offset);
+                (c * one).round() as i32
+            }).collect()
+        };
+        Self {
+            y: table(kernel.y_mul, kernel.y_sub, offset),
+            rv: table(kernel.rv_mul, kernel.rv_sub, 0.),
+            gu: table(kernel.gu_mul, kernel.gu_sub, 0.),
+            gv: table(kernel.gv_mul, kernel.gv_sub, 0.),
+            bu: table(kernel.bu_mul, kernel.bu_sub, 0.),
+            curve,
+            max,
+            coefficients,
+            transfer,
+        }
+    }
+
+    #[inline(always)]
+    fn index(table: &[i32], i: usize) -> i32 {
+        table[i.min(table.len() - 1)]
+    }
+
+    #[inline(always)]
+    fn output(&self, c: i32) -> i32 {
+        let Some(curve) = &self.curve else {
+            return (c >> FRAC).clamp(0, self.max);
+        };
+        let c = c.clamp(0, (CURVE_STEPS as i32) << FRAC);
+        let i = (c >> FRAC) as usize;
+        let fract = i64::from(c & ((1 << FRAC) - 1));
+        let (a, b) = (i64::from(curve[i]), i64::from(curve[(i + 1).min(CURVE_STEPS)]));
+        (((a + (((b - a) * fract) >> FRAC) + 128) >> 8) as i32).clamp(0, self.max)
+    }
+
+    #[inline(always)]
+    fn rgb(&self, y: usize, u: usize, v: usize) -> Rgb<i32> {
+        let y = Self::index(&self.y, y);
+        Rgb {
+            r: self.output(y + Self::index(&self.rv, v)),
+            g: self.output(y - Self::index(&self.gu, u) - Self::index(&self.gv, v)),
+            b: self.output(y + Self::index(&self.bu, u)),
+        }
+    }
+}
+
+impl<T: Sample> Lut<T> {
+    fn from_matrix_internal(matrix: &Matrix<T>, inputs: usize, max: u16, transfer: Option<(TransferCharacteristics, TransferCharacteristics)>) -> Self {
+        let policy = matrix.policy();
+        let coefficients = matrix.coefficients();
+        let tables = if let Some((from, to)) = transfer {
+            let matrix = matrix.with_policy(Policy { rounding: Rounding::Nearest, clamp: Clamp::PerChannel });
+            let kernel = matrix.simd_kernel(CURVE_STEPS as f32).unwrap();
+            let curve = (0..=CURVE_STEPS).map(|i| {
+                let linear = transfer::to_linear(from, i as f64 / CURVE_STEPS as f64);
+                let out = transfer::from_linear(to, linear).clamp(0., 1.) * f64::from(max);
+                (out * 256.).round() as i32
+            }).collect();
+            Tables::new(&kernel, inputs, 0., max.into(), Some(curve), coefficients, transfer)
+        } else {
+            let matrix = matrix.with_policy(Policy { clamp: Clamp::PerChannel, ..policy });
+            let kernel = matrix.simd_kernel(max.into()).unwrap();
+            Tables::new(&kernel, inputs, kernel.offset, max.into(), None, coefficients, None)
+        };
+        Self {
+            tables: Arc::new(tables),
+            _pixel: PhantomData,
+        }
+    }
+}
+
+impl<T> Lut<T> {
+    /// Coefficients of the matrix, and the transfer function conversion the tables have been made from
+    pub(crate) fn source(&self) -> (Coefficients, Option<(TransferCharacteristics, TransferCharacteristics)>) {
+        (self.tables.coefficients, self.tables.transfer)
+    }
+}
+
+impl Lut<u8> {
+    /// Tables for 8-bit input and output. Results are within ±1 of [`RGBConvert::<u8>::new`](RGBConvert#method.new).
+    pub fn new(range: Range, matrix: MatrixCoefficients) -> Result<Self, Error> {
+        match RGBConvert::<u8>::new(range, matrix)? {
+            RGBConvert::Matrix(m) => Ok(Self::from_matrix(&m)),
+            _ => Err(Error::UnsupportedMatrixCoefficients),
+        }
+    }
+
+    /// Tables with the same coefficients, rounding and clamping as the `matrix`
+    #[must_use]
+    pub fn from_matrix(matrix: &Matrix<u8>) -> Self {
+        Self::from_matrix_internal(matrix, 256, 255, None)
+    }
+
+    /// Also decode the RGB output from the `from` transfer function, and encode it with `to` (e.g. [`TransferCharacteristics::Linear`])
+    #[must_use]
+    pub fn from_matrix_with_transfer(matrix: &Matrix<u8>, from: TransferCharacteristics, to: TransferCharacteristics) -> Self {
+        Self::from_matrix_internal(matrix, 256, 255, Some((from, to)))
+    }
+}
+
+impl Lut<u16> {
+    /// Tables for input with `depth` bits, and 16-bit output. Results are within ±1 of [`RGBConvert::<u16>::new`](RGBConvert#method.new-1).
+    pub fn new(range: Range, matrix: MatrixCoefficients, depth: impl Into<BitDepth>) -> Result<Self, Error> {
+        let depth = depth.into();
+        match RGBConvert::<u16>::new(range, matrix, depth)? {
+            RGBConvert::Matrix(m) => Ok(Self::from_matrix(&m, depth)),
+            _ => Err(Error::UnsupportedMatrixCoefficients),
+        }
+    }
+
+    /// Tables with the same coefficients, rounding and clamping as the `matrix`. Input values above `depth` are clamped.
+    #[must_use]
+    pub fn from_matrix(matrix: &Matrix<u16>, depth: impl Into<BitDepth>) -> Self {
+        Self::from_matrix_internal(matrix, 1 << depth.into().bits(), 65535, None)
+    }
+
+    /// Also decode the RGB output from the `from` transfer function, and encode it with `to` (e.g. [`TransferCharacteristics::Linear`])
+    #[must_use]
+    pub fn from_matrix_with_transfer(matrix: &Matrix<u16>, depth: impl Into<BitDepth>, from: TransferCharacteristics, to: TransferCharacteristics) -> Self {
+        Self::from_matrix_internal(matrix, 1 << depth.into().bits(), 65535, Some((from, to)))
+    }
+}
+
+impl<T: Sample> ToRGB<T, T> for Lut<T> {
+    #[inline]
+    fn to_rgb(&self, px: YUV<T>) -> Rgb<T> {
+        self.tables.rgb(px.y.index(), px.u.index(), px.v.index()).map(T::from_code)
+    }
+
+    #[inline]
+    fn to_luma(&self, y: T) -> T {
+        T::from_code(self.tables.output(Tables::index(&self.tables.y, y.index())))
+    }
+}
+
+impl ToRGB<u8, f32> for Lut<u8> {
+    #[inline]
+    fn to_rgb(&self, px: YUV<u8>) -> Rgb<f32> {
+        ToRGB::<u8, u8>::to_rgb(self, px).map(|c| f32::from(c) / 255.)
+    }
+
+    #[inline]
+    fn to_luma(&self, y: u8) -> f32 {
+        f32::from(ToRGB::<u8, u8>::to_luma(self, y)) / 255.
+    }
+}
+
+impl ToRGB<u16, f32> for Lut<u16> {
+    #[inline]
+    fn to_rgb(&self, px: YUV<u16>) -> Rgb<f32> {
+        ToRGB::<u16, u16>::to_rgb(self, px).map(|c| f32::from(c) / 65535.)
+    }
+
+    #[inline]
+    fn to_luma(&self, y: u16) -> f32 {
+        f32::from(ToRGB::<u16, u16>::to_luma(self, y)) / 65535.
+    }
+}
+
+#[test]
+fn lut_matches_matrix() {
+    for range in [Range::Full, Range::Limited] {
+        let float = RGBConvert::<u8>::new(range, MatrixCoefficients::BT601).unwrap();
+        let lut = Lut::<u8>::new(range, MatrixCoefficients::BT601).unwrap();
+        for y in (0..=255).step_by(3) {
+            for u in (0..=255).step_by(5) {
+                for v in (0..=255).step_by(7) {
+                    let px = YUV { y, u, v };
+                    let (a, b) = (float.to_rgb(px), lut.to_rgb(px));
+                    assert!(a.r.abs_diff(b.r) <= 1 && a.g.abs_diff(b.g) <= 1 && a.b.abs_diff(b.b) <= 1, "{px:?} {a:?} {b:?}");
+                }
+            }
+            assert!(float.to_luma(y).abs_diff(lut.to_luma(y)) <= 1);
+        }
+    }
+
+    use crate::color::Depth;
+
+    let RGBConvert::Matrix(m) = RGBConvert::<u16>::new(Range::Limited, MatrixCoefficients::BT2020NCL, Depth::Depth10).unwrap() else { panic!() };
+    let lut = Lut::<u16>::from_matrix(&m, Depth::Depth10);
+    for y in (0..1024).step_by(3) {
+        for uv in (0..1024).step_by(7) {
+            let px = YUV { y, u: uv, v: 1023 - uv };
+            let (a, b) = (ToRGB::<u16, u16>::to_rgb(&m, px), lut.to_rgb(px));
+            assert!(a.r.abs_diff(b.r) <= 1 && a.g.abs_diff(b.g) <= 1 && a.b.abs_diff(b.b) <= 1, "{px:?} {a:?} {b:?}");
+        }
+    }
+}
+
+#[test]
+fn lut_transfer() {
+    let RGBConvert::Matrix(m) = RGBConvert::<u8>::new(Range::Full, MatrixCoefficients::BT709).unwrap() else { panic!() };
+    let lut = Lut::<u8>::from_matrix_with_transfer(&m, TransferCharacteristics::SRGB, TransferCharacteristics::Linear);
+    let gray = |y| ToRGB::<u8, u8>::to_rgb(&lut, YUV { y, u: 128, v: 128 });
+    assert_eq!(Rgb::new(0, 0, 0), gray(0));
+    assert_eq!(Rgb::new(255, 255, 255), gray(255));
+    assert_eq!(Rgb::new(55, 55, 55), gray(128));
+
+    // converters are shared between threads
+    let lut2 = lut.clone();
+    assert_eq!(Rgb::new(55, 55, 55), std::thread::spawn(move || ToRGB::<u8, u8>::to_rgb(&lut2, YUV { y: 128, u: 128, v: 128 })).join().unwrap());
+}
@@ -0,0 +1,192 @@
+//! Names of the [`color`](crate::color) enums, as used by FFmpeg, with aliases from mpv and GStreamer.
+//!
+//! Parsing is case-insensitive, and ignores `.`, and `_` vs `-` differences, so `bt.709`, `BT709` and `Bt_709` are the same.
+
+#![allow(deprecated)]
+
+use crate::color::{ChromaSamplePosition, ChromaSampling, ColorPrimaries, Depth, MatrixCoefficients, Range, TransferCharacteristics};
+use crate::Error;
+use core::fmt;
+use core::str::FromStr;
+
+/// `name` compared with the canonical-ish `known` name, which must be lowercase and use only `-`
+fn matches(name: &str, known: &str) -> bool {
+    let name = name.bytes().filter(|&c| c != b'.').map(|c| if c == b'_' { b'-' } else { c.to_ascii_lowercase() });
+    name.eq(known.bytes().filter(|&c| c != b'.'))
+}
+
+macro_rules! names {
+    ($ty:ident { $($variant:ident => $name:literal $(| $alias:literal)*,)+ }) => {
+        impl $ty {
+            /// Short name of this value, the same as FFmpeg's where it has one
+            #[must_use]
+            pub const fn name(&self) -> &'static str {
+                match self {
+                    $(Self::$variant => $name,)+
+                }
+            }
+        }
+
+        impl fmt::Display for $ty {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.name())
+            }
+        }
+
+        impl FromStr for $ty {
+            type Err = Error;
+
+            fn from_str(s: &str) -> Result<Self, Error> {
+                let s = s.trim();
+                $(if matches(s, $name) $(|| matches(s, $alias))* {
+                    return Ok(Self::$variant);
+                })+
+                Err(Error::UnknownName)
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl serde::Serialize for $ty {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(self.name())
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl<'de> serde::Deserialize<'de> for $ty {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                deserializer.deserialize_str(NameVisitor(core::marker::PhantomData))
+            }
+        }
+    };
+}
+
+names!(ChromaSampling {
+    Cs420 => "420" | "4:2:0" | "yuv420p" | "i420",
+    Cs422 => "422" | "4:2:2" | "yuv422p",
+    Cs444 => "444" | "4:4:4" | "yuv444p",
+    Monochrome => "400" | "4:0:0" | "gray" | "mono" | "monochrome",
+});
+
+names!(Range {
+    Limited => "tv" | "limited" | "mpeg" | "studio" | "16-235",
+    Full => "pc" | "full" | "jpeg" | "0-255",
+});
+
+names!(ColorPrimaries {
+    BT709 => "bt709" | "srgb",
+    BT470M => "bt470m",
+    BT470BG => "bt470bg" | "bt601-625" | "pal",
+    BT601 => "smpte170m" | "bt601" | "bt601-525" | "smpte240m" | "ntsc",
+    GenericFilm => "film" | "film-c",
+    BT2020 => "bt2020",
+    XYZ => "smpte428" | "smpte428-1" | "smpte-st-428" | "xyz" | "cie1931",
+    SMPTE431 => "smpte431" | "smpte-rp-431" | "dci-p3",
+    SMPTE432 => "smpte432" | "smpte-eg-432" | "display-p3" | "p3-d65",
+    EBU3213 => "ebu3213" | "ebu-3213" | "jedec-p22",
+});
+
+names!(TransferCharacteristics {
+    BT709 => "bt709" | "bt1886",
+    BT470M => "bt470m" | "gamma22",
+    BT470BG => "bt470bg" | "gamma28",
+    BT601 => "smpte170m" | "bt601",
+    SMPTE240 => "smpte240m",
+    Linear => "linear" | "gamma10",
+    Log100 => "log100" | "log",
+    Log100Sqrt10 => "log316" | "log-sqrt",
+    IEC61966 => "iec61966-2-4" | "xvycc",
+    BT1361 => "bt1361e" | "bt1361",
+    SRGB => "iec61966-2-1" | "srgb",
+    BT2020_10Bit => "bt2020-10" | "bt2020-10bit",
+    BT2020_12Bit => "bt2020-12" | "bt2020-12bit",
+    SMPTE2084 => "smpte2084" | "pq" | "st2084",
+    SMPTE428 => "smpte428" | "smpte428-1" | "st428",
+    HLG => "arib-std-b67" | "hlg",
+});
+
+names!(MatrixCoefficients {
+    Identity => "gbr" | "rgb" | "identity",
+    BT709 => "bt709",
+    FCC => "fcc",
+    BT470BG => "bt470bg",
+    BT601 => "smpte170m" | "bt601",
+    SMPTE240 => "smpte240m",
+    YCgCo => "ycgco" | "ycocg",
+    BT2020NCL => "bt2020nc" | "bt2020-ncl" | "bt2020",
+    BT2020CL => "bt2020c" | "bt2020-cl",
+    SMPTE2085 => "smpte2085",
+    ChromatNCL => "chroma-derived-nc",
+    ChromatCL => "chroma-derived-c",
+    ICtCp => "ictcp",
+});
+
+names!(ChromaSamplePosition {
+    Vertical => "left" | "mpeg2" | "mpeg2/4",
+    Colocated => "topleft" | "top-left" | "cosited",
+});
+
+names!(Depth {
+    Depth8 => "8",
+    Depth10 => "10",
+    Depth12 => "12",
+    Depth16 => "16",
+});
+
+#[cfg(feature = "serde")]
+struct NameVisitor<T>(core::marker::PhantomData<T>);
+
+#[cfg(feature = "serde")]
+impl<T: FromStr> serde::de::Visitor<'_> for NameVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a color space name")
+    }
+
+    fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<T, E> {
+        v.parse().map_err(|_| E::invalid_value(serde::de::Unexpected::Str(v), &self))
+    }
+}
+
+#[test]
+#[cfg(not(any(not(feature = "std"), feature = "no_std")))]
+fn names_round_trip() {
+    use crate::color::*;
+
+    assert_eq!("bt2020nc", MatrixCoefficients::BT2020NCL.to_string());
+    assert_eq!("arib-std-b67", TransferCharacteristics::HLG.to_string());
+    assert_eq!("tv", Range::Limited.to_string());
+    for tc in [TransferCharacteristics::BT709, TransferCharacteristics::BT470M, TransferCharacteristics::BT1361, TransferCharacteristics::SRGB, TransferCharacteristics::SMPTE2084] {
+        assert_eq!(tc, tc.to_string().parse().unwrap());
+    }
+    for p in [ColorPrimaries::BT601, ColorPrimaries::XYZ, ColorPrimaries::EBU3213] {
+        assert_eq!(p, p.name().parse().unwrap());
+    }
+
+    // FFmpeg option spellings, mpv, GStreamer
+    assert_eq!(TransferCharacteristics::SMPTE2084, "PQ".parse().unwrap());
+    assert_eq!(TransferCharacteristics::BT470M, "gamma2.2".parse().unwrap());
+    assert_eq!(TransferCharacteristics::BT2020_10Bit, "bt2020_10bit".parse().unwrap());
+    assert_eq!(MatrixCoefficients::BT709, "bt.709".parse().unwrap());
+    assert_eq!(MatrixCoefficients::BT2020NCL, "bt.2020-ncl".parse().unwrap());
+    assert_eq!(ColorPrimaries::BT601, "bt.601-525".parse().unwrap());
+    assert_eq!(ColorPrimaries::SMPTE431, "smpte-rp-431".parse().unwrap());
+    assert_eq!(Range::Full, " JPEG ".parse().unwrap());
+    assert_eq!(ChromaSampling::Cs420, "4:2:0".parse().unwrap());
+    assert_eq!(Depth::Depth10, "10".parse().unwrap());
+    assert!("bt7090".parse::<MatrixCoefficients>().is_err());
+    assert!("".parse::<Range>().is_err());
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn names_serde() {
+    use serde::de::IntoDeserializer;
+    use serde::Deserialize;
+
+    let de: serde::de::value::StrDeserializer<'_, serde::de::value::Error> = "smpte2084".into_deserializer();
+    assert_eq!(TransferCharacteristics::SMPTE2084, TransferCharacteristics::deserialize(de).unwrap());
+    let de: serde::de::value::StrDeserializer<'_, serde::de::value::Error> = "nope".into_deserializer();
+    assert!(Range::deserialize(de).is_err());
+}
@@ -0,0 +1,301 @@
+//! Binary PPM (RGB), PGM (one plane) and PFM (float RGB) images, for looking at intermediate results of conversions.
+//!
+//! Writers take rows with a stride, like [`YUVFrameRef::convert`](crate::frame::YUVFrameRef::convert) outputs and frame planes.
+//! Readers return `None` at the end of the file, so they can read concatenated images.
+//!
+//! ```
+//! # use yuv::pnm::*;
+//! # use yuv::RGB;
+//! let pixels = [RGB::new(0u8, 128, 255), RGB::new(1, 2, 3)];
+//! let mut file = Vec::new();
+//! write_ppm(&mut file, 2, 1, &pixels, 2, 255)?;
+//!
+//! let image = read_ppm(&file[..])?.unwrap();
+//! assert_eq!(image.pixels[0], RGB::new(0u16, 128, 255));
+//! # Ok::<_, std::io::Error>(())
+//! ```
+
+use crate::convert::Sample;
+use crate::y4m::read_exact_vec;
+use crate::Error;
+use rgb::{Rgb, Rgba};
+use std::io::{self, BufRead, Write};
+
+/// Longest header field accepted
+const MAX_TOKEN: usize = 32;
+
+/// Image read from a file. Rows are top to bottom, without padding.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PnmImage<P> {
+    pub width: usize,
+    pub height: usize,
+    /// Largest sample value (`maxval` of the header). It's 1 for PFM.
+    pub max: u16,
+    pub pixels: Vec<P>,
+}
+
+/// [`Rgb`] or [`Rgba`] pixel. Alpha isn't written.
+pub trait RgbPixel: Copy {
+    type Component: Sample;
+
+    /// Red, green and blue
+    fn rgb(self) -> [Self::Component; 3];
+}
+
+impl<T: Sample> RgbPixel for Rgb<T> {
+    type Component = T;
+
+    #[inline(always)]
+    fn rgb(self) -> [T; 3] {
+        [self.r, self.g, self.b]
+    }
+}
+
+impl<T: Sample> RgbPixel for Rgba<T> {
+    type Component = T;
+
+    #[inline(always)]
+    fn rgb(self) -> [T; 3] {
+        [self.r, self.g, self.b]
+    }
+}
+
+/// Writes a `P6` image with samples from 0 to `max`, in one byte if `max` is at most 255, or two bytes otherwise.
+///
+/// Float samples (`0..=1`) are scaled to `max`. Integer samples are written as-is (use `max` of 1023 for 10-bit), and clamped to `max`.
+pub fn write_ppm<P: RgbPixel>(mut out: impl Write, width: usize, height: usize, pixels: &[P], stride: usize, max: u16) -> io::Result<()> {
+    check(pixels, width, height, stride, max)?;
+    write!(out, "P6\n{width} {height}\n{max}\n")?;
+    let mut buf = Vec::new();
+    for row in pixels.chunks(stride.max(1)).take(height) {
+        buf.clear();
+        for px in &row[..width] {
+            for v in px.rgb() {
+                push_code(&mut buf, v, max);
+            }
+        }
+        out.write_all(&buf)?;
+    }
+    Ok(())
+}
+
+/// Writes a `P5` image of one plane, e.g. [`YUVFrameRef::y`](crate::frame::YUVFrameRef::y). Samples are written as in [`write_ppm`].
+pub fn write_pgm<T: Sample>(mut out: impl Write, width: usize, height: usize, plane: &[T], stride: usize, max: u16) -> io::Result<()> {
+    check(plane, width, height, stride, max)?;
+    write!(out, "P5\n{width} {height}\n{max}\n")?;
+    let mut buf = Vec::new();
+    for row in plane.chunks(stride.max(1)).take(height) {
+        buf.clear();
+        for &v in &row[..width] {
+            push_code(&mut buf, v, max);
+        }
+        out.write_all(&buf)?;
+    }
+    Ok(())
+}
+
+/// Writes a little-endian `PF` image. Integer samples are divided by the largest value of their type (255 or 65535).
+///
+/// Float samples aren't clamped, so out-of-gamut and linear light values above 1 are kept.
+pub fn write_pfm<P: RgbPixel>(mut out: impl Write, width: usize, height: usize, pixels: &[P], stride: usize) -> io::Result<()> {
+    check(pixels, width, height, stride, 1)?;
+    write!(out, "PF\n{width} {height}\n-1.0\n")?;
+    let mut buf = Vec::new();
+    // PFM rows are bottom to top
+    for row in pixels.chunks(stride.max(1)).take(height).rev() {
+        buf.clear();
+        for px in &row[..width] {
+            for v in px.rgb() {
+                buf.extend_from_slice(&normalized(v).to_le_bytes());
+            }
+        }
+        out.write_all(&buf)?;
+    }
+    Ok(())
+}
+
+/// Reads a `P6` image. One-byte samples are widened to `u16`, but not scaled.
+pub fn read_ppm(mut reader: impl BufRead) -> io::Result<Option<PnmImage<Rgb<u16>>>> {
+    let Some((width, height, max)) = read_header(&mut reader, "P6")? else {
+        return Ok(None);
+    };
+    let max = parse_max(&max)?;
+    let samples = read_samples(&mut reader, width, height, 3, max)?;
+    let pixels = samples.chunks_exact(3).map(|px| Rgb::new(px[0], px[1], px[2])).collect();
+    Ok(Some(PnmImage { width, height, max, pixels }))
+}
+
+/// Reads a `P5` image. One-byte samples are widened to `u16`, but not scaled.
+pub fn read_pgm(mut reader: impl BufRead) -> io::Result<Option<PnmImage<u16>>> {
+    let Some((width, height, max)) = read_header(&mut reader, "P5")? else {
+        return Ok(None);
+    };
+    let max = parse_max(&max)?;
+    let pixels = read_samples(&mut reader, width, height, 1, max)?;
+    Ok(Some(PnmImage { width, height, max, pixels }))
+}
+
+/// Reads a `PF` image of either byte order, and flips its rows to be top to bottom
+pub fn read_pfm(mut reader: impl BufRead) -> io::Result<Option<PnmImage<Rgb<f32>>>> {
+    let Some((width, height, scale)) = read_header(&mut reader, "PF")? else {
+        return Ok(None);
+    };
+    let scale: f32 = scale.parse().map_err(|_| invalid_header())?;
+    let len = width.checked_mul(height).and_then(|px| px.checked_mul(12)).ok_or_else(invalid_header)?;
+    let mut buf = Vec::new();
+    read_exact_vec(&mut reader, &mut buf, len)?;
+    let mut pixels: Vec<_> = buf.chunks_exact(12).map(|px| {
+        let [r, g, b] = [0, 4, 8].map(|i| {
+            let bytes = [px[i], px[i + 1], px[i + 2], px[i + 3]];
+            if scale < 0. { f32::from_le_bytes(bytes) } else { f32::from_be_bytes(bytes) }
+        });
+        Rgb::new(r, g, b)
+    }).collect();
+    if width > 0 {
+        let rows: Vec<_> = pixels.chunks_exact(width).rev().flatten().copied().collect();
+        pixels = rows;
+    }
+    Ok(Some(PnmImage { width, height, max: 1, pixels }))
+}
+
+fn check<T>(pixels: &[T], width: usize, height: usize, stride: usize, max: u16) -> io::Result<()> {
+    if max == 0 || (height > 0 && (stride < width || pixels.len() < stride * (height - 1) + width)) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, Error::InvalidFrameSize));
+    }
+    Ok(())
+}
+
+#[inline]
+fn push_code<T: Sample>(buf: &mut Vec<u8>, v: T, max: u16) {
+    let v: f32 = v.into();
+    let code = if T::MAX_DEPTH == 0 { (v.clamp(0., 1.) * f32::from(max)).round() as u16 } else { (v as u16).min(max) };
+    if max > 255 {
+        buf.extend_from_slice(&code.to_be_bytes());
+    } else {
+        buf.push(code as u8);
+    }
+}
+
+#[inline]
+fn normalized<T: Sample>(v: T) -> f32 {
+    if T::MAX_DEPTH == 0 {
+        v.into()
+    } else {
+        v.into() / ((1u32 << T::MAX_DEPTH) - 1) as f32
+    }
+}
+
+fn invalid_header() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, Error::InvalidHeader)
+}
+
+fn parse_max(max: &str) -> io::Result<u16> {
+    max.parse().ok().filter(|&max| max > 0).ok_or_else(invalid_header)
+}
+
+/// Width, height and the third field. `None` if there's only whitespace left.
+fn read_header(r: &mut impl BufRead, magic: &str) -> io::Result<Option<(usize, usize, String)>> {
+    loop {
+        match r.fill_buf()?.first() {
+            None => return Ok(None),
+            Some(c) if c.is_ascii_whitespace() => r.consume(1),
+            Some(_) => break,
+        }
+    }
+    if read_token(r)? != magic {
+        return Err(invalid_header());
+    }
+    let width = read_token(r)?.parse().map_err(|_| invalid_header())?;
+    let height = read_token(r)?.parse().map_err(|_| invalid_header())?;
+    Ok(Some((width, height, read_token(r)?)))
+}
+
+/// Header field, skipping `#` comments. Consumes the one whitespace byte after it.
+fn read_token(r: &mut impl BufRead) -> io::Result<String> {
+    let mut token = String::new();
+    let mut byte = [0];
+    loop {
+        r.read_exact(&mut byte)?;
+        match byte[0] {
+            b'#' => {
+                r.read_until(b'\n', &mut Vec::new())?;
+            },
+            c if c.is_ascii_whitespace() => if !token.is_empty() {
+                return Ok(token);
+            },
+            c if token.len() < MAX_TOKEN => token.push(c.into()),
+            _ => return Err(invalid_header()),
+        }
+    }
+}
+
+fn read_samples(r: &mut impl BufRead, width: usize, height: usize, channels: usize, max: u16) -> io::Result<Vec<u16>> {
+    let bytes = if max > 255 { 2 } else { 1 };
+    let len = width.checked_mul(height).and_then(|px| px.checked_mul(channels * bytes)).ok_or_else(invalid_header)?;
+    let mut buf = Vec::new();
+    read_exact_vec(r, &mut buf, len)?;
+    Ok(if bytes == 2 {
+        buf.chunks_exact(2).map(|b| u16::from_be_bytes([b[0], b[1]])).collect()
+    } else {
+        buf.into_iter().map(u16::from).collect()
+    })
+}
+
+#[test]
+fn pnm_round_trip() {
+    // stride of 3, 10-bit in u16
+    let pixels = [Rgb::new(0u16, 1, 1023), Rgb::new(512, 2, 3), Rgb::new(9, 9, 9), Rgb::new(4, 5, 6), Rgb::new(7, 8, 2000), Rgb::new(9, 9, 9)];
+    let mut file = Vec::new();
+    write_ppm(&mut file, 2, 2, &pixels, 3, 1023).unwrap();
+    assert!(file.starts_with(b"P6\n2 2\n1023\n"));
+    // and a second image in the same file
+    write_ppm(&mut file, 1, 1, &[Rgba::new(0.5f32, 1., 2., 0.)], 1, 255).unwrap();
+    let mut r = &file[..];
+    let image = read_ppm(&mut r).unwrap().unwrap();
+    assert_eq!((image.width, image.height, image.max), (2, 2, 1023));
+    assert_eq!(image.pixels, [pixels[0], pixels[1], pixels[3], Rgb::new(7, 8, 1023)]);
+    let image = read_ppm(&mut r).unwrap().unwrap();
+    assert_eq!((image.max, image.pixels[0]), (255, Rgb::new(128, 255, 255)));
+    assert!(read_ppm(&mut r).unwrap().is_none());
+
+    let plane = [16u8, 17, 18, 19];
+    let mut file = Vec::new();
+    write_pgm(&mut file, 1, 2, &plane, 2, 255).unwrap();
+    assert_eq!(read_pgm(&file[..]).unwrap().unwrap().pixels, [16, 18]);
+
+    let pixels = [Rgb::new(0.25f32, -1., 3.), Rgb::new(1., 0., 0.5)];
+    let mut file = Vec::new();
+    write_pfm(&mut file, 1, 2, &pixels, 1).unwrap();
+    assert!(file.starts_with(b"PF\n1 2\n-1.0\n"));
+    // bottom row first
+    assert_eq!(&file[12..16], &1f32.to_le_bytes());
+    assert_eq!(read_pfm(&file[..]).unwrap().unwrap().pixels, pixels);
+    let mut file = Vec::new();
+    write_pfm(&mut file, 1, 1, &[Rgb::new(0u8, 51, 255)], 1).unwrap();
+    assert_eq!(read_pfm(&file[..]).unwrap().unwrap().pixels, [Rgb::new(0., 0.2, 1.)]);
+
+    assert!(write_ppm(Vec::new(), 2, 2, &pixels, 2, 255).is_err());
+}
+
+#[test]
+fn pnm_header() {
+    let file = b"P6 # comment\n2 1\n# another\n65535\n\0\x01\0\x02\0\x03\xff\xff\0\0\0\0\n";
+    let image = read_ppm(&file[..]).unwrap().unwrap();
+    assert_eq!((image.width, image.height, image.max), (2, 1, 65535));
+    assert_eq!(image.pixels, [Rgb::new(1, 2, 3), Rgb::new(65535, 0, 0)]);
+
+    assert!(read_ppm(&b"P5\n1 1\n255\n\0"[..]).is_err());
+    assert!(read_pgm(&b"P5\n1 1\n0\n\0"[..]).is_err());
+    assert!(read_pgm(&b"P5\n1 1\n255\n"[..]).is_err());
+    assert!(read_pgm(&b" \n"[..]).unwrap().is_none());
+}
+
+#[test]
+fn pnm_oversized_header() {
+    let file = b"P6\n1000000 1000000\n65535\n\x00\x01";
+    assert_eq!(io::ErrorKind::UnexpectedEof, read_ppm(&file[..]).unwrap_err().kind());
+    let file = b"PF\n1000000 1000000\n-1.0\n\x00\x01";
+    assert_eq!(io::ErrorKind::UnexpectedEof, read_pfm(&file[..]).unwrap_err().kind());
+    let file = b"P5\n18446744073709551615 2\n255\n";
+    assert_eq!(io::ErrorKind::InvalidData, read_pgm(&file[..]).unwrap_err().kind());
+}
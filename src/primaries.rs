@@ -0,0 +1,81 @@
+#[cfg(not(any(not(feature = "std"), feature = "no_std")))]
+use crate::color::ColorPrimaries;
+
+pub(crate) type Mat3 = [[f64; 3]; 3];
+
+/// CIE xy of red, green, blue and the white point
+#[cfg(not(any(not(feature = "std"), feature = "no_std")))]
+pub(crate) fn chromaticities(primaries: ColorPrimaries) -> [[f64; 2]; 4] {
+    const D65: [f64; 2] = [0.3127, 0.3290];
+    const C: [f64; 2] = [0.310, 0.316];
+    match primaries {
+        ColorPrimaries::BT709 => [[0.640, 0.330], [0.300, 0.600], [0.150, 0.060], D65],
+        ColorPrimaries::BT470M => [[0.670, 0.330], [0.210, 0.710], [0.140, 0.080], C],
+        ColorPrimaries::BT470BG => [[0.640, 0.330], [0.290, 0.600], [0.150, 0.060], D65],
+        ColorPrimaries::BT601 => [[0.630, 0.340], [0.310, 0.595], [0.155, 0.070], D65],
+        ColorPrimaries::GenericFilm => [[0.681, 0.319], [0.243, 0.692], [0.145, 0.049], C],
+        ColorPrimaries::BT2020 => [[0.708, 0.292], [0.170, 0.797], [0.131, 0.046], D65],
+        ColorPrimaries::XYZ => [[1., 0.], [0., 1.], [0., 0.], [1. / 3., 1. / 3.]],
+        ColorPrimaries::SMPTE431 => [[0.680, 0.320], [0.265, 0.690], [0.150, 0.060], [0.314, 0.351]],
+        ColorPrimaries::SMPTE432 => [[0.680, 0.320], [0.265, 0.690], [0.150, 0.060], D65],
+        ColorPrimaries::EBU3213 => [[0.630, 0.340], [0.295, 0.605], [0.155, 0.077], D65],
+    }
+}
+
+/// Linear RGB to CIE XYZ, with white at Y = 1
+#[cfg(not(any(not(feature = "std"), feature = "no_std")))]
+pub(crate) fn rgb_to_xyz(primaries: ColorPrimaries) -> Mat3 {
+    let [r, g, b, w] = chromaticities(primaries);
+    let xyz = |[x, y]: [f64; 2]| [x / y, 1., (1. - x - y) / y];
+    // XYZ of primaries with Y = 1 as columns, scaled so that they add up to the white point
+    let columns = [r, g, b].map(|p| if p[1] == 0. { [p[0], 0., 1. - p[0] - p[1]] } else { xyz(p) });
+    let m: Mat3 = core::array::from_fn(|i| core::array::from_fn(|j| columns[j][i]));
+    let white = xyz(w);
+    let inv = invert(&m);
+    let s: [f64; 3] = core::array::from_fn(|i| (0..3).map(|k| inv[i][k] * white[k]).sum());
+    core::array::from_fn(|i| core::array::from_fn(|j| m[i][j] * s[j]))
+}
+
+/// Linear RGB with `from` primaries to linear RGB with `to` primaries, without chromatic adaptation
+#[cfg(not(any(not(feature = "std"), feature = "no_std")))]
+pub(crate) fn convert(from: ColorPrimaries, to: ColorPrimaries) -> Mat3 {
+    mul(&invert(&rgb_to_xyz(to)), &rgb_to_xyz(from))
+}
+
+/// Bradford chromatic adaptation of XYZ from the `from` white point to the `to` white point
+#[cfg(not(any(not(feature = "std"), feature = "no_std")))]
+pub(crate) fn bradford(from: [f64; 3], to: [f64; 3]) -> Mat3 {
+    const M: Mat3 = [[0.8951, 0.2664, -0.1614], [-0.7502, 1.7135, 0.0367], [0.0389, -0.0685, 1.0296]];
+    let cone = |w: [f64; 3]| -> [f64; 3] { core::array::from_fn(|i| (0..3).map(|k| M[i][k] * w[k]).sum()) };
+    let (from, to) = (cone(from), cone(to));
+    let scale: Mat3 = core::array::from_fn(|i| core::array::from_fn(|j| if i == j { to[i] / from[i] } else { 0. }));
+    mul(&invert(&M), &mul(&scale, &M))
+}
+
+pub(crate) fn mul(a: &Mat3, b: &Mat3) -> Mat3 {
+    core::array::from_fn(|i| core::array::from_fn(|j| (0..3).map(|k| a[i][k] * b[k][j]).sum()))
+}
+
+pub(crate) fn invert(m: &Mat3) -> Mat3 {
+    let cofactor = |r: usize, c: usize| {
+        let (r0, r1, c0, c1) = ((r + 1) % 3, (r + 2) % 3, (c + 1) % 3, (c + 2) % 3);
+        m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0]
+    };
+    let det = (0..3).map(|c| m[0][c] * cofactor(0, c)).sum::<f64>();
+    core::array::from_fn(|i| core::array::from_fn(|j| cofactor(j, i) / det))
+}
+
+#[test]
+#[cfg(not(any(not(feature = "std"), feature = "no_std")))]
+fn primaries_matrices() {
+    // well-known BT.709 luma coefficients are the middle row
+    let m = rgb_to_xyz(ColorPrimaries::BT709);
+    assert!((m[1][0] - 0.2126).abs() < 1e-4 && (m[1][1] - 0.7152).abs() < 1e-4 && (m[1][2] - 0.0722).abs() < 1e-4, "{m:?}");
+
+    let m = convert(ColorPrimaries::BT709, ColorPrimaries::BT2020);
+    assert!((m[0][0] - 0.6274).abs() < 1e-3 && (m[0][1] - 0.3293).abs() < 1e-3 && (m[2][2] - 0.8955).abs() < 1e-3, "{m:?}");
+    // white stays white
+    for row in m {
+        assert!((row.iter().sum::<f64>() - 1.).abs() < 1e-9);
+    }
+}
@@ -1,4 +1,6 @@
+use crate::color;
 use crate::depth::{Bounded, Depth, Depth10, Depth12, Depth16, Depth8};
+use crate::levels::Levels;
 use num_traits::PrimInt;
 
 pub(crate) trait Range: 'static {
@@ -89,3 +91,27 @@ pub(crate) fn to_floats<F: Range>(multiply: f64) -> (RangeScale, RangeScale) whe
     };
     (y, uv)
 }
+
+/// Runtime version of the [`Range`] constants: `[Y_MIN, Y_MAX, UV_MIN, UV_MAX]`
+pub(crate) fn levels(range: color::Range, depth: color::BitDepth) -> [u16; 4] {
+    let l = match range {
+        color::Range::Full => Levels::full(depth.bits()),
+        color::Range::Limited => Levels::limited(depth.bits()),
+    };
+    [l.y_min, l.y_max, l.uv_min, l.uv_max]
+}
+
+/// Runtime version of [`to_floats`]
+pub(crate) fn scales(levels: [u16; 4], multiply: f64) -> (RangeScale, RangeScale) {
+    let [y_min, y_max, uv_min, uv_max] = levels.map(f64::from);
+    let uv_center = if uv_min == 0. { (uv_max / 2.).ceil() } else { (uv_min + uv_max) / 2. };
+    let y = RangeScale {
+        mul: (multiply / (y_max - y_min)) as f32,
+        sub: (multiply * y_min / (y_max - y_min)) as f32,
+    };
+    let uv = RangeScale {
+        mul: (multiply / (uv_max - uv_min)) as f32,
+        sub: (multiply * uv_center / (uv_max - uv_min)) as f32,
+    };
+    (y, uv)
+}
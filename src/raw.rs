@@ -0,0 +1,271 @@
+//! Headerless raw YUV files (`.yuv`), with frames stored back to back.
+//!
+//! The frame size and pixel format aren't in the file, and must be known. [`RawFormat`] parses FFmpeg's pixel format names, like `yuv420p10le` or `nv12`.
+//!
+//! ```no_run
+//! # use yuv::raw::*;
+//! let file = std::io::BufReader::new(std::fs::File::open("dump.yuv")?);
+//! let mut r = RawReader::new(file, 1920, 1080, "p010le".parse()?)?;
+//! let last = r.read_frame::<u16>(r.frame_count() - 1)?;
+//! # Ok::<_, Box<dyn std::error::Error>>(())
+//! ```
+
+use crate::color::{BitDepth, ChromaSampling, Depth};
+use crate::convert::Sample;
+use crate::frame::{chroma_size, YUVFrame};
+use crate::Error;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::str::FromStr;
+
+/// How chroma planes are stored
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Layout {
+    /// Separate Y, U and V planes (e.g. `yuv420p`, I420)
+    Planar,
+    /// Y plane, and one plane of interleaved U and V (e.g. `nv12`, `p010`)
+    SemiPlanar,
+    /// Y plane, and one plane of interleaved V and U (e.g. `nv21`)
+    SemiPlanarVU,
+}
+
+/// Byte order of samples larger than 8 bits
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Endian {
+    Little,
+    Big,
+}
+
+/// Pixel format of a raw file
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct RawFormat {
+    pub sampling: ChromaSampling,
+    /// From 8 to 16 bits, e.g. `yuv420p9` or `yuv444p14le`
+    pub depth: BitDepth,
+    pub layout: Layout,
+    pub endian: Endian,
+    /// Samples are in the high bits of 16-bit words, as in `p010`
+    pub msb_aligned: bool,
+}
+
+impl RawFormat {
+    /// Planar and little-endian
+    #[must_use]
+    pub fn planar(sampling: ChromaSampling, depth: impl Into<BitDepth>) -> Self {
+        Self { sampling, depth: depth.into(), layout: Layout::Planar, endian: Endian::Little, msb_aligned: false }
+    }
+
+    /// Bytes per sample
+    fn sample_size(&self) -> u64 {
+        if self.depth.bits() > 8 { 2 } else { 1 }
+    }
+}
+
+/// FFmpeg's `pix_fmt` names of planar and semi-planar YUV formats, e.g. `yuv422p12be`, `gray10le`, `nv21` or `p210le`
+impl FromStr for RawFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let s = s.trim().to_ascii_lowercase();
+        let (name, endian) = match s.strip_suffix("le") {
+            Some(name) => (name, Endian::Little),
+            None => match s.strip_suffix("be") {
+                Some(name) => (name, Endian::Big),
+                None => (s.as_str(), Endian::Little),
+            },
+        };
+        let depth = |bits: &str| match bits {
+            "" => Some(BitDepth::from(Depth::Depth8)),
+            _ if bits.bytes().all(|b| b.is_ascii_digit()) => BitDepth::new(bits.parse().ok()?).ok(),
+            _ => None,
+        };
+        let sampling = |s: &str| match s {
+            "420" => Some(ChromaSampling::Cs420),
+            "422" => Some(ChromaSampling::Cs422),
+            "444" => Some(ChromaSampling::Cs444),
+            _ => None,
+        };
+        let parsed = if let Some(rest) = name.strip_prefix("yuvj").or_else(|| name.strip_prefix("yuv")) {
+            rest.split_once('p').and_then(|(s, bits)| Some(Self::planar(sampling(s)?, depth(bits)?)))
+        } else if let Some(bits) = name.strip_prefix("gray") {
+            depth(bits).map(|d| Self::planar(ChromaSampling::Monochrome, d))
+        } else if let Some(n) = name.strip_prefix("nv") {
+            let (sampling, layout) = match n {
+                "12" => (ChromaSampling::Cs420, Layout::SemiPlanar),
+                "21" => (ChromaSampling::Cs420, Layout::SemiPlanarVU),
+                "16" => (ChromaSampling::Cs422, Layout::SemiPlanar),
+                "61" => (ChromaSampling::Cs422, Layout::SemiPlanarVU),
+                "24" => (ChromaSampling::Cs444, Layout::SemiPlanar),
+                "42" => (ChromaSampling::Cs444, Layout::SemiPlanarVU),
+                _ => return Err(Error::UnknownName),
+            };
+            Some(Self { layout, ..Self::planar(sampling, Depth::Depth8) })
+        } else if let Some(n) = name.strip_prefix('p') {
+            // p010, p210, p410, p016, etc.
+            let (s, bits) = n.split_at_checked(1).ok_or(Error::UnknownName)?;
+            let s = match s { "0" => "420", "2" => "422", "4" => "444", _ => return Err(Error::UnknownName) };
+            let bits = bits.strip_prefix('0').unwrap_or(bits);
+            sampling(s).zip(depth(bits)).filter(|&(_, d)| d.bits() > 8).map(|(s, d)| Self { layout: Layout::SemiPlanar, msb_aligned: true, ..Self::planar(s, d) })
+        } else {
+            None
+        };
+        let format = parsed.ok_or(Error::UnknownName)?;
+        Ok(Self { endian, ..format })
+    }
+}
+
+/// Reader of raw frames from a seekable file
+#[derive(Debug)]
+pub struct RawReader<R> {
+    reader: R,
+    width: usize,
+    height: usize,
+    format: RawFormat,
+    frame_size: u64,
+    frame_count: u64,
+    next: u64,
+    buf: Vec<u8>,
+}
+
+impl<R: Read + Seek> RawReader<R> {
+    /// Fails if the file size isn't a multiple of the frame size
+    pub fn new(mut reader: R, width: usize, height: usize, format: RawFormat) -> io::Result<Self> {
+        if format.sampling == ChromaSampling::Monochrome && format.layout != Layout::Planar {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, Error::InvalidFrameSize));
+        }
+        let (cw, ch) = chroma_size(width, height, format.sampling);
+        let samples = (width as u64).checked_mul(height as u64)
+            .and_then(|y| (cw as u64 * ch as u64).checked_mul(2)?.checked_add(y))
+            .and_then(|s| s.checked_mul(format.sample_size()))
+            .filter(|&s| s > 0)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, Error::InvalidFrameSize))?;
+
+        let len = reader.seek(SeekFrom::End(0))? - reader.seek(SeekFrom::Start(0))?;
+        if len % samples != 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+                "File size {len} isn't a multiple of the {width}x{height} frame size {samples}: {} frames and {} extra bytes",
+                len / samples, len % samples,
+            )));
+        }
+        Ok(Self { reader, width, height, format, frame_size: samples, frame_count: len / samples, next: 0, buf: Vec::new() })
+    }
+
+    /// Number of complete frames in the file
+    #[inline]
+    #[must_use]
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// Bytes per frame
+    #[inline]
+    #[must_use]
+    pub fn frame_size(&self) -> u64 {
+        self.frame_size
+    }
+
+    /// Seeks to the frame at `index` (from 0), and reads it. Iteration continues after it.
+    ///
+    /// `T` must be `u8` for 8-bit formats, and `u16` otherwise. Chroma position is unknown, and set to centered.
+    pub fn read_frame<T: Sample>(&mut self, index: u64) -> io::Result<YUVFrame<T>> {
+        let expected = if self.format.depth.bits() > 8 { 16 } else { 8 };
+        if T::MAX_DEPTH != expected {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, Error::InvalidDepthRequested));
+        }
+        if index >= self.frame_count {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, Error::InvalidFrameSize));
+        }
+        self.reader.seek(SeekFrom::Start(index * self.frame_size))?;
+        self.buf.resize(self.frame_size as usize, 0);
+        self.reader.read_exact(&mut self.buf)?;
+        self.next = index + 1;
+
+        let shift = if self.format.msb_aligned { 16 - u32::from(self.format.depth.bits()) } else { 0 };
+        let samples = self.buf.chunks_exact(self.format.sample_size() as usize).map(|b| T::from_code(match (b, self.format.endian) {
+            (&[b], _) => b.into(),
+            (&[a, b], Endian::Little) => (u16::from_le_bytes([a, b]) >> shift).into(),
+            (&[a, b], Endian::Big) => (u16::from_be_bytes([a, b]) >> shift).into(),
+            _ => unreachable!(),
+        }));
+        let mut samples = samples.collect::<Vec<T>>();
+        let chroma = samples.split_off(self.width * self.height);
+        let (u, v) = match self.format.layout {
+            Layout::Planar => {
+                let (u, v) = chroma.split_at(chroma.len() / 2);
+                (u.to_vec(), v.to_vec())
+            },
+            Layout::SemiPlanar => chroma.chunks_exact(2).map(|uv| (uv[0], uv[1])).unzip(),
+            Layout::SemiPlanarVU => chroma.chunks_exact(2).map(|vu| (vu[1], vu[0])).unzip(),
+        };
+        Ok(YUVFrame {
+            width: self.width,
+            height: self.height,
+            y: samples,
+            u,
+            v,
+            sampling: self.format.sampling,
+            chroma_position: None,
+        })
+    }
+}
+
+impl<R: Read + Seek> Iterator for RawReader<R> {
+    type Item = io::Result<YUVFrame<u16>>;
+
+    /// Frames of any depth as `u16`. For 8-bit formats, use [`RawReader::read_frame`] with `u8` instead.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next >= self.frame_count {
+            return None;
+        }
+        if self.format.depth.bits() == 8 {
+            return Some(self.read_frame::<u8>(self.next).map(YUVFrame::from));
+        }
+        Some(self.read_frame::<u16>(self.next))
+    }
+}
+
+#[test]
+fn raw_formats() {
+    assert_eq!(RawFormat::planar(ChromaSampling::Cs420, Depth::Depth8), "yuv420p".parse().unwrap());
+    assert_eq!(RawFormat { endian: Endian::Big, ..RawFormat::planar(ChromaSampling::Cs422, Depth::Depth12) }, "yuv422p12be".parse().unwrap());
+    assert_eq!(RawFormat::planar(ChromaSampling::Monochrome, Depth::Depth10), "gray10le".parse().unwrap());
+    let p010: RawFormat = "p010le".parse().unwrap();
+    assert_eq!((p010.sampling, p010.depth, p010.layout, p010.msb_aligned), (ChromaSampling::Cs420, Depth::Depth10.into(), Layout::SemiPlanar, true));
+    assert_eq!(Layout::SemiPlanarVU, "nv21".parse::<RawFormat>().unwrap().layout);
+    assert_eq!(ChromaSampling::Cs444, "p416le".parse::<RawFormat>().unwrap().sampling);
+    assert_eq!(RawFormat::planar(ChromaSampling::Cs420, BitDepth::new(9).unwrap()), "yuv420p9".parse().unwrap());
+    assert_eq!(RawFormat::planar(ChromaSampling::Cs444, BitDepth::new(14).unwrap()), "yuv444p14le".parse().unwrap());
+    assert_eq!(RawFormat::planar(ChromaSampling::Monochrome, BitDepth::new(9).unwrap()), "gray9".parse().unwrap());
+    assert!("yuv420p17".parse::<RawFormat>().is_err());
+    assert!("yuv420p+9".parse::<RawFormat>().is_err());
+    assert!("rgb24".parse::<RawFormat>().is_err());
+}
+
+#[test]
+fn raw_reader() {
+    use std::io::Cursor;
+
+    // 2x2 nv12, 3 frames
+    let file: Vec<u8> = (0..3).flat_map(|f| [f, 1, 2, 3, 100 + f, 200]).collect();
+    let mut r = RawReader::new(Cursor::new(&file), 2, 2, "nv12".parse().unwrap()).unwrap();
+    assert_eq!((r.frame_count(), r.frame_size()), (3, 6));
+    let frame = r.read_frame::<u8>(2).unwrap();
+    assert_eq!((frame.y.as_slice(), frame.u.as_slice(), frame.v.as_slice()), (&[2, 1, 2, 3][..], &[102][..], &[200][..]));
+    assert!(r.read_frame::<u8>(3).is_err());
+    let frames = RawReader::new(Cursor::new(&file), 2, 2, "nv12".parse().unwrap()).unwrap().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(frames.len(), 3);
+    assert_eq!(frames[1].u, [101]);
+
+    // big-endian 10-bit planar 4:4:4, and p010
+    let file = [0x03, 0xff, 0, 1, 0, 2];
+    let frame = RawReader::new(Cursor::new(&file), 1, 1, "yuv444p10be".parse().unwrap()).unwrap().read_frame::<u16>(0).unwrap();
+    assert_eq!((frame.y[0], frame.u[0], frame.v[0]), (1023, 1, 2));
+    let file = [0xc0, 0xff, 0, 0x80, 0x40, 0];
+    let frame = RawReader::new(Cursor::new(&file), 1, 1, "p410le".parse().unwrap()).unwrap().read_frame::<u16>(0).unwrap();
+    assert_eq!((frame.y[0], frame.u[0], frame.v[0]), (1023, 512, 1));
+    let file = [0xff, 0x3f, 0, 0x20, 1, 0];
+    let frame = RawReader::new(Cursor::new(&file), 1, 1, "yuv444p14le".parse().unwrap()).unwrap().read_frame::<u16>(0).unwrap();
+    assert_eq!((frame.y[0], frame.u[0], frame.v[0]), (16383, 8192, 1));
+
+    let err = RawReader::new(Cursor::new(&[0u8; 13]), 2, 2, "yuv420p".parse().unwrap()).unwrap_err();
+    assert_eq!(err.to_string(), "File size 13 isn't a multiple of the 2x2 frame size 6: 2 frames and 1 extra bytes");
+}
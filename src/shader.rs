@@ -0,0 +1,347 @@
+//! Generates GPU shader functions that convert YUV to RGB the same way as an [`RGBConvert`].
+//!
+//! The output is a self-contained function (plus helpers) in GLSL, WGSL or HLSL,
+//! with the matrix and range offsets baked in as constants.
+//! It can also convert transfer functions and color primaries (in linear light).
+//!
+//! ```
+//! # use yuv::color::*;
+//! # use yuv::convert::RGBConvert;
+//! # use yuv::shader::{Language, Shader};
+//! let conv = RGBConvert::<u16>::new(Range::Limited, MatrixCoefficients::BT2020NCL, Depth::Depth10)?;
+//! let glsl = Shader::new(&conv)
+//!     .with_input_max(1023.) // from a texture that has 10-bit values normalized to 0..1
+//!     .with_transfer(TransferCharacteristics::SMPTE2084, TransferCharacteristics::SRGB)
+//!     .with_primaries(ColorPrimaries::BT2020, ColorPrimaries::BT709)
+//!     .generate(Language::Glsl)?;
+//! assert!(glsl.contains("vec3 yuv_to_rgb(vec3 yuv)"));
+//! # Ok::<_, yuv::Error>(())
+//! ```
+
+use crate::color::{ColorPrimaries, TransferCharacteristics};
+use crate::convert::{Coefficients, Lut, RGBConvert, Sample};
+use crate::primaries;
+use crate::transfer::{BT709_ALPHA, BT709_BETA, HLG_A, HLG_B, HLG_C, PQ_C1, PQ_C2, PQ_C3, PQ_M1, PQ_M2};
+use crate::Error;
+use std::fmt::Write;
+
+/// Shading language to generate
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Language {
+    /// GLSL 3.30+ or GLSL ES 3.00+
+    Glsl,
+    /// WebGPU Shading Language
+    Wgsl,
+    /// HLSL Shader Model 4+
+    Hlsl,
+}
+
+/// Configuration of a generated shader function. See [`Shader::new`]
+#[derive(Debug, Clone)]
+pub struct Shader {
+    coefficients: Coefficients,
+    clamp: bool,
+    transfer: Option<(TransferCharacteristics, TransferCharacteristics)>,
+    primaries: Option<(ColorPrimaries, ColorPrimaries)>,
+    name: String,
+}
+
+impl Shader {
+    /// Same conversion as the `conv`. The function takes YUV code values (e.g. `0..=255`), and returns RGB in `0..=1`.
+    ///
+    /// [`Clamp::HuePreserving`](crate::convert::Clamp::HuePreserving) is approximated by clamping each channel.
+    #[must_use]
+    pub fn new<T: Sample>(conv: &RGBConvert<T>) -> Self {
+        let (coefficients, clamp, transfer) = conv.linear_parts();
+        Self {
+            coefficients,
+            clamp,
+            transfer,
+            primaries: None,
+            name: "yuv_to_rgb".into(),
+        }
+    }
+
+    /// Same conversion as the `lut`, including its transfer function conversion
+    #[must_use]
+    pub fn from_lut<T>(lut: &Lut<T>) -> Self {
+        let (coefficients, transfer) = lut.source();
+        Self {
+            coefficients,
+            clamp: true,
+            transfer,
+            primaries: None,
+            name: "yuv_to_rgb".into(),
+        }
+    }
+
+    /// Take input normalized to `0..=1` by dividing code values by `max`, as sampled from unorm textures.
+    ///
+    /// For example, 10-bit values in a 16-bit texture need 65535, and in a 10-bit texture 1023.
+    #[must_use]
+    pub fn with_input_max(mut self, max: f32) -> Self {
+        self.coefficients = self.coefficients.with_input_max(max);
+        self
+    }
+
+    /// Decode RGB from the `from` transfer function, and encode it with `to`
+    #[must_use]
+    pub fn with_transfer(mut self, from: TransferCharacteristics, to: TransferCharacteristics) -> Self {
+        self.transfer = Some((from, to));
+        self
+    }
+
+    /// Convert RGB primaries in linear light, without chromatic adaptation.
+    ///
+    /// Set [`Shader::with_transfer`] too, unless the RGB is already linear.
+    #[must_use]
+    pub fn with_primaries(mut self, from: ColorPrimaries, to: ColorPrimaries) -> Self {
+        self.primaries = Some((from, to));
+        self
+    }
+
+    /// Name of the function (`yuv_to_rgb` by default). Helper functions get it as a prefix.
+    #[must_use]
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Source code of the function. Fails if a transfer function isn't supported.
+    pub fn generate(&self, lang: Language) -> Result<String, Error> {
+        let mut out = String::new();
+        let name = &self.name;
+        let vec3 = vec3(lang);
+
+        if let Some((from, to)) = self.transfer {
+            function(&mut out, lang, &format!("{name}_to_linear"), &to_linear(from)?);
+            function(&mut out, lang, &format!("{name}_from_linear"), &from_linear(to)?);
+        }
+
+        let m = self.coefficients.matrix.map(|row| row.map(f64::from));
+        let offset = self.coefficients.offset.map(f64::from);
+        match lang {
+            Language::Glsl => writeln!(out, "vec3 {name}(vec3 yuv) {{\n    vec3 rgb = {} * yuv + {};", mat3(lang, &m), vector(lang, offset)),
+            Language::Wgsl => writeln!(out, "fn {name}(yuv: vec3<f32>) -> vec3<f32> {{\n    var rgb = {} * yuv + {};", mat3(lang, &m), vector(lang, offset)),
+            Language::Hlsl => writeln!(out, "float3 {name}(float3 yuv) {{\n    float3 rgb = mul({}, yuv) + {};", mat3(lang, &m), vector(lang, offset)),
+        }.unwrap();
+        if self.clamp {
+            match lang {
+                // HLSL has no single-scalar vector constructor
+                Language::Hlsl => writeln!(out, "    rgb = saturate(rgb);"),
+                Language::Glsl | Language::Wgsl => writeln!(out, "    rgb = clamp(rgb, {vec3}({}), {vec3}({}));", lit(0.), lit(1.)),
+            }.unwrap();
+        }
+        let per_channel = |out: &mut String, fun: &str| {
+            writeln!(out, "    rgb = {vec3}({name}_{fun}(rgb.r), {name}_{fun}(rgb.g), {name}_{fun}(rgb.b));").unwrap();
+        };
+        if self.transfer.is_some() {
+            per_channel(&mut out, "to_linear");
+        }
+        if let Some((from, to)) = self.primaries {
+            let m = primaries::convert(from, to);
+            match lang {
+                Language::Hlsl => writeln!(out, "    rgb = mul({}, rgb);", mat3(lang, &m)),
+                _ => writeln!(out, "    rgb = {} * rgb;", mat3(lang, &m)),
+            }.unwrap();
+        }
+        if self.transfer.is_some() {
+            per_channel(&mut out, "from_linear");
+        }
+        writeln!(out, "    return rgb;\n}}").unwrap();
+        Ok(out)
+    }
+}
+
+fn float(lang: Language) -> &'static str {
+    match lang {
+        Language::Glsl | Language::Hlsl => "float",
+        Language::Wgsl => "f32",
+    }
+}
+
+fn vec3(lang: Language) -> &'static str {
+    match lang {
+        Language::Glsl => "vec3",
+        Language::Wgsl => "vec3<f32>",
+        Language::Hlsl => "float3",
+    }
+}
+
+/// Float literal that is valid in all the languages
+fn lit(v: f64) -> String {
+    let s = format!("{:?}", v as f32);
+    if s.contains(['.', 'e']) { s } else { format!("{s}.0") }
+}
+
+fn vector(lang: Language, v: [f64; 3]) -> String {
+    format!("{}({}, {}, {})", vec3(lang), lit(v[0]), lit(v[1]), lit(v[2]))
+}
+
+/// Matrix that multiplies a column vector. GLSL and WGSL constructors take columns, HLSL takes rows.
+fn mat3(lang: Language, m: &primaries::Mat3) -> String {
+    let elements = |transpose: bool| {
+        (0..3).flat_map(|i| (0..3).map(move |j| if transpose { m[j][i] } else { m[i][j] })).map(lit).collect::<Vec<_>>().join(", ")
+    };
+    match lang {
+        Language::Glsl => format!("mat3({})", elements(true)),
+        Language::Wgsl => format!("mat3x3<f32>({})", elements(true)),
+        Language::Hlsl => format!("float3x3({})", elements(false)),
+    }
+}
+
+/// Function of one float `v`. The body uses only syntax common to all the languages.
+fn function(out: &mut String, lang: Language, name: &str, body: &str) {
+    let f = float(lang);
+    match lang {
+        Language::Glsl | Language::Hlsl => writeln!(out, "{f} {name}({f} v) {{"),
+        Language::Wgsl => writeln!(out, "fn {name}(v: {f}) -> {f} {{"),
+    }.unwrap();
+    for line in body.lines() {
+        writeln!(out, "    {line}").unwrap();
+    }
+    writeln!(out, "}}\n").unwrap();
+}
+
+fn if_less(cond: &str, then: String) -> String {
+    format!("if ({cond}) {{ return {then}; }}\n")
+}
+
+/// Signal to linear, the same as [`transfer::to_linear`](crate::transfer::to_linear)
+#[allow(deprecated)]
+fn to_linear(tc: TransferCharacteristics) -> Result<String, Error> {
+    use TransferCharacteristics as TC;
+    let ln10 = core::f64::consts::LN_10;
+    let gamma = |alpha: f64, beta: f64, slope: f64| format!("{}return pow((v + {}) / {}, {});",
+        if_less(&format!("v < {}", lit(beta * slope)), format!("max(v, {}) / {}", lit(0.), lit(slope))),
+        lit(alpha - 1.), lit(alpha), lit(1. / 0.45));
+    Ok(match tc {
+        TC::BT709 | TC::BT601 | TC::BT2020_10Bit | TC::BT2020_12Bit | TC::BT1361 => gamma(BT709_ALPHA, BT709_BETA, 4.5),
+        TC::SMPTE240 => gamma(1.1115, 0.0228, 4.),
+        TC::IEC61966 => format!("{}return sign(v) * pow((abs(v) + {}) / {}, {});",
+            if_less(&format!("abs(v) < {}", lit(BT709_BETA * 4.5)), format!("v / {}", lit(4.5))),
+            lit(BT709_ALPHA - 1.), lit(BT709_ALPHA), lit(1. / 0.45)),
+        TC::BT470M => format!("return pow(max(v, {}), {});", lit(0.), lit(2.2)),
+        TC::BT470BG => format!("return pow(max(v, {}), {});", lit(0.), lit(2.8)),
+        TC::Linear => "return v;".into(),
+        TC::Log100 => format!("{}return exp((v - {}) * {});", if_less(&format!("v <= {}", lit(0.)), lit(0.)), lit(1.), lit(2. * ln10)),
+        TC::Log100Sqrt10 => format!("{}return exp((v - {}) * {});", if_less(&format!("v <= {}", lit(0.)), lit(0.)), lit(1.), lit(2.5 * ln10)),
+        TC::SRGB => format!("{}return pow((v + {}) / {}, {});",
+            if_less(&format!("v <= {}", lit(0.04045)), format!("max(v, {}) / {}", lit(0.), lit(12.92))),
+            lit(0.055), lit(1.055), lit(2.4)),
+        TC::SMPTE2084 => {
+            let p = format!("pow(max(v, {}), {})", lit(0.), lit(1. / PQ_M2));
+            format!("return pow(max({p} - {}, {}) / ({} - {} * {p}), {});", lit(PQ_C1), lit(0.), lit(PQ_C2), lit(PQ_C3), lit(1. / PQ_M1))
+        },
+        TC::SMPTE428 => format!("return pow(max(v, {}), {}) * {};", lit(0.), lit(2.6), lit(52.37 / 48.)),
+        TC::HLG => format!("{}return (exp((v - {}) / {}) + {}) / {};",
+            if_less(&format!("v <= {}", lit(0.5)), format!("max(v, {0}) * max(v, {0}) / {1}", lit(0.), lit(3.))),
+            lit(HLG_C), lit(HLG_A), lit(HLG_B), lit(12.)),
+        #[allow(unreachable_patterns)]
+        _ => return Err(Error::UnsupportedTransferCharacteristics),
+    })
+}
+
+/// Linear to signal, the same as [`transfer::from_linear`](crate::transfer::from_linear)
+#[allow(deprecated)]
+fn from_linear(tc: TransferCharacteristics) -> Result<String, Error> {
+    use TransferCharacteristics as TC;
+    let ln10 = core::f64::consts::LN_10;
+    let gamma = |alpha: f64, beta: f64, slope: f64| format!("{}return {} * pow(v, {}) - {};",
+        if_less(&format!("v < {}", lit(beta)), format!("max(v, {}) * {}", lit(0.), lit(slope))),
+        lit(alpha), lit(0.45), lit(alpha - 1.));
+    Ok(match tc {
+        TC::BT709 | TC::BT601 | TC::BT2020_10Bit | TC::BT2020_12Bit | TC::BT1361 => gamma(BT709_ALPHA, BT709_BETA, 4.5),
+        TC::SMPTE240 => gamma(1.1115, 0.0228, 4.),
+        TC::IEC61966 => format!("{}return sign(v) * ({} * pow(abs(v), {}) - {});",
+            if_less(&format!("abs(v) < {}", lit(BT709_BETA)), format!("v * {}", lit(4.5))),
+            lit(BT709_ALPHA), lit(0.45), lit(BT709_ALPHA - 1.)),
+        TC::BT470M => format!("return pow(max(v, {}), {});", lit(0.), lit(1. / 2.2)),
+        TC::BT470BG => format!("return pow(max(v, {}), {});", lit(0.), lit(1. / 2.8)),
+        TC::Linear => "return v;".into(),
+        TC::Log100 => format!("{}return {} + log(v) * {};", if_less(&format!("v < {}", lit(0.01)), lit(0.)), lit(1.), lit(1. / (2. * ln10))),
+        TC::Log100Sqrt10 => format!("{}return {} + log(v) * {};", if_less(&format!("v < {}", lit(0.003_162_277_66)), lit(0.)), lit(1.), lit(1. / (2.5 * ln10))),
+        TC::SRGB => format!("{}return {} * pow(v, {}) - {};",
+            if_less(&format!("v <= {}", lit(0.003_130_8)), format!("max(v, {}) * {}", lit(0.), lit(12.92))),
+            lit(1.055), lit(1. / 2.4), lit(0.055)),
+        TC::SMPTE2084 => {
+            let p = format!("pow(max(v, {}), {})", lit(0.), lit(PQ_M1));
+            format!("return pow(({} + {} * {p}) / ({} + {} * {p}), {});", lit(PQ_C1), lit(PQ_C2), lit(1.), lit(PQ_C3), lit(PQ_M2))
+        },
+        TC::SMPTE428 => format!("return pow(max(v, {}) * {}, {});", lit(0.), lit(48. / 52.37), lit(1. / 2.6)),
+        TC::HLG => format!("{}return {} * log({} * v - {}) + {};",
+            if_less(&format!("v <= {}", lit(1. / 12.)), format!("sqrt({} * max(v, {}))", lit(3.), lit(0.))),
+            lit(HLG_A), lit(12.), lit(HLG_B), lit(HLG_C)),
+        #[allow(unreachable_patterns)]
+        _ => return Err(Error::UnsupportedTransferCharacteristics),
+    })
+}
+
+#[cfg(test)]
+#[allow(deprecated)]
+const ALL_TRANSFERS: [TransferCharacteristics; 13] = {
+    use TransferCharacteristics as TC;
+    [TC::BT709, TC::BT470M, TC::BT470BG, TC::SMPTE240, TC::Linear, TC::Log100, TC::Log100Sqrt10, TC::IEC61966,
+     TC::SRGB, TC::BT2020_10Bit, TC::SMPTE2084, TC::SMPTE428, TC::HLG]
+};
+
+#[test]
+fn wgsl_is_valid() {
+    use crate::color::{Depth, MatrixCoefficients, Range};
+
+    let conv = RGBConvert::<u16>::new(Range::Limited, MatrixCoefficients::BT2020NCL, Depth::Depth10).unwrap();
+    for tc in ALL_TRANSFERS {
+        let src = Shader::new(&conv).with_input_max(65535.).with_transfer(tc, TransferCharacteristics::SRGB)
+            .with_primaries(ColorPrimaries::BT2020, ColorPrimaries::BT709).generate(Language::Wgsl).unwrap();
+        let src = src + &Shader::new(&conv).with_name("back").with_transfer(TransferCharacteristics::Linear, tc).generate(Language::Wgsl).unwrap();
+        let module = naga::front::wgsl::parse_str(&src).unwrap_or_else(|e| panic!("{tc:?} {}\n{src}", e.emit_to_string(&src)));
+        naga::valid::Validator::new(naga::valid::ValidationFlags::all(), naga::valid::Capabilities::empty())
+            .validate(&module).unwrap_or_else(|e| panic!("{tc:?} {e:?}\n{src}"));
+    }
+
+    // converters that aren't a matrix
+    for conv in [RGBConvert::<u8>::new(Range::Full, MatrixCoefficients::Identity).unwrap(), RGBConvert::<u8>::new(Range::Limited, MatrixCoefficients::Identity).unwrap()] {
+        let src = Shader::new(&conv).generate(Language::Wgsl).unwrap();
+        naga::front::wgsl::parse_str(&src).unwrap();
+    }
+}
+
+#[test]
+fn glsl_is_valid() {
+    use crate::color::{MatrixCoefficients, Range};
+
+    let conv = RGBConvert::<u8>::new(Range::Full, MatrixCoefficients::BT601).unwrap();
+    for tc in ALL_TRANSFERS {
+        let src = Shader::new(&conv).with_input_max(255.).with_transfer(TransferCharacteristics::SRGB, tc)
+            .with_primaries(ColorPrimaries::BT601, ColorPrimaries::BT2020).generate(Language::Glsl).unwrap();
+        let src = format!("#version 450\n{src}\nlayout(location = 0) out vec4 color;\nvoid main() {{ color = vec4(yuv_to_rgb(vec3(0.5, 0.25, 0.75)), 1.0); }}\n");
+        let mut frontend = naga::front::glsl::Frontend::default();
+        let module = frontend.parse(&naga::front::glsl::Options::from(naga::ShaderStage::Fragment), &src)
+            .unwrap_or_else(|e| panic!("{tc:?} {}\n{src}", e.emit_to_string(&src)));
+        naga::valid::Validator::new(naga::valid::ValidationFlags::all(), naga::valid::Capabilities::empty())
+            .validate(&module).unwrap_or_else(|e| panic!("{tc:?} {e:?}\n{src}"));
+    }
+}
+
+#[test]
+fn hlsl_matches_glsl() {
+    use crate::color::{MatrixCoefficients, Range};
+
+    let conv = RGBConvert::<u8>::new(Range::Limited, MatrixCoefficients::BT709).unwrap();
+    let shader = Shader::new(&conv).with_transfer(TransferCharacteristics::BT709, TransferCharacteristics::Linear);
+    let hlsl = shader.generate(Language::Hlsl).unwrap();
+    let glsl = shader.generate(Language::Glsl).unwrap();
+    assert!(hlsl.contains("float3 yuv_to_rgb(float3 yuv) {") && hlsl.contains("mul(float3x3("), "{hlsl}");
+    assert!(hlsl.contains("\n    rgb = saturate(rgb);\n") && !hlsl.contains("float3(0.0)"), "{hlsl}");
+    assert!(glsl.contains("\n    rgb = clamp(rgb, vec3(0.0), vec3(1.0));\n"), "{glsl}");
+    // same functions apart from the vector types
+    assert_eq!(glsl.replace("vec3", "float3").lines().count(), hlsl.lines().count());
+
+    // coefficients are the same as the CPU conversion uses
+    let RGBConvert::Matrix(m) = &conv else { panic!() };
+    let c = m.coefficients();
+    assert!(hlsl.contains(&format!("float3x3({}, {}, {},", lit(c.matrix[0][0].into()), lit(c.matrix[0][1].into()), lit(c.matrix[0][2].into()))), "{hlsl}");
+
+    let lut = Lut::<u8>::from_matrix_with_transfer(m, TransferCharacteristics::BT709, TransferCharacteristics::Linear);
+    assert_eq!(glsl, Shader::from_lut(&lut).generate(Language::Glsl).unwrap());
+}
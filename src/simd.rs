@@ -0,0 +1,1008 @@
+//! Explicitly vectorized row kernels for `Matrix`, `IdentityScale` and `CopyGBR`.
+//!
+//! The kernels do the same float operations in the same order as the scalar code (and never fuse multiply-add),
+//! so their results are identical. They convert as many whole vectors as fit in a row, and return the number of pixels done.
+//! The caller converts the remainder with the scalar code.
+
+use rgb::Rgb;
+
+/// `Matrix::to_rgbf` and output scaling with the coefficients multiplied out
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct MatrixKernel {
+    pub y_mul: f32, pub y_sub: f32,
+    pub rv_mul: f32, pub rv_sub: f32,
+    pub bu_mul: f32, pub bu_sub: f32,
+    pub gu_mul: f32, pub gu_sub: f32,
+    pub gv_mul: f32, pub gv_sub: f32,
+    /// Clamp negative values before scaling (`Clamp::PerChannel`)
+    pub clamp: bool,
+    pub scale: f32,
+    pub offset: f32,
+    pub max: f32,
+}
+
+/// `(max(v - min, 0) * mul + bias) / range`, truncated and clamped to `max`.
+///
+/// It's computed in `f64`, where it's exact for all 16-bit values.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct IdentityKernel {
+    pub min: f64,
+    pub range: f64,
+    pub mul: f64,
+    pub bias: f64,
+    pub max: f64,
+}
+
+/// Vector of `f32`
+pub(crate) trait F32s: Copy {
+    const N: usize;
+    unsafe fn splat(x: f32) -> Self;
+    /// Reads `N` values
+    unsafe fn load_u8(src: *const u8) -> Self;
+    unsafe fn load_u16(src: *const u16) -> Self;
+    unsafe fn add(self, other: Self) -> Self;
+    unsafe fn sub(self, other: Self) -> Self;
+    unsafe fn mul(self, other: Self) -> Self;
+    unsafe fn min(self, other: Self) -> Self;
+    unsafe fn max(self, other: Self) -> Self;
+    /// Writes `N` values
+    unsafe fn store_trunc(self, out: &mut [i32; 8]);
+}
+
+/// Vector of `f64`
+pub(crate) trait F64s: Copy {
+    const N: usize;
+    unsafe fn splat(x: f64) -> Self;
+    unsafe fn load_u8(src: *const u8) -> Self;
+    unsafe fn load_u16(src: *const u16) -> Self;
+    unsafe fn add(self, other: Self) -> Self;
+    unsafe fn sub(self, other: Self) -> Self;
+    unsafe fn mul(self, other: Self) -> Self;
+    unsafe fn div(self, other: Self) -> Self;
+    unsafe fn min(self, other: Self) -> Self;
+    unsafe fn max(self, other: Self) -> Self;
+    unsafe fn store_trunc(self, out: &mut [i32; 8]);
+}
+
+/// Input samples the kernels can read
+pub(crate) trait Load: Copy {
+    unsafe fn load_f32<V: F32s>(src: *const Self) -> V;
+    unsafe fn load_f64<V: F64s>(src: *const Self) -> V;
+}
+
+impl Load for u8 {
+    #[inline(always)]
+    unsafe fn load_f32<V: F32s>(src: *const Self) -> V {
+        unsafe { V::load_u8(src) }
+    }
+
+    #[inline(always)]
+    unsafe fn load_f64<V: F64s>(src: *const Self) -> V {
+        unsafe { V::load_u8(src) }
+    }
+}
+
+impl Load for u16 {
+    #[inline(always)]
+    unsafe fn load_f32<V: F32s>(src: *const Self) -> V {
+        unsafe { V::load_u16(src) }
+    }
+
+    #[inline(always)]
+    unsafe fn load_f64<V: F64s>(src: *const Self) -> V {
+        unsafe { V::load_u16(src) }
+    }
+}
+
+/// Output samples the kernels can write. Values are already clamped to the type's range.
+pub(crate) trait Store: Copy {
+    fn from_i32(v: i32) -> Self;
+}
+
+impl Store for u8 {
+    #[inline(always)]
+    fn from_i32(v: i32) -> Self {
+        v as u8
+    }
+}
+
+impl Store for u16 {
+    #[inline(always)]
+    fn from_i32(v: i32) -> Self {
+        v as u16
+    }
+}
+
+#[inline(always)]
+unsafe fn matrix<V: F32s, I: Load, O: Store>(k: &MatrixKernel, y: &[I], u: &[I], v: &[I], out: &mut [Rgb<O>]) -> usize {
+    let len = out.len().min(y.len()).min(u.len()).min(v.len());
+    let done = len - len % V::N;
+    unsafe {
+        let zero = V::splat(0.);
+        let (y_mul, y_sub) = (V::splat(k.y_mul), V::splat(k.y_sub));
+        let (rv_mul, rv_sub) = (V::splat(k.rv_mul), V::splat(k.rv_sub));
+        let (bu_mul, bu_sub) = (V::splat(k.bu_mul), V::splat(k.bu_sub));
+        let (gu_mul, gu_sub) = (V::splat(k.gu_mul), V::splat(k.gu_sub));
+        let (gv_mul, gv_sub) = (V::splat(k.gv_mul), V::splat(k.gv_sub));
+        let (scale, offset, max) = (V::splat(k.scale), V::splat(k.offset), V::splat(k.max));
+        let (mut r, mut g, mut b) = ([0; 8], [0; 8], [0; 8]);
+
+        for i in (0..done).step_by(V::N) {
+            let py = I::load_f32::<V>(y.as_ptr().add(i));
+            let pu = I::load_f32::<V>(u.as_ptr().add(i));
+            let pv = I::load_f32::<V>(v.as_ptr().add(i));
+
+            let luma = py.mul(y_mul).sub(y_sub);
+            let mut rgb = [
+                luma.add(pv.mul(rv_mul)).sub(rv_sub),
+                luma.sub(pu.mul(gu_mul).sub(gu_sub)).sub(pv.mul(gv_mul).sub(gv_sub)),
+                luma.add(pu.mul(bu_mul)).sub(bu_sub),
+            ];
+            for c in &mut rgb {
+                if k.clamp {
+                    *c = c.max(zero);
+                }
+                *c = c.mul(scale).add(offset).min(max).max(zero);
+            }
+            rgb[0].store_trunc(&mut r);
+            rgb[1].store_trunc(&mut g);
+            rgb[2].store_trunc(&mut b);
+
+            for (j, out) in out[i..i + V::N].iter_mut().enumerate() {
+                *out = Rgb { r: O::from_i32(r[j]), g: O::from_i32(g[j]), b: O::from_i32(b[j]) };
+            }
+        }
+    }
+    done
+}
+
+#[inline(always)]
+unsafe fn identity<V: F64s, I: Load, O: Store>(k: &IdentityKernel, y: &[I], u: &[I], v: &[I], out: &mut [Rgb<O>]) -> usize {
+    let len = out.len().min(y.len()).min(u.len()).min(v.len());
+    let done = len - len % V::N;
+    unsafe {
+        let zero = V::splat(0.);
+        let (min, range, mul, bias, max) = (V::splat(k.min), V::splat(k.range), V::splat(k.mul), V::splat(k.bias), V::splat(k.max));
+        let rescale = |c: V| c.sub(min).max(zero).mul(mul).add(bias).div(range).min(max);
+        let (mut r, mut g, mut b) = ([0; 8], [0; 8], [0; 8]);
+
+        for i in (0..done).step_by(V::N) {
+            rescale(I::load_f64::<V>(v.as_ptr().add(i))).store_trunc(&mut r);
+            rescale(I::load_f64::<V>(y.as_ptr().add(i))).store_trunc(&mut g);
+            rescale(I::load_f64::<V>(u.as_ptr().add(i))).store_trunc(&mut b);
+
+            for (j, out) in out[i..i + V::N].iter_mut().enumerate() {
+                *out = Rgb { r: O::from_i32(r[j]), g: O::from_i32(g[j]), b: O::from_i32(b[j]) };
+            }
+        }
+    }
+    done
+}
+
+/// Byte shuffles interleaving 16 bytes of V, Y and U planes into 48 bytes of RGB.
+///
+/// Indexed by `[output vector][channel]`. Unused lanes are `0x80`, which both `pshufb` and `i8x16.swizzle` turn into 0.
+#[cfg(any(target_arch = "x86", target_arch = "x86_64", all(target_arch = "wasm32", target_feature = "simd128")))]
+const fn interleave_masks(sample_size: usize) -> [[[u8; 16]; 3]; 3] {
+    let mut masks = [[[0x80; 16]; 3]; 3];
+    let mut pos = 0;
+    while pos < 48 {
+        let sample = pos / sample_size;
+        let pixel = sample / 3;
+        masks[pos / 16][sample % 3][pos % 16] = (pixel * sample_size + pos % sample_size) as u8;
+        pos += 1;
+    }
+    masks
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64", all(target_arch = "wasm32", target_feature = "simd128")))]
+const INTERLEAVE_U8: [[[u8; 16]; 3]; 3] = interleave_masks(1);
+#[cfg(any(target_arch = "x86", target_arch = "x86_64", all(target_arch = "wasm32", target_feature = "simd128")))]
+const INTERLEAVE_U16: [[[u8; 16]; 3]; 3] = interleave_masks(2);
+
+/// Best instruction set available, in order of preference
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Level {
+    Scalar,
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    Sse41,
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    Avx2,
+    #[cfg(target_arch = "aarch64")]
+    Neon,
+    #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+    Simd128,
+}
+
+#[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), not(any(not(feature = "std"), feature = "no_std"))))]
+macro_rules! has_feature {
+    ($f:tt) => { std::is_x86_feature_detected!($f) };
+}
+
+/// Without `std` it's known only at compile time
+#[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), any(not(feature = "std"), feature = "no_std")))]
+macro_rules! has_feature {
+    ($f:tt) => { cfg!(target_feature = $f) };
+}
+
+#[inline]
+#[allow(unreachable_code)]
+fn detect() -> Level {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if has_feature!("avx2") {
+            return Level::Avx2;
+        }
+        if has_feature!("sse4.1") {
+            return Level::Sse41;
+        }
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        return Level::Neon;
+    }
+    #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+    {
+        return Level::Simd128;
+    }
+    Level::Scalar
+}
+
+/// Converts a prefix of the row with `Matrix` coefficients
+#[inline]
+pub(crate) fn matrix_row<I: Load, O: Store>(k: &MatrixKernel, y: &[I], u: &[I], v: &[I], out: &mut [Rgb<O>]) -> usize {
+    matrix_row_with(detect(), k, y, u, v, out)
+}
+
+#[inline]
+fn matrix_row_with<I: Load, O: Store>(level: Level, k: &MatrixKernel, y: &[I], u: &[I], v: &[I], out: &mut [Rgb<O>]) -> usize {
+    // Safety: the level has been checked to be supported by the CPU
+    unsafe {
+        match level {
+            Level::Scalar => 0,
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Level::Sse41 => x86::matrix_sse41(k, y, u, v, out),
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Level::Avx2 => x86::matrix_avx2(k, y, u, v, out),
+            #[cfg(target_arch = "aarch64")]
+            Level::Neon => matrix::<neon::F32x4, I, O>(k, y, u, v, out),
+            #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+            Level::Simd128 => matrix::<wasm::F32x4, I, O>(k, y, u, v, out),
+        }
+    }
+}
+
+/// Converts a prefix of the row with `IdentityScale`
+#[inline]
+pub(crate) fn identity_row<I: Load, O: Store>(k: &IdentityKernel, y: &[I], u: &[I], v: &[I], out: &mut [Rgb<O>]) -> usize {
+    identity_row_with(detect(), k, y, u, v, out)
+}
+
+#[inline]
+fn identity_row_with<I: Load, O: Store>(level: Level, k: &IdentityKernel, y: &[I], u: &[I], v: &[I], out: &mut [Rgb<O>]) -> usize {
+    // Safety: the level has been checked to be supported by the CPU
+    unsafe {
+        match level {
+            Level::Scalar => 0,
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Level::Sse41 => x86::identity_sse41(k, y, u, v, out),
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Level::Avx2 => x86::identity_avx2(k, y, u, v, out),
+            #[cfg(target_arch = "aarch64")]
+            Level::Neon => identity::<neon::F64x2, I, O>(k, y, u, v, out),
+            #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+            Level::Simd128 => identity::<wasm::F64x2, I, O>(k, y, u, v, out),
+        }
+    }
+}
+
+/// Copies a prefix of the row of `u8` planes to RGB
+#[inline]
+pub(crate) fn copy_gbr_u8(y: &[u8], u: &[u8], v: &[u8], out: &mut [Rgb<u8>]) -> usize {
+    copy_gbr_u8_with(detect(), y, u, v, out)
+}
+
+#[inline]
+#[allow(unused_variables)]
+fn copy_gbr_u8_with(level: Level, y: &[u8], u: &[u8], v: &[u8], out: &mut [Rgb<u8>]) -> usize {
+    let len = out.len().min(y.len()).min(u.len()).min(v.len());
+    let done = len - len % 16;
+    // Safety: the level has been checked to be supported by the CPU, and all slices have at least `done` elements
+    unsafe {
+        match level {
+            Level::Scalar => return 0,
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Level::Sse41 | Level::Avx2 => x86::interleave_ssse3(&INTERLEAVE_U8, y.as_ptr().cast(), u.as_ptr().cast(), v.as_ptr().cast(), out.as_mut_ptr().cast(), done / 16),
+            #[cfg(target_arch = "aarch64")]
+            Level::Neon => neon::interleave_u8(y.as_ptr(), u.as_ptr(), v.as_ptr(), out.as_mut_ptr().cast(), done / 16),
+            #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+            Level::Simd128 => wasm::interleave(&INTERLEAVE_U8, y.as_ptr().cast(), u.as_ptr().cast(), v.as_ptr().cast(), out.as_mut_ptr().cast(), done / 16),
+        }
+    }
+    done
+}
+
+/// Copies a prefix of the row of `u16` planes to RGB
+#[inline]
+pub(crate) fn copy_gbr_u16(y: &[u16], u: &[u16], v: &[u16], out: &mut [Rgb<u16>]) -> usize {
+    copy_gbr_u16_with(detect(), y, u, v, out)
+}
+
+#[inline]
+#[allow(unused_variables)]
+fn copy_gbr_u16_with(level: Level, y: &[u16], u: &[u16], v: &[u16], out: &mut [Rgb<u16>]) -> usize {
+    let len = out.len().min(y.len()).min(u.len()).min(v.len());
+    let done = len - len % 8;
+    // Safety: the level has been checked to be supported by the CPU, and all slices have at least `done` elements
+    unsafe {
+        match level {
+            Level::Scalar => return 0,
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Level::Sse41 | Level::Avx2 => x86::interleave_ssse3(&INTERLEAVE_U16, y.as_ptr().cast(), u.as_ptr().cast(), v.as_ptr().cast(), out.as_mut_ptr().cast(), done / 8),
+            #[cfg(target_arch = "aarch64")]
+            Level::Neon => neon::interleave_u16(y.as_ptr(), u.as_ptr(), v.as_ptr(), out.as_mut_ptr().cast(), done / 8),
+            #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+            Level::Simd128 => wasm::interleave(&INTERLEAVE_U16, y.as_ptr().cast(), u.as_ptr().cast(), v.as_ptr().cast(), out.as_mut_ptr().cast(), done / 8),
+        }
+    }
+    done
+}
+
+// Intrinsics that need only the baseline target features are safe to call since Rust 1.87,
+// but the `unsafe` blocks are still needed for older compilers.
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[allow(unused_unsafe)]
+mod x86 {
+    use super::*;
+    #[cfg(target_arch = "x86")]
+    use core::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use core::arch::x86_64::*;
+
+    #[derive(Copy, Clone)]
+    pub struct F32x4(__m128);
+
+    impl F32s for F32x4 {
+        const N: usize = 4;
+
+        #[inline(always)]
+        unsafe fn splat(x: f32) -> Self {
+            unsafe { Self(_mm_set1_ps(x)) }
+        }
+
+        #[inline(always)]
+        unsafe fn load_u8(src: *const u8) -> Self {
+            unsafe { Self(_mm_cvtepi32_ps(_mm_cvtepu8_epi32(_mm_cvtsi32_si128(src.cast::<i32>().read_unaligned())))) }
+        }
+
+        #[inline(always)]
+        unsafe fn load_u16(src: *const u16) -> Self {
+            unsafe { Self(_mm_cvtepi32_ps(_mm_cvtepu16_epi32(_mm_loadl_epi64(src.cast())))) }
+        }
+
+        #[inline(always)]
+        unsafe fn add(self, other: Self) -> Self {
+            unsafe { Self(_mm_add_ps(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn sub(self, other: Self) -> Self {
+            unsafe { Self(_mm_sub_ps(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn mul(self, other: Self) -> Self {
+            unsafe { Self(_mm_mul_ps(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn min(self, other: Self) -> Self {
+            unsafe { Self(_mm_min_ps(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn max(self, other: Self) -> Self {
+            unsafe { Self(_mm_max_ps(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn store_trunc(self, out: &mut [i32; 8]) {
+            unsafe { _mm_storeu_si128(out.as_mut_ptr().cast(), _mm_cvttps_epi32(self.0)) }
+        }
+    }
+
+    #[derive(Copy, Clone)]
+    pub struct F32x8(__m256);
+
+    impl F32s for F32x8 {
+        const N: usize = 8;
+
+        #[inline(always)]
+        unsafe fn splat(x: f32) -> Self {
+            unsafe { Self(_mm256_set1_ps(x)) }
+        }
+
+        #[inline(always)]
+        unsafe fn load_u8(src: *const u8) -> Self {
+            unsafe { Self(_mm256_cvtepi32_ps(_mm256_cvtepu8_epi32(_mm_loadl_epi64(src.cast())))) }
+        }
+
+        #[inline(always)]
+        unsafe fn load_u16(src: *const u16) -> Self {
+            unsafe { Self(_mm256_cvtepi32_ps(_mm256_cvtepu16_epi32(_mm_loadu_si128(src.cast())))) }
+        }
+
+        #[inline(always)]
+        unsafe fn add(self, other: Self) -> Self {
+            unsafe { Self(_mm256_add_ps(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn sub(self, other: Self) -> Self {
+            unsafe { Self(_mm256_sub_ps(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn mul(self, other: Self) -> Self {
+            unsafe { Self(_mm256_mul_ps(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn min(self, other: Self) -> Self {
+            unsafe { Self(_mm256_min_ps(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn max(self, other: Self) -> Self {
+            unsafe { Self(_mm256_max_ps(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn store_trunc(self, out: &mut [i32; 8]) {
+            unsafe { _mm256_storeu_si256(out.as_mut_ptr().cast(), _mm256_cvttps_epi32(self.0)) }
+        }
+    }
+
+    #[derive(Copy, Clone)]
+    pub struct F64x2(__m128d);
+
+    impl F64s for F64x2 {
+        const N: usize = 2;
+
+        #[inline(always)]
+        unsafe fn splat(x: f64) -> Self {
+            unsafe { Self(_mm_set1_pd(x)) }
+        }
+
+        #[inline(always)]
+        unsafe fn load_u8(src: *const u8) -> Self {
+            unsafe { Self(_mm_set_pd(f64::from(*src.add(1)), f64::from(*src))) }
+        }
+
+        #[inline(always)]
+        unsafe fn load_u16(src: *const u16) -> Self {
+            unsafe { Self(_mm_set_pd(f64::from(src.add(1).read_unaligned()), f64::from(src.read_unaligned()))) }
+        }
+
+        #[inline(always)]
+        unsafe fn add(self, other: Self) -> Self {
+            unsafe { Self(_mm_add_pd(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn sub(self, other: Self) -> Self {
+            unsafe { Self(_mm_sub_pd(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn mul(self, other: Self) -> Self {
+            unsafe { Self(_mm_mul_pd(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn div(self, other: Self) -> Self {
+            unsafe { Self(_mm_div_pd(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn min(self, other: Self) -> Self {
+            unsafe { Self(_mm_min_pd(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn max(self, other: Self) -> Self {
+            unsafe { Self(_mm_max_pd(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn store_trunc(self, out: &mut [i32; 8]) {
+            unsafe { _mm_storel_epi64(out.as_mut_ptr().cast(), _mm_cvttpd_epi32(self.0)) }
+        }
+    }
+
+    #[derive(Copy, Clone)]
+    pub struct F64x4(__m256d);
+
+    impl F64s for F64x4 {
+        const N: usize = 4;
+
+        #[inline(always)]
+        unsafe fn splat(x: f64) -> Self {
+            unsafe { Self(_mm256_set1_pd(x)) }
+        }
+
+        #[inline(always)]
+        unsafe fn load_u8(src: *const u8) -> Self {
+            unsafe { Self(_mm256_cvtepi32_pd(_mm_cvtepu8_epi32(_mm_cvtsi32_si128(src.cast::<i32>().read_unaligned())))) }
+        }
+
+        #[inline(always)]
+        unsafe fn load_u16(src: *const u16) -> Self {
+            unsafe { Self(_mm256_cvtepi32_pd(_mm_cvtepu16_epi32(_mm_loadl_epi64(src.cast())))) }
+        }
+
+        #[inline(always)]
+        unsafe fn add(self, other: Self) -> Self {
+            unsafe { Self(_mm256_add_pd(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn sub(self, other: Self) -> Self {
+            unsafe { Self(_mm256_sub_pd(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn mul(self, other: Self) -> Self {
+            unsafe { Self(_mm256_mul_pd(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn div(self, other: Self) -> Self {
+            unsafe { Self(_mm256_div_pd(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn min(self, other: Self) -> Self {
+            unsafe { Self(_mm256_min_pd(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn max(self, other: Self) -> Self {
+            unsafe { Self(_mm256_max_pd(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn store_trunc(self, out: &mut [i32; 8]) {
+            unsafe { _mm_storeu_si128(out.as_mut_ptr().cast(), _mm256_cvttpd_epi32(self.0)) }
+        }
+    }
+
+    #[target_feature(enable = "sse4.1")]
+    pub unsafe fn matrix_sse41<I: Load, O: Store>(k: &MatrixKernel, y: &[I], u: &[I], v: &[I], out: &mut [Rgb<O>]) -> usize {
+        unsafe { matrix::<F32x4, I, O>(k, y, u, v, out) }
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn matrix_avx2<I: Load, O: Store>(k: &MatrixKernel, y: &[I], u: &[I], v: &[I], out: &mut [Rgb<O>]) -> usize {
+        unsafe { matrix::<F32x8, I, O>(k, y, u, v, out) }
+    }
+
+    #[target_feature(enable = "sse4.1")]
+    pub unsafe fn identity_sse41<I: Load, O: Store>(k: &IdentityKernel, y: &[I], u: &[I], v: &[I], out: &mut [Rgb<O>]) -> usize {
+        unsafe { identity::<F64x2, I, O>(k, y, u, v, out) }
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn identity_avx2<I: Load, O: Store>(k: &IdentityKernel, y: &[I], u: &[I], v: &[I], out: &mut [Rgb<O>]) -> usize {
+        unsafe { identity::<F64x4, I, O>(k, y, u, v, out) }
+    }
+
+    /// Reads `count` vectors from each plane, and writes `count * 3` vectors
+    #[target_feature(enable = "ssse3")]
+    pub unsafe fn interleave_ssse3(masks: &[[[u8; 16]; 3]; 3], y: *const __m128i, u: *const __m128i, v: *const __m128i, out: *mut __m128i, count: usize) {
+        unsafe {
+            let masks = masks.map(|m| m.map(|m| _mm_loadu_si128(m.as_ptr().cast())));
+            for i in 0..count {
+                let (y, u, v) = (_mm_loadu_si128(y.add(i)), _mm_loadu_si128(u.add(i)), _mm_loadu_si128(v.add(i)));
+                for (j, [mr, mg, mb]) in masks.iter().enumerate() {
+                    let rgb = _mm_or_si128(_mm_or_si128(_mm_shuffle_epi8(v, *mr), _mm_shuffle_epi8(y, *mg)), _mm_shuffle_epi8(u, *mb));
+                    _mm_storeu_si128(out.add(i * 3 + j), rgb);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+#[allow(unused_unsafe)]
+mod neon {
+    use super::*;
+    use core::arch::aarch64::*;
+
+    #[derive(Copy, Clone)]
+    pub struct F32x4(float32x4_t);
+
+    impl F32s for F32x4 {
+        const N: usize = 4;
+
+        #[inline(always)]
+        unsafe fn splat(x: f32) -> Self {
+            unsafe { Self(vdupq_n_f32(x)) }
+        }
+
+        #[inline(always)]
+        unsafe fn load_u8(src: *const u8) -> Self {
+            unsafe {
+                let px = [u32::from(*src), u32::from(*src.add(1)), u32::from(*src.add(2)), u32::from(*src.add(3))];
+                Self(vcvtq_f32_u32(vld1q_u32(px.as_ptr())))
+            }
+        }
+
+        #[inline(always)]
+        unsafe fn load_u16(src: *const u16) -> Self {
+            unsafe { Self(vcvtq_f32_u32(vmovl_u16(vld1_u16(src)))) }
+        }
+
+        #[inline(always)]
+        unsafe fn add(self, other: Self) -> Self {
+            unsafe { Self(vaddq_f32(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn sub(self, other: Self) -> Self {
+            unsafe { Self(vsubq_f32(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn mul(self, other: Self) -> Self {
+            unsafe { Self(vmulq_f32(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn min(self, other: Self) -> Self {
+            unsafe { Self(vminq_f32(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn max(self, other: Self) -> Self {
+            unsafe { Self(vmaxq_f32(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn store_trunc(self, out: &mut [i32; 8]) {
+            unsafe { vst1q_s32(out.as_mut_ptr(), vcvtq_s32_f32(self.0)) }
+        }
+    }
+
+    #[derive(Copy, Clone)]
+    pub struct F64x2(float64x2_t);
+
+    impl F64s for F64x2 {
+        const N: usize = 2;
+
+        #[inline(always)]
+        unsafe fn splat(x: f64) -> Self {
+            unsafe { Self(vdupq_n_f64(x)) }
+        }
+
+        #[inline(always)]
+        unsafe fn load_u8(src: *const u8) -> Self {
+            unsafe {
+                let px = [u64::from(*src), u64::from(*src.add(1))];
+                Self(vcvtq_f64_u64(vld1q_u64(px.as_ptr())))
+            }
+        }
+
+        #[inline(always)]
+        unsafe fn load_u16(src: *const u16) -> Self {
+            unsafe {
+                let px = [u64::from(*src), u64::from(*src.add(1))];
+                Self(vcvtq_f64_u64(vld1q_u64(px.as_ptr())))
+            }
+        }
+
+        #[inline(always)]
+        unsafe fn add(self, other: Self) -> Self {
+            unsafe { Self(vaddq_f64(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn sub(self, other: Self) -> Self {
+            unsafe { Self(vsubq_f64(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn mul(self, other: Self) -> Self {
+            unsafe { Self(vmulq_f64(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn div(self, other: Self) -> Self {
+            unsafe { Self(vdivq_f64(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn min(self, other: Self) -> Self {
+            unsafe { Self(vminq_f64(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn max(self, other: Self) -> Self {
+            unsafe { Self(vmaxq_f64(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn store_trunc(self, out: &mut [i32; 8]) {
+            unsafe {
+                let mut tmp = [0_i64; 2];
+                vst1q_s64(tmp.as_mut_ptr(), vcvtq_s64_f64(self.0));
+                out[0] = tmp[0] as i32;
+                out[1] = tmp[1] as i32;
+            }
+        }
+    }
+
+    /// Reads `count` vectors of 16 from each plane
+    #[inline]
+    pub unsafe fn interleave_u8(y: *const u8, u: *const u8, v: *const u8, out: *mut u8, count: usize) {
+        unsafe {
+            for i in 0..count {
+                let rgb = uint8x16x3_t(vld1q_u8(v.add(i * 16)), vld1q_u8(y.add(i * 16)), vld1q_u8(u.add(i * 16)));
+                vst3q_u8(out.add(i * 48), rgb);
+            }
+        }
+    }
+
+    /// Reads `count` vectors of 8 from each plane
+    #[inline]
+    pub unsafe fn interleave_u16(y: *const u16, u: *const u16, v: *const u16, out: *mut u16, count: usize) {
+        unsafe {
+            for i in 0..count {
+                let rgb = uint16x8x3_t(vld1q_u16(v.add(i * 8)), vld1q_u16(y.add(i * 8)), vld1q_u16(u.add(i * 8)));
+                vst3q_u16(out.add(i * 24), rgb);
+            }
+        }
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+#[allow(unused_unsafe)]
+mod wasm {
+    use super::*;
+    use core::arch::wasm32::*;
+
+    #[derive(Copy, Clone)]
+    pub struct F32x4(v128);
+
+    impl F32s for F32x4 {
+        const N: usize = 4;
+
+        #[inline(always)]
+        unsafe fn splat(x: f32) -> Self {
+            unsafe { Self(f32x4_splat(x)) }
+        }
+
+        #[inline(always)]
+        unsafe fn load_u8(src: *const u8) -> Self {
+            unsafe { Self(f32x4_convert_u32x4(u32x4(u32::from(*src), u32::from(*src.add(1)), u32::from(*src.add(2)), u32::from(*src.add(3))))) }
+        }
+
+        #[inline(always)]
+        unsafe fn load_u16(src: *const u16) -> Self {
+            unsafe { Self(f32x4_convert_u32x4(u32x4(u32::from(*src), u32::from(*src.add(1)), u32::from(*src.add(2)), u32::from(*src.add(3))))) }
+        }
+
+        #[inline(always)]
+        unsafe fn add(self, other: Self) -> Self {
+            unsafe { Self(f32x4_add(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn sub(self, other: Self) -> Self {
+            unsafe { Self(f32x4_sub(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn mul(self, other: Self) -> Self {
+            unsafe { Self(f32x4_mul(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn min(self, other: Self) -> Self {
+            unsafe { Self(f32x4_pmin(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn max(self, other: Self) -> Self {
+            unsafe { Self(f32x4_pmax(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn store_trunc(self, out: &mut [i32; 8]) {
+            unsafe { v128_store(out.as_mut_ptr().cast(), i32x4_trunc_sat_f32x4(self.0)) }
+        }
+    }
+
+    #[derive(Copy, Clone)]
+    pub struct F64x2(v128);
+
+    impl F64s for F64x2 {
+        const N: usize = 2;
+
+        #[inline(always)]
+        unsafe fn splat(x: f64) -> Self {
+            unsafe { Self(f64x2_splat(x)) }
+        }
+
+        #[inline(always)]
+        unsafe fn load_u8(src: *const u8) -> Self {
+            unsafe { Self(f64x2_convert_low_u32x4(u32x4(u32::from(*src), u32::from(*src.add(1)), 0, 0))) }
+        }
+
+        #[inline(always)]
+        unsafe fn load_u16(src: *const u16) -> Self {
+            unsafe { Self(f64x2_convert_low_u32x4(u32x4(u32::from(*src), u32::from(*src.add(1)), 0, 0))) }
+        }
+
+        #[inline(always)]
+        unsafe fn add(self, other: Self) -> Self {
+            unsafe { Self(f64x2_add(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn sub(self, other: Self) -> Self {
+            unsafe { Self(f64x2_sub(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn mul(self, other: Self) -> Self {
+            unsafe { Self(f64x2_mul(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn div(self, other: Self) -> Self {
+            unsafe { Self(f64x2_div(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn min(self, other: Self) -> Self {
+            unsafe { Self(f64x2_pmin(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn max(self, other: Self) -> Self {
+            unsafe { Self(f64x2_pmax(self.0, other.0)) }
+        }
+
+        #[inline(always)]
+        unsafe fn store_trunc(self, out: &mut [i32; 8]) {
+            // the upper two lanes are zeroed
+            unsafe { v128_store(out.as_mut_ptr().cast(), i32x4_trunc_sat_f64x2_zero(self.0)) }
+        }
+    }
+
+    /// Reads `count` vectors from each plane, and writes `count * 3` vectors
+    #[inline]
+    pub unsafe fn interleave(masks: &[[[u8; 16]; 3]; 3], y: *const v128, u: *const v128, v: *const v128, out: *mut v128, count: usize) {
+        unsafe {
+            let masks = masks.map(|m| m.map(|m| v128_load(m.as_ptr().cast())));
+            for i in 0..count {
+                let (y, u, v) = (v128_load(y.add(i)), v128_load(u.add(i)), v128_load(v.add(i)));
+                for (j, [mr, mg, mb]) in masks.iter().enumerate() {
+                    let rgb = v128_or(v128_or(u8x16_swizzle(v, *mr), u8x16_swizzle(y, *mg)), u8x16_swizzle(u, *mb));
+                    v128_store(out.add(i * 3 + j), rgb);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+fn levels() -> impl Iterator<Item = Level> {
+    let best = detect();
+    [
+        Level::Scalar,
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        Level::Sse41,
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        Level::Avx2,
+        #[cfg(target_arch = "aarch64")]
+        Level::Neon,
+        #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+        Level::Simd128,
+    ]
+    .into_iter()
+    .filter(move |&l| l <= best)
+}
+
+#[cfg(test)]
+fn test_planes<T: Copy>(samples: impl Fn(usize) -> T) -> [[T; 37]; 3] {
+    // the odd length leaves a remainder for the scalar code
+    [0, 1, 2].map(|plane| core::array::from_fn(|i| samples(i * 3 + plane * 1001)))
+}
+
+#[test]
+fn matrix_kernels_match_scalar() {
+    use crate::color::{Depth, MatrixCoefficients, Range};
+    use crate::convert::{Clamp, Policy, RGBConvert, Rounding, ToRGB};
+    use crate::YUV;
+
+    let policies = [
+        Policy::default(),
+        Policy { rounding: Rounding::Nearest, clamp: Clamp::None },
+        Policy { rounding: Rounding::Nearest, clamp: Clamp::PerChannel },
+    ];
+    for policy in policies {
+        for range in [Range::Full, Range::Limited] {
+            let RGBConvert::Matrix(m8) = RGBConvert::<u8>::new(range, MatrixCoefficients::BT709).unwrap().with_policy(policy) else { panic!() };
+            let RGBConvert::Matrix(m16) = RGBConvert::<u16>::new(range, MatrixCoefficients::BT2020NCL, Depth::Depth10).unwrap().with_policy(policy) else { panic!() };
+
+            for seed in 0..50 {
+                let [y, u, v] = test_planes(|i| (i * 7919 + seed * 104_729) as u8);
+                let expected = core::array::from_fn::<_, 37, _>(|i| ToRGB::<u8, u8>::to_rgb(&m8, YUV { y: y[i], u: u[i], v: v[i] }));
+                for level in levels() {
+                    let mut out = [rgb::Rgb::new(0, 0, 0); 37];
+                    let done = matrix_row_with(level, &m8.simd_kernel(255.).unwrap(), &y, &u, &v, &mut out);
+                    assert_eq!(expected[..done], out[..done], "{level:?}");
+                }
+
+                let [y, u, v] = test_planes(|i| ((i * 7919 + seed * 104_729) % 1100) as u16);
+                let expected = core::array::from_fn::<_, 37, _>(|i| ToRGB::<u16, u16>::to_rgb(&m16, YUV { y: y[i], u: u[i], v: v[i] }));
+                let expected8 = core::array::from_fn::<_, 37, _>(|i| ToRGB::<u16, u8>::to_rgb(&m16, YUV { y: y[i], u: u[i], v: v[i] }));
+                for level in levels() {
+                    let mut out = [rgb::Rgb::new(0, 0, 0); 37];
+                    let done = matrix_row_with(level, &m16.simd_kernel(65535.).unwrap(), &y, &u, &v, &mut out);
+                    assert_eq!(expected[..done], out[..done], "{level:?}");
+                    let mut out = [rgb::Rgb::new(0, 0, 0); 37];
+                    let done = matrix_row_with(level, &m16.simd_kernel(255.).unwrap(), &y, &u, &v, &mut out);
+                    assert_eq!(expected8[..done], out[..done], "{level:?}");
+                }
+
+                let mut out = [rgb::Rgb::new(0, 0, 0); 37];
+                ToRGB::<u16, u16>::to_rgb_row(&m16, &y, &u, &v, &mut out);
+                assert_eq!(expected, out);
+            }
+        }
+    }
+}
+
+#[test]
+fn identity_and_copy_kernels_match_scalar() {
+    use crate::color::{Depth, MatrixCoefficients, Range};
+    use crate::convert::{Policy, RGBConvert, Rounding, ToRGB};
+    use crate::YUV;
+
+    for rounding in [Rounding::Truncate, Rounding::Nearest] {
+        let policy = Policy { rounding, ..Policy::default() };
+        let RGBConvert::IdentityScale(s8) = RGBConvert::<u8>::new(Range::Limited, MatrixCoefficients::Identity).unwrap().with_policy(policy) else { panic!() };
+        let [y, u, v] = test_planes(|i| (i * 7) as u8);
+        let expected = core::array::from_fn::<_, 37, _>(|i| s8.to_rgb(YUV { y: y[i], u: u[i], v: v[i] }));
+        for level in levels() {
+            let mut out = [rgb::Rgb::new(0, 0, 0); 37];
+            let done = identity_row_with(level, &s8.simd_kernel(255), &y, &u, &v, &mut out);
+            assert_eq!(expected[..done], out[..done], "{level:?}");
+        }
+
+        for (range, depth) in [(Range::Limited, Depth::Depth10), (Range::Full, Depth::Depth12), (Range::Limited, Depth::Depth16)] {
+            let RGBConvert::IdentityScale(s16) = RGBConvert::<u16>::new(range, MatrixCoefficients::Identity, depth).unwrap().with_policy(policy) else { panic!() };
+            let [y, u, v] = test_planes(|i| (i * 1877) as u16);
+            let expected = core::array::from_fn::<_, 37, _>(|i| s16.to_rgb(YUV { y: y[i], u: u[i], v: v[i] }));
+            for level in levels() {
+                let mut out = [rgb::Rgb::new(0, 0, 0); 37];
+                let done = identity_row_with(level, &s16.simd_kernel(65535), &y, &u, &v, &mut out);
+                assert_eq!(expected[..done], out[..done], "{level:?}");
+            }
+        }
+    }
+
+    let [y, u, v] = test_planes(|i| i as u8);
+    let [y16, u16, v16] = test_planes(|i| (i * 251) as u16);
+    for level in levels() {
+        let mut out = [rgb::Rgb::new(0, 0, 0); 37];
+        let done = copy_gbr_u8_with(level, &y, &u, &v, &mut out);
+        assert!(out[..done].iter().enumerate().all(|(i, px)| *px == rgb::Rgb::new(v[i], y[i], u[i])), "{level:?}");
+        let mut out = [rgb::Rgb::new(0, 0, 0); 37];
+        let done = copy_gbr_u16_with(level, &y16, &u16, &v16, &mut out);
+        assert!(out[..done].iter().enumerate().all(|(i, px)| *px == rgb::Rgb::new(v16[i], y16[i], u16[i])), "{level:?}");
+    }
+}
@@ -0,0 +1,403 @@
+use crate::color::{BitDepth, ChromaSampling, MatrixCoefficients, Range};
+use crate::convert::{coeffs_for_matrix, Rounding, Sample};
+use crate::dither::Dither;
+use crate::frame::YUVFrameRef;
+use crate::levels::Levels;
+use crate::primaries::{invert, mul, Mat3};
+use crate::Error;
+use crate::YUV;
+use core::marker::PhantomData;
+
+/// Converts YUV to YUV with a different matrix, range or bit depth. See [`YUVConvert::new`]
+///
+/// The inverse of the source matrix and the forward target matrix are combined into one transform,
+/// so colors aren't clamped or rounded to RGB in between.
+/// Colors outside of the RGB gamut are kept as long as they fit in the output values.
+#[derive(Debug, Copy, Clone)]
+pub struct YUVConvert<F = u8, T = u8> {
+    /// Output Y, U, V from input Y, U, V, plus an offset that includes rounding
+    m: [[f32; 4]; 3],
+    max: f32,
+    /// Input value of neutral chroma, used for monochrome frames
+    uv_center: f32,
+    _pixel: PhantomData<(F, T)>,
+}
+
+/// Mapping from code values to normalized components, and from them to RGB
+struct Format {
+    /// `[Y, U, V]` scale and offset: `n = code * mul - sub`
+    mul: [f64; 3],
+    sub: [f64; 3],
+    to_rgb: Mat3,
+}
+
+impl Format {
+    fn new(range: Range, matrix: MatrixCoefficients, depth: BitDepth, max_depth: u8) -> Result<Self, Error> {
+        if depth.bits() > max_depth {
+            return Err(Error::InvalidDepthRequested);
+        }
+        let levels = Levels::new(range, depth);
+        let [y_min, y_max, uv_min, uv_max] = [levels.y_min, levels.y_max, levels.uv_min, levels.uv_max].map(f64::from);
+        let y_mul = 1. / (y_max - y_min);
+        if matrix == MatrixCoefficients::Identity {
+            // G, B, R planes, all using the luma range
+            return Ok(Self {
+                mul: [y_mul; 3],
+                sub: [y_min * y_mul; 3],
+                to_rgb: [[0., 0., 1.], [1., 0., 0.], [0., 1., 0.]],
+            });
+        }
+        let (kr, kb) = coeffs_for_matrix(matrix).ok_or(Error::UnsupportedMatrixCoefficients)?;
+        let kg = 1. - kr - kb;
+        let uv_mul = 1. / (uv_max - uv_min);
+        let uv_center = f64::from(levels.uv_center());
+        Ok(Self {
+            mul: [y_mul, uv_mul, uv_mul],
+            sub: [y_min * y_mul, uv_center * uv_mul, uv_center * uv_mul],
+            to_rgb: [
+                [1., 0., 2. * (1. - kr)],
+                [1., -2. * (1. - kb) * kb / kg, -2. * (1. - kr) * kr / kg],
+                [1., 2. * (1. - kb), 0.],
+            ],
+        })
+    }
+}
+
+impl<F: Sample, T: Sample> YUVConvert<F, T> {
+    /// Arguments are `(range, matrix, depth)` of the input and the output.
+    ///
+    /// `u8` input or output must have 8 bits, and `u16` can have 8 to 16 bits (any [`BitDepth`]). [`MatrixCoefficients::Identity`] (GBR) is supported too.
+    pub fn new(from: (Range, MatrixCoefficients, impl Into<BitDepth>), to: (Range, MatrixCoefficients, impl Into<BitDepth>)) -> Result<Self, Error> {
+        let (from_depth, to_depth) = (from.2.into(), to.2.into());
+        let src = Format::new(from.0, from.1, from_depth, F::MAX_DEPTH)?;
+        let dst = Format::new(to.0, to.1, to_depth, T::MAX_DEPTH)?;
+
+        // normalized input -> RGB -> normalized output
+        let m = mul(&invert(&dst.to_rgb), &src.to_rgb);
+        let mut out = [[0.; 4]; 3];
+        for (i, out) in out.iter_mut().enumerate() {
+            let mut offset = dst.sub[i];
+            for j in 0..3 {
+                out[j] = (m[i][j] * src.mul[j] / dst.mul[i]) as f32;
+                offset -= m[i][j] * src.sub[j];
+            }
+            out[3] = (offset / dst.mul[i] + 0.5) as f32;
+        }
+        Ok(Self {
+            m: out,
+            max: to_depth.max_value().into(),
+            uv_center: (src.sub[1] / src.mul[1]) as f32,
+            _pixel: PhantomData,
+        })
+    }
+
+    /// Convert a single pixel
+    #[inline]
+    #[must_use]
+    pub fn convert(&self, px: YUV<F>) -> YUV<T> {
+        let [y, u, v] = [0, 1, 2].map(|i| self.component(i, [px.y.into(), px.u.into(), px.v.into()]));
+        YUV { y, u, v }
+    }
+
+    #[inline(always)]
+    fn component(&self, i: usize, [y, u, v]: [f32; 3]) -> T {
+        let [my, mu, mv, offset] = self.m[i];
+        T::from_code((y * my + u * mu + v * mv + offset).clamp(0., self.max) as i32)
+    }
+
+    /// Convert a frame to planes of the same size and chroma subsampling.
+    /// Output planes have no padding between rows, and chroma planes are sized as in [`YUVFrameRef::chroma_size`].
+    ///
+    /// Subsampled chroma is upsampled to compute luma, and is combined with the average luma of the pixels it covers.
+    pub fn convert_frame(&self, frame: &YUVFrameRef<'_, F>, y: &mut [T], u: &mut [T], v: &mut [T]) -> Result<(), Error> {
+        frame.check()?;
+        let (width, height) = (frame.width, frame.height);
+        let (chroma_width, chroma_height) = frame.chroma_size();
+        if y.len() < width * height || u.len() < chroma_width * chroma_height || v.len() < chroma_width * chroma_height {
+            return Err(Error::InvalidFrameSize);
+        }
+        if width == 0 {
+            return Ok(());
+        }
+
+        let mut u_buf = [0.; CHUNK];
+        let mut v_buf = [0.; CHUNK];
+        let mut u_up = [frame.y[0]; CHUNK];
+        let mut v_up = [frame.y[0]; CHUNK];
+        for (row, out) in y.chunks_exact_mut(width).take(height).enumerate() {
+            let y_row = &frame.y[row * frame.y_stride..][..width];
+            for (chunk, (y_row, out)) in y_row.chunks(CHUNK).zip(out.chunks_mut(CHUNK)).enumerate() {
+                let n = y_row.len();
+                let x = chunk * CHUNK;
+                match frame.sampling {
+                    ChromaSampling::Monochrome => {
+                        u_buf.fill(self.uv_center);
+                        v_buf.fill(self.uv_center);
+                    },
+                    ChromaSampling::Cs444 => {
+                        let start = row * frame.uv_stride + x;
+                        for i in 0..n {
+                            u_buf[i] = frame.u[start + i].into();
+                            v_buf[i] = frame.v[start + i].into();
+                        }
+                    },
+                    ChromaSampling::Cs422 | ChromaSampling::Cs420 => {
+                        frame.upsample_chroma(row, x, &mut u_up[..n], &mut v_up[..n]);
+                        for i in 0..n {
+                            u_buf[i] = u_up[i].into();
+                            v_buf[i] = v_up[i].into();
+                        }
+                    },
+                }
+                for (i, (out, &y)) in out.iter_mut().zip(y_row).enumerate() {
+                    *out = self.component(0, [y.into(), u_buf[i], v_buf[i]]);
+                }
+            }
+        }
+
+        if frame.sampling == ChromaSampling::Monochrome {
+            return Ok(());
+        }
+        // luma pixels covered by one chroma sample
+        let (block_width, block_height) = (width.div_ceil(chroma_width), height.div_ceil(chroma_height));
+        for cy in 0..chroma_height {
+            let rows = cy * block_height..((cy + 1) * block_height).min(height);
+            for cx in 0..chroma_width {
+                let cols = cx * block_width..((cx + 1) * block_width).min(width);
+                let mut sum = 0.;
+                for row in rows.clone() {
+                    sum += frame.y[row * frame.y_stride..][cols.clone()].iter().map(|&y| y.into()).sum::<f32>();
+                }
+                let luma = sum / (rows.len() * cols.len()) as f32;
+                let i = cy * frame.uv_stride + cx;
+                let px = [luma, frame.u[i].into(), frame.v[i].into()];
+                u[cy * chroma_width + cx] = self.component(1, px);
+                v[cy * chroma_width + cx] = self.component(2, px);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Scales YUV values to a different range or bit depth, without changing the matrix. See [`LevelConvert::new`]
+///
+/// Luma and chroma are scaled separately, and chroma stays centered.
+/// GBR ([`MatrixCoefficients::Identity`]) uses the luma range for all planes, so convert them all with [`LevelConvert::luma_row`].
+///
+/// It can also expand 8-bit values to 16 bits, e.g. 255 to 65535.
+#[derive(Debug, Copy, Clone)]
+pub struct LevelConvert<F = u8, T = u8> {
+    /// `[mul, add]` of luma and chroma
+    y: [f32; 2],
+    uv: [f32; 2],
+    max: f32,
+    rounding: Rounding,
+    dither: Dither,
+    _pixel: PhantomData<(F, T)>,
+}
+
+impl<F: Sample, T: Sample> LevelConvert<F, T> {
+    /// Arguments are `(range, depth)` of the input and the output. `u8` input or output must have 8 bits, and `u16` can have 8 to 16 bits (any [`BitDepth`]).
+    ///
+    /// Rounds to nearest by default.
+    pub fn new(from: (Range, impl Into<BitDepth>), to: (Range, impl Into<BitDepth>)) -> Result<Self, Error> {
+        let (from_depth, to_depth) = (from.1.into(), to.1.into());
+        if from_depth.bits() > F::MAX_DEPTH || to_depth.bits() > T::MAX_DEPTH {
+            return Err(Error::InvalidDepthRequested);
+        }
+        let (src, dst) = (Levels::new(from.0, from_depth), Levels::new(to.0, to_depth));
+        let y_mul = f64::from(dst.y_max - dst.y_min) / f64::from(src.y_max - src.y_min);
+        let uv_mul = f64::from(dst.uv_max - dst.uv_min) / f64::from(src.uv_max - src.uv_min);
+        Ok(Self {
+            y: [y_mul as f32, (f64::from(dst.y_min) - f64::from(src.y_min) * y_mul) as f32],
+            uv: [uv_mul as f32, (f64::from(dst.uv_center()) - f64::from(src.uv_center()) * uv_mul) as f32],
+            max: to_depth.max_value().into(),
+            rounding: Rounding::Nearest,
+            dither: Dither::None,
+            _pixel: PhantomData,
+        })
+    }
+
+    /// Set rounding used when there's no dithering
+    #[inline]
+    #[must_use]
+    pub fn with_rounding(mut self, rounding: Rounding) -> Self {
+        self.rounding = rounding;
+        self
+    }
+
+    /// Set dithering, useful when reducing bit depth. [`Dither::FloydSteinberg`] diffuses error only along rows, and rounds single pixels.
+    #[inline]
+    #[must_use]
+    pub fn with_dither(mut self, dither: Dither) -> Self {
+        self.dither = dither;
+        self
+    }
+
+    /// Convert a single pixel, without ordered dithering
+    #[inline]
+    #[must_use]
+    pub fn convert(&self, px: YUV<F>) -> YUV<T> {
+        let t = self.rounding_threshold();
+        YUV {
+            y: self.scale(self.y, px.y.into(), t),
+            u: self.scale(self.uv, px.u.into(), t),
+            v: self.scale(self.uv, px.v.into(), t),
+        }
+    }
+
+    /// Convert a single pixel at the given position, which is used for ordered dithering
+    #[inline]
+    #[must_use]
+    pub fn convert_at(&self, px: YUV<F>, x: usize, y: usize) -> YUV<T> {
+        let t = self.dither.threshold(x, y).unwrap_or_else(|| self.rounding_threshold());
+        YUV {
+            y: self.scale(self.y, px.y.into(), t),
+            u: self.scale(self.uv, px.u.into(), t),
+            v: self.scale(self.uv, px.v.into(), t),
+        }
+    }
+
+    /// Convert row number `row` of the luma plane
+    #[inline]
+    pub fn luma_row(&self, row: usize, input: &[F], out: &mut [T]) {
+        self.convert_row(self.y, row, input, out);
+    }
+
+    /// Convert row number `row` of the U or V plane
+    #[inline]
+    pub fn chroma_row(&self, row: usize, input: &[F], out: &mut [T]) {
+        self.convert_row(self.uv, row, input, out);
+    }
+
+    fn convert_row(&self, scale: [f32; 2], row: usize, input: &[F], out: &mut [T]) {
+        if self.dither == Dither::FloydSteinberg {
+            let mut error = 0.;
+            for (out, &v) in out.iter_mut().zip(input) {
+                let v = v.into() * scale[0] + scale[1] + error;
+                let rounded = (v + 0.5).floor();
+                error = v - rounded;
+                *out = T::from_code(rounded.clamp(0., self.max) as i32);
+            }
+            return;
+        }
+        let rounding = self.rounding_threshold();
+        for (x, (out, &v)) in out.iter_mut().zip(input).enumerate() {
+            *out = self.scale(scale, v.into(), self.dither.threshold(x, row).unwrap_or(rounding));
+        }
+    }
+
+    #[inline(always)]
+    fn rounding_threshold(&self) -> f32 {
+        match self.rounding {
+            Rounding::Truncate => 0.,
+            Rounding::Nearest => 0.5,
+        }
+    }
+
+    #[inline(always)]
+    fn scale(&self, [mul, add]: [f32; 2], v: f32, threshold: f32) -> T {
+        T::from_code((v * mul + add + threshold).clamp(0., self.max) as i32)
+    }
+}
+
+/// Pixels converted at a time, into buffers on the stack
+const CHUNK: usize = 128;
+
+#[test]
+fn transcode_pixels() {
+    use crate::color::Depth;
+    use crate::convert::RGBConvert;
+
+    let to709 = YUVConvert::<u8, u8>::new((Range::Limited, MatrixCoefficients::BT601, Depth::Depth8), (Range::Limited, MatrixCoefficients::BT709, Depth::Depth8)).unwrap();
+    let to601 = YUVConvert::<u8, u8>::new((Range::Limited, MatrixCoefficients::BT709, Depth::Depth8), (Range::Limited, MatrixCoefficients::BT601, Depth::Depth8)).unwrap();
+    let rgb601 = RGBConvert::<u8>::new(Range::Limited, MatrixCoefficients::BT601).unwrap();
+    let rgb709 = RGBConvert::<u8>::new(Range::Limited, MatrixCoefficients::BT709).unwrap();
+    // colors within the RGB gamut, so that RGB isn't clipped
+    for y in (70..=180).step_by(5) {
+        for u in (108..=148).step_by(4) {
+            for v in (108..=148).step_by(4) {
+                let px = YUV { y, u, v };
+                let out = to709.convert(px);
+                let (a, b) = (rgb601.to_rgb(px), rgb709.to_rgb(out));
+                assert!(a.r.abs_diff(b.r) <= 2 && a.g.abs_diff(b.g) <= 2 && a.b.abs_diff(b.b) <= 2, "{px:?} {out:?} {a:?} {b:?}");
+                let back = to601.convert(out);
+                assert!(back.y.abs_diff(y) <= 1 && back.u.abs_diff(u) <= 1 && back.v.abs_diff(v) <= 1, "{px:?} {back:?}");
+            }
+        }
+    }
+    assert_eq!(YUV { y: 126, u: 128, v: 128 }, to709.convert(YUV { y: 126, u: 128, v: 128 }));
+
+    let to10 = YUVConvert::<u8, u16>::new((Range::Limited, MatrixCoefficients::BT709, Depth::Depth8), (Range::Full, MatrixCoefficients::BT709, Depth::Depth10)).unwrap();
+    assert_eq!(YUV { y: 0, u: 512, v: 512 }, to10.convert(YUV { y: 16, u: 128, v: 128 }));
+    // full range chroma is centered at 512, so -0.5 is 0.5
+    assert_eq!(YUV { y: 1023, u: 1, v: 1023 }, to10.convert(YUV { y: 235, u: 16, v: 240 }));
+
+    let gbr = YUVConvert::<u8, u8>::new((Range::Full, MatrixCoefficients::BT601, Depth::Depth8), (Range::Full, MatrixCoefficients::Identity, Depth::Depth8)).unwrap();
+    let rgb = RGBConvert::<u8>::new(Range::Full, MatrixCoefficients::BT601).unwrap().to_rgb(YUV { y: 100, u: 90, v: 200 });
+    let out = gbr.convert(YUV { y: 100, u: 90, v: 200 });
+    assert!(out.y.abs_diff(rgb.g) <= 1 && out.u.abs_diff(rgb.b) <= 1 && out.v.abs_diff(rgb.r) <= 1);
+
+    assert!(YUVConvert::<u8, u8>::new((Range::Full, MatrixCoefficients::BT601, Depth::Depth10), (Range::Full, MatrixCoefficients::BT709, Depth::Depth8)).is_err());
+}
+
+#[test]
+fn transcode_frame() {
+    use crate::color::Depth;
+
+    let conv = YUVConvert::<u8, u8>::new((Range::Full, MatrixCoefficients::BT601, Depth::Depth8), (Range::Full, MatrixCoefficients::BT709, Depth::Depth8)).unwrap();
+    let y = [10, 20, 30, 40, 50, 60];
+    let u = [100, 150];
+    let v = [200, 50];
+    let frame = YUVFrameRef::new(3, 2, &y, &u, &v, ChromaSampling::Cs420).unwrap();
+    let (mut y_out, mut u_out, mut v_out) = ([0; 6], [0; 2], [0; 2]);
+    conv.convert_frame(&frame, &mut y_out, &mut u_out, &mut v_out).unwrap();
+
+    // the first chroma sample covers luma 10, 20, 40, 50
+    let expected = conv.convert(YUV { y: 30, u: 100, v: 200 });
+    assert_eq!([expected.u, expected.v], [u_out[0], v_out[0]]);
+    let expected = conv.convert(YUV { y: 45, u: 150, v: 50 });
+    assert_eq!([expected.u, expected.v], [u_out[1], v_out[1]]);
+
+    assert!(conv.convert_frame(&frame, &mut y_out[..5], &mut u_out, &mut v_out).is_err());
+}
+
+#[test]
+fn levels() {
+    use crate::color::Depth;
+
+    let expand = LevelConvert::<u8, u16>::new((Range::Full, Depth::Depth8), (Range::Full, Depth::Depth16)).unwrap();
+    for y in 0..=255 {
+        assert_eq!(u16::from(y) * 257, expand.convert(YUV { y, u: 128, v: 128 }).y);
+    }
+    // chroma stays centered
+    assert_eq!(YUV { y: 0, u: 32768, v: 65407 }, expand.convert(YUV { y: 0, u: 128, v: 255 }));
+
+    let to_full = LevelConvert::<u16, u8>::new((Range::Limited, Depth::Depth10), (Range::Full, Depth::Depth8)).unwrap();
+    // full range chroma is centered at 128, so -0.5 is 0.5
+    assert_eq!(YUV { y: 0, u: 1, v: 255 }, to_full.convert(YUV { y: 64, u: 64, v: 960 }));
+    assert_eq!(YUV { y: 255, u: 128, v: 128 }, to_full.convert(YUV { y: 940, u: 512, v: 512 }));
+    assert_eq!(YUV { y: 0, u: 0, v: 255 }, to_full.convert(YUV { y: 0, u: 0, v: 1023 }));
+
+    let to_limited = LevelConvert::<u8, u8>::new((Range::Full, Depth::Depth8), (Range::Limited, Depth::Depth8)).unwrap();
+    assert_eq!(YUV { y: 16, u: 16, v: 240 }, to_limited.convert(YUV { y: 0, u: 0, v: 255 }));
+    assert_eq!(YUV { y: 235, u: 128, v: 128 }, to_limited.convert(YUV { y: 255, u: 128, v: 128 }));
+
+    // 10-bit gradient keeps its average brightness when dithered to 8 bits
+    let to8 = LevelConvert::<u16, u8>::new((Range::Full, Depth::Depth10), (Range::Full, Depth::Depth8)).unwrap();
+    let input = [514u16; 64];
+    let mut out = [0u8; 64];
+    for dither in [Dither::Bayer, Dither::BlueNoise, Dither::FloydSteinberg] {
+        let to8 = to8.with_dither(dither);
+        let mut sum = 0;
+        for row in 0..8 {
+            to8.luma_row(row, &input, &mut out);
+            sum += out.iter().map(|&v| u32::from(v)).sum::<u32>();
+        }
+        let avg = sum as f32 / 512.;
+        assert!((avg - 514. * 255. / 1023.).abs() < 0.1, "{dither:?} {avg}");
+    }
+    assert_eq!(128, to8.with_rounding(Rounding::Truncate).convert(YUV { y: 514, u: 512, v: 512 }).y);
+
+    assert!(LevelConvert::<u8, u8>::new((Range::Full, Depth::Depth10), (Range::Full, Depth::Depth8)).is_err());
+}
@@ -0,0 +1,108 @@
+//! Transfer functions ("gamma") defined by ISO/IEC 23091-4/ITU-T H.273.
+//!
+//! Signal and linear values are normalized to `0..=1`. For PQ, linear 1.0 is 10000 cd/m²,
+//! and for other functions it's the nominal peak. Scene-referred functions (like BT.709 and HLG)
+//! are inverted without applying any OOTF.
+
+use crate::color::TransferCharacteristics;
+
+pub(crate) const BT709_ALPHA: f64 = 1.099_296_826_809_44;
+pub(crate) const BT709_BETA: f64 = 0.018_053_968_510_807;
+
+pub(crate) const PQ_M1: f64 = 2610. / 16384.;
+pub(crate) const PQ_M2: f64 = 2523. / 4096. * 128.;
+pub(crate) const PQ_C1: f64 = 3424. / 4096.;
+pub(crate) const PQ_C2: f64 = 2413. / 4096. * 32.;
+pub(crate) const PQ_C3: f64 = 2392. / 4096. * 32.;
+
+pub(crate) const HLG_A: f64 = 0.178_832_77;
+pub(crate) const HLG_B: f64 = 0.284_668_92;
+pub(crate) const HLG_C: f64 = 0.559_910_73;
+
+/// Decode a signal value to linear light
+#[must_use]
+#[allow(deprecated)]
+pub fn to_linear(tc: TransferCharacteristics, v: f64) -> f64 {
+    use TransferCharacteristics as TC;
+    match tc {
+        TC::BT709 | TC::BT601 | TC::BT2020_10Bit | TC::BT2020_12Bit | TC::BT1361 => gamma_to_linear(v.max(0.), BT709_ALPHA, BT709_BETA, 4.5),
+        TC::SMPTE240 => gamma_to_linear(v.max(0.), 1.1115, 0.0228, 4.),
+        TC::IEC61966 => v.signum() * gamma_to_linear(v.abs(), BT709_ALPHA, BT709_BETA, 4.5),
+        TC::BT470M => v.max(0.).powf(2.2),
+        TC::BT470BG => v.max(0.).powf(2.8),
+        TC::Linear => v,
+        TC::Log100 => if v > 0. { 10_f64.powf(2. * (v - 1.)) } else { 0. },
+        TC::Log100Sqrt10 => if v > 0. { 10_f64.powf(2.5 * (v - 1.)) } else { 0. },
+        TC::SRGB => {
+            let v = v.max(0.);
+            if v <= 0.04045 { v / 12.92 } else { ((v + 0.055) / 1.055).powf(2.4) }
+        },
+        TC::SMPTE2084 => {
+            let p = v.max(0.).powf(1. / PQ_M2);
+            ((p - PQ_C1).max(0.) / (PQ_C2 - PQ_C3 * p)).powf(1. / PQ_M1)
+        },
+        TC::SMPTE428 => v.max(0.).powf(2.6) * 52.37 / 48.,
+        TC::HLG => {
+            let v = v.max(0.);
+            if v <= 0.5 { v * v / 3. } else { (((v - HLG_C) / HLG_A).exp() + HLG_B) / 12. }
+        },
+    }
+}
+
+/// Encode linear light to a signal value
+#[must_use]
+#[allow(deprecated)]
+pub fn from_linear(tc: TransferCharacteristics, l: f64) -> f64 {
+    use TransferCharacteristics as TC;
+    match tc {
+        TC::BT709 | TC::BT601 | TC::BT2020_10Bit | TC::BT2020_12Bit | TC::BT1361 => gamma_from_linear(l.max(0.), BT709_ALPHA, BT709_BETA, 4.5),
+        TC::SMPTE240 => gamma_from_linear(l.max(0.), 1.1115, 0.0228, 4.),
+        TC::IEC61966 => l.signum() * gamma_from_linear(l.abs(), BT709_ALPHA, BT709_BETA, 4.5),
+        TC::BT470M => l.max(0.).powf(1. / 2.2),
+        TC::BT470BG => l.max(0.).powf(1. / 2.8),
+        TC::Linear => l,
+        TC::Log100 => if l >= 0.01 { 1. + l.log10() / 2. } else { 0. },
+        TC::Log100Sqrt10 => if l >= 0.003_162_277_66 { 1. + l.log10() / 2.5 } else { 0. },
+        TC::SRGB => {
+            let l = l.max(0.);
+            if l <= 0.003_130_8 { l * 12.92 } else { 1.055 * l.powf(1. / 2.4) - 0.055 }
+        },
+        TC::SMPTE2084 => {
+            let p = l.max(0.).powf(PQ_M1);
+            ((PQ_C1 + PQ_C2 * p) / (1. + PQ_C3 * p)).powf(PQ_M2)
+        },
+        TC::SMPTE428 => (l.max(0.) * 48. / 52.37).powf(1. / 2.6),
+        TC::HLG => {
+            let l = l.max(0.);
+            if l <= 1. / 12. { (3. * l).sqrt() } else { HLG_A * (12. * l - HLG_B).ln() + HLG_C }
+        },
+    }
+}
+
+/// BT.709-style curve: linear segment near black, and a power of 0.45 above it
+#[inline]
+fn gamma_to_linear(v: f64, alpha: f64, beta: f64, slope: f64) -> f64 {
+    if v < beta * slope { v / slope } else { ((v + alpha - 1.) / alpha).powf(1. / 0.45) }
+}
+
+#[inline]
+fn gamma_from_linear(l: f64, alpha: f64, beta: f64, slope: f64) -> f64 {
+    if l < beta { l * slope } else { alpha * l.powf(0.45) - (alpha - 1.) }
+}
+
+#[test]
+#[allow(deprecated)]
+fn round_trip() {
+    use TransferCharacteristics as TC;
+    for tc in [TC::BT709, TC::BT470M, TC::BT470BG, TC::BT601, TC::SMPTE240, TC::Linear, TC::Log100, TC::Log100Sqrt10, TC::IEC61966,
+        TC::BT1361, TC::SRGB, TC::BT2020_10Bit, TC::BT2020_12Bit, TC::SMPTE2084, TC::SMPTE428, TC::HLG] {
+        for i in 1..=100 {
+            let v = f64::from(i) / 100.;
+            let l = to_linear(tc, v);
+            assert!((from_linear(tc, l) - v).abs() < 1e-6, "{tc:?} {v} {l}");
+        }
+        assert!((to_linear(tc, 1.) - if tc == TC::SMPTE428 { 52.37 / 48. } else { 1. }).abs() < 1e-4, "{tc:?}");
+    }
+    assert!((to_linear(TC::SMPTE2084, 0.508) * 10000. - 100.).abs() < 0.5);
+    assert!((to_linear(TC::SRGB, 0.5) - 0.214_041).abs() < 1e-6);
+}
@@ -0,0 +1,394 @@
+//! YUV4MPEG2 (`.y4m`) files, as used by FFmpeg, x264, aomenc, rav1e, etc.
+//!
+//! Frames are read into [`YUVFrame`], and written from [`YUVFrameRef`]. 8-bit files use `u8` samples, and higher bit depths (9 to 16 bits) use `u16`.
+//!
+//! ```
+//! # use yuv::color::*;
+//! # use yuv::y4m::*;
+//! let header = Y4mHeader::new(2, 2, ChromaSampling::Cs420, Depth::Depth8);
+//! let mut w = Y4mWriter::new(Vec::new(), header)?;
+//! let frame = yuv::frame::YUVFrameRef::new(2, 2, &[16u8; 4], &[128], &[128], ChromaSampling::Cs420)?;
+//! w.write_frame(&frame)?;
+//!
+//! let file = w.into_inner();
+//! let mut r = Y4mReader::new(&file[..])?;
+//! let frame = r.read_frame::<u8>()?.unwrap();
+//! assert_eq!(frame.y, [16; 4]);
+//! # Ok::<_, Box<dyn std::error::Error>>(())
+//! ```
+
+use crate::color::{BitDepth, ChromaSamplePosition, ChromaSampling, Depth, Range};
+use crate::convert::Sample;
+use crate::frame::{chroma_size, YUVFrame, YUVFrameRef};
+use crate::Error;
+use std::io::{self, BufRead, Read, Write};
+
+/// Longest header line accepted
+const MAX_LINE: u64 = 4096;
+
+/// Stream parameters from the file header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Y4mHeader {
+    pub width: usize,
+    pub height: usize,
+    /// Frames per second, as numerator and denominator
+    pub framerate: (u32, u32),
+    pub sampling: ChromaSampling,
+    /// From `C420jpeg` (`None`, centered), `C420mpeg2` (left) and `C420paldv` (top-left).
+    /// Only 8-bit 4:2:0 can store it, and other formats are assumed to be centered.
+    pub chroma_position: Option<ChromaSamplePosition>,
+    /// From 8 to 16 bits, e.g. `C420p9` or `C444p14` from FFmpeg
+    pub depth: BitDepth,
+    /// From FFmpeg's `XCOLORRANGE` extension
+    pub range: Option<Range>,
+    /// Other parameters, like interlacing (`I`), pixel aspect ratio (`A`) or comments (`X`), kept verbatim
+    pub other: Vec<String>,
+}
+
+impl Y4mHeader {
+    /// 25 frames per second, and centered chroma
+    #[must_use]
+    pub fn new(width: usize, height: usize, sampling: ChromaSampling, depth: impl Into<BitDepth>) -> Self {
+        Self { width, height, framerate: (25, 1), sampling, chroma_position: None, depth: depth.into(), range: None, other: Vec::new() }
+    }
+
+    fn parse(line: &str) -> Option<Self> {
+        let params = line.strip_prefix("YUV4MPEG2")?.split_ascii_whitespace();
+        let (mut width, mut height, mut framerate) = (None, None, None);
+        let mut header = Self::new(0, 0, ChromaSampling::Cs420, Depth::Depth8);
+        for param in params {
+            let (key, value) = param.split_at_checked(1)?;
+            match key {
+                "W" => width = Some(value.parse().ok()?),
+                "H" => height = Some(value.parse().ok()?),
+                "F" => {
+                    let (num, den) = value.split_once(':')?;
+                    framerate = Some((num.parse().ok()?, den.parse().ok()?));
+                },
+                "C" => (header.sampling, header.chroma_position, header.depth) = parse_colorspace(value)?,
+                "X" if value.starts_with("COLORRANGE=") => header.range = match &value[11..] {
+                    "FULL" => Some(Range::Full),
+                    "LIMITED" => Some(Range::Limited),
+                    _ => None,
+                },
+                _ => header.other.push(param.into()),
+            }
+        }
+        header.width = width?;
+        header.height = height?;
+        header.framerate = framerate?;
+        Some(header)
+    }
+
+    fn write(&self, mut w: impl Write) -> io::Result<()> {
+        write!(w, "YUV4MPEG2 W{} H{} F{}:{} C{}", self.width, self.height, self.framerate.0, self.framerate.1, self.colorspace())?;
+        for param in &self.other {
+            write!(w, " {param}")?;
+        }
+        match self.range {
+            Some(Range::Full) => w.write_all(b" XCOLORRANGE=FULL")?,
+            Some(Range::Limited) => w.write_all(b" XCOLORRANGE=LIMITED")?,
+            None => {},
+        }
+        w.write_all(b"\n")
+    }
+
+    fn colorspace(&self) -> String {
+        let sampling = match self.sampling {
+            ChromaSampling::Cs420 => "420",
+            ChromaSampling::Cs422 => "422",
+            ChromaSampling::Cs444 => "444",
+            ChromaSampling::Monochrome => "mono",
+        };
+        match (self.depth.bits(), self.sampling) {
+            (8, ChromaSampling::Cs420) => match self.chroma_position {
+                None => "420jpeg",
+                Some(ChromaSamplePosition::Vertical) => "420mpeg2",
+                Some(ChromaSamplePosition::Colocated) => "420paldv",
+            }.into(),
+            (8, _) => sampling.into(),
+            (bits, ChromaSampling::Monochrome) => format!("mono{bits}"),
+            (bits, _) => format!("{sampling}p{bits}"),
+        }
+    }
+
+    /// Number of samples in the Y plane, and in each chroma plane
+    fn plane_sizes(&self) -> Option<(usize, usize)> {
+        let (cw, ch) = chroma_size(self.width, self.height, self.sampling);
+        Some((self.width.checked_mul(self.height)?, cw.checked_mul(ch)?))
+    }
+
+    /// Bytes of samples in a frame
+    fn frame_len(&self) -> Option<usize> {
+        let (y_len, uv_len) = self.plane_sizes()?;
+        uv_len.checked_mul(2)?.checked_add(y_len)?.checked_mul(if self.depth.bits() > 8 { 2 } else { 1 })
+    }
+
+    fn check_sample<T: Sample>(&self) -> io::Result<()> {
+        let expected = if self.depth.bits() > 8 { 16 } else { 8 };
+        if T::MAX_DEPTH != expected {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, Error::InvalidDepthRequested));
+        }
+        Ok(())
+    }
+}
+
+fn parse_colorspace(c: &str) -> Option<(ChromaSampling, Option<ChromaSamplePosition>, BitDepth)> {
+    let depth8 = BitDepth::from(Depth::Depth8);
+    Some(match c {
+        "420jpeg" | "420" => (ChromaSampling::Cs420, None, depth8),
+        "420mpeg2" => (ChromaSampling::Cs420, Some(ChromaSamplePosition::Vertical), depth8),
+        "420paldv" => (ChromaSampling::Cs420, Some(ChromaSamplePosition::Colocated), depth8),
+        "422" => (ChromaSampling::Cs422, None, depth8),
+        "444" => (ChromaSampling::Cs444, None, depth8),
+        "mono" => (ChromaSampling::Monochrome, None, depth8),
+        _ => {
+            let (sampling, bits) = if let Some(bits) = c.strip_prefix("mono") {
+                (ChromaSampling::Monochrome, bits)
+            } else {
+                let (sampling, bits) = c.split_once('p')?;
+                (match sampling {
+                    "420" => ChromaSampling::Cs420,
+                    "422" => ChromaSampling::Cs422,
+                    "444" => ChromaSampling::Cs444,
+                    _ => return None,
+                }, bits)
+            };
+            if !bits.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            let depth = BitDepth::new(bits.parse().ok()?).ok().filter(|&d| d > depth8)?;
+            (sampling, None, depth)
+        },
+    })
+}
+
+fn invalid_header() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, Error::InvalidHeader)
+}
+
+/// Reads exactly `len` bytes into the `buf`. It grows only as the data arrives, so sizes from a corrupt header fail with `UnexpectedEof` instead of allocating too much.
+pub(crate) fn read_exact_vec(reader: &mut impl Read, buf: &mut Vec<u8>, len: usize) -> io::Result<()> {
+    buf.clear();
+    if reader.take(len as u64).read_to_end(buf)? != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(())
+}
+
+/// Reads one line, without the `\n`. `None` at the end of the file.
+fn read_line(r: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut line = Vec::new();
+    r.by_ref().take(MAX_LINE).read_until(b'\n', &mut line)?;
+    if line.is_empty() {
+        return Ok(None);
+    }
+    if line.pop() != Some(b'\n') {
+        return Err(invalid_header());
+    }
+    String::from_utf8(line).map(Some).map_err(|_| invalid_header())
+}
+
+/// Streaming reader of `.y4m` files
+#[derive(Debug)]
+pub struct Y4mReader<R> {
+    reader: R,
+    header: Y4mHeader,
+    buf: Vec<u8>,
+}
+
+impl<R: BufRead> Y4mReader<R> {
+    /// Reads the file header
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let line = read_line(&mut reader)?.ok_or_else(invalid_header)?;
+        let header = Y4mHeader::parse(&line).ok_or_else(invalid_header)?;
+        header.frame_len().ok_or_else(invalid_header)?;
+        Ok(Self { reader, header, buf: Vec::new() })
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn header(&self) -> &Y4mHeader {
+        &self.header
+    }
+
+    /// Next frame, or `None` at the end of the file. `T` must be `u8` for 8-bit files, and `u16` otherwise.
+    pub fn read_frame<T: Sample>(&mut self) -> io::Result<Option<YUVFrame<T>>> {
+        self.header.check_sample::<T>()?;
+        let Some(line) = read_line(&mut self.reader)? else {
+            return Ok(None);
+        };
+        if line.split_ascii_whitespace().next() != Some("FRAME") {
+            return Err(invalid_header());
+        }
+        let (y_len, uv_len) = self.header.plane_sizes().ok_or_else(invalid_header)?;
+        Ok(Some(YUVFrame {
+            width: self.header.width,
+            height: self.header.height,
+            y: self.read_plane(y_len)?,
+            u: self.read_plane(uv_len)?,
+            v: self.read_plane(uv_len)?,
+            sampling: self.header.sampling,
+            chroma_position: self.header.chroma_position,
+        }))
+    }
+
+    fn read_plane<T: Sample>(&mut self, len: usize) -> io::Result<Vec<T>> {
+        let bytes = if T::MAX_DEPTH > 8 { 2 } else { 1 };
+        let len = len.checked_mul(bytes).ok_or_else(invalid_header)?;
+        read_exact_vec(&mut self.reader, &mut self.buf, len)?;
+        Ok(if bytes == 2 {
+            self.buf.chunks_exact(2).map(|b| T::from_code(u16::from_le_bytes([b[0], b[1]]).into())).collect()
+        } else {
+            self.buf.iter().map(|&b| T::from_code(b.into())).collect()
+        })
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: BufRead> Iterator for Y4mReader<R> {
+    type Item = io::Result<YUVFrame<u16>>;
+
+    /// Frames of any depth as `u16`. For 8-bit files, use [`Y4mReader::read_frame`] with `u8` instead.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.header.depth.bits() == 8 {
+            return match self.read_frame::<u8>() {
+                Ok(frame) => frame.map(|f| Ok(f.into())),
+                Err(e) => Some(Err(e)),
+            };
+        }
+        self.read_frame::<u16>().transpose()
+    }
+}
+
+/// Streaming writer of `.y4m` files
+#[derive(Debug)]
+pub struct Y4mWriter<W> {
+    writer: W,
+    header: Y4mHeader,
+}
+
+impl<W: Write> Y4mWriter<W> {
+    /// Writes the file header
+    pub fn new(mut writer: W, header: Y4mHeader) -> io::Result<Self> {
+        header.frame_len().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, Error::InvalidFrameSize))?;
+        header.write(&mut writer)?;
+        Ok(Self { writer, header })
+    }
+
+    /// Frame must have the size and chroma sampling of the header. `T` must be `u8` for 8-bit files, and `u16` otherwise.
+    pub fn write_frame<T: Sample>(&mut self, frame: &YUVFrameRef<'_, T>) -> io::Result<()> {
+        self.header.check_sample::<T>()?;
+        if frame.width != self.header.width || frame.height != self.header.height || frame.sampling != self.header.sampling {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, Error::InvalidFrameSize));
+        }
+        frame.check().map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+        self.writer.write_all(b"FRAME\n")?;
+        let (cw, ch) = frame.chroma_size();
+        let mut buf = Vec::new();
+        for (plane, stride, width, height) in [(frame.y, frame.y_stride, frame.width, frame.height), (frame.u, frame.uv_stride, cw, ch), (frame.v, frame.uv_stride, cw, ch)] {
+            for row in plane.chunks(stride.max(1)).take(height) {
+                let row = &row[..width];
+                if let Some(bytes) = T::as_u8(row) {
+                    self.writer.write_all(bytes)?;
+                } else if let Some(row) = T::as_u16(row) {
+                    buf.clear();
+                    buf.extend(row.iter().flat_map(|v| v.to_le_bytes()));
+                    self.writer.write_all(&buf)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[test]
+fn y4m_round_trip() {
+    let header = Y4mHeader {
+        range: Some(Range::Full),
+        chroma_position: Some(ChromaSamplePosition::Vertical),
+        other: vec!["Ip".into(), "A1:1".into()],
+        ..Y4mHeader::new(3, 3, ChromaSampling::Cs420, Depth::Depth8)
+    };
+    let y: Vec<u8> = (0..9).collect();
+    let mut w = Y4mWriter::new(Vec::new(), header.clone()).unwrap();
+    for i in 0..2 {
+        w.write_frame(&YUVFrameRef::new(3, 3, &y, &[i; 4], &[2; 4], ChromaSampling::Cs420).unwrap()).unwrap();
+    }
+    let file = w.into_inner();
+    assert!(file.starts_with(b"YUV4MPEG2 W3 H3 F25:1 C420mpeg2 Ip A1:1 XCOLORRANGE=FULL\nFRAME\n"));
+
+    let mut r = Y4mReader::new(&file[..]).unwrap();
+    assert_eq!(&header, r.header());
+    assert!(r.read_frame::<u16>().is_err());
+    for i in 0..2 {
+        let frame = r.read_frame::<u8>().unwrap().unwrap();
+        assert_eq!(frame.y, y);
+        assert_eq!(frame.u, [i; 4]);
+        assert_eq!(frame.chroma_position, Some(ChromaSamplePosition::Vertical));
+    }
+    assert!(r.read_frame::<u8>().unwrap().is_none());
+}
+
+#[test]
+fn y4m_high_depth() {
+    let file = b"YUV4MPEG2 W2 H1 F30000:1001 C422p10 Ip A1:1 XYSCSS=422P10\nFRAME\n\x00\x01\xff\x03\x00\x02\x01\x02";
+    let mut r = Y4mReader::new(&file[..]).unwrap();
+    let h = r.header().clone();
+    assert_eq!((h.width, h.height, h.framerate, h.sampling, h.depth, h.range), (2, 1, (30000, 1001), ChromaSampling::Cs422, Depth::Depth10.into(), None));
+    let frame = r.next().unwrap().unwrap();
+    assert_eq!((frame.y.as_slice(), frame.u.as_slice(), frame.v.as_slice()), (&[256, 1023][..], &[512][..], &[513][..]));
+    assert!(r.next().is_none());
+
+    let mut w = Y4mWriter::new(Vec::new(), h).unwrap();
+    w.write_frame(&frame.as_frame_ref()).unwrap();
+    assert_eq!(&w.into_inner()[..], &file[..]);
+
+    // truncated frame
+    assert!(Y4mReader::new(&file[..file.len() - 1]).unwrap().read_frame::<u16>().is_err());
+    assert!(Y4mReader::new(&b"YUV4MPEG2 W2 H1 F1:1 C420p17\n"[..]).is_err());
+    assert!(Y4mReader::new(&b"YUV4MPEG2 W2 H1 F1:1 C420p8\n"[..]).is_err());
+}
+
+#[test]
+fn y4m_any_depth() {
+    for (c, bits, sampling) in [("C420p9", 9, ChromaSampling::Cs420), ("C422p14", 14, ChromaSampling::Cs422), ("C444p11", 11, ChromaSampling::Cs444), ("Cmono9", 9, ChromaSampling::Monochrome)] {
+        let header = Y4mHeader::new(2, 2, sampling, BitDepth::new(bits).unwrap());
+        let y = [0, 1, 256, (1 << bits) - 1];
+        let (cw, ch) = chroma_size(2, 2, sampling);
+        let uv = vec![1 << (bits - 1); cw * ch];
+        let mut w = Y4mWriter::new(Vec::new(), header.clone()).unwrap();
+        w.write_frame(&YUVFrameRef::new(2, 2, &y, &uv, &uv, sampling).unwrap()).unwrap();
+        let file = w.into_inner();
+        assert!(file.starts_with(format!("YUV4MPEG2 W2 H2 F25:1 {c}\nFRAME\n").as_bytes()));
+
+        let mut r = Y4mReader::new(&file[..]).unwrap();
+        assert_eq!(&header, r.header());
+        assert!(r.read_frame::<u8>().is_err());
+        let frame = r.read_frame::<u16>().unwrap().unwrap();
+        assert_eq!((frame.y.as_slice(), &frame.u, &frame.v), (&y[..], &uv, &uv));
+    }
+}
+
+#[test]
+fn y4m_oversized_header() {
+    let file = b"YUV4MPEG2 W1000000 H1000000 F1:1 C420\nFRAME\n\x10\x10";
+    let mut r = Y4mReader::new(&file[..]).unwrap();
+    assert_eq!(io::ErrorKind::UnexpectedEof, r.read_frame::<u8>().unwrap_err().kind());
+
+    // 2^63 samples fit in `usize` on 64-bit, but their bytes don't
+    let file = b"YUV4MPEG2 W4294967296 H2147483648 F1:1 C444p10\nFRAME\n";
+    let err = Y4mReader::new(&file[..]).unwrap_err();
+    assert_eq!(io::ErrorKind::InvalidData, err.kind());
+    assert!(matches!(err.get_ref().and_then(|e| e.downcast_ref()), Some(Error::InvalidHeader)));
+}